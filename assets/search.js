@@ -36,20 +36,23 @@ function renderResults(items) {
 
   items.forEach((item) => {
     const clone = resultTemplate.content.cloneNode(true);
-    clone.querySelector("a").href = item.uri;
+    // Only web links, a javascript: uri would run on click
+    clone.querySelector("a").href = /^(https?|mailto):/i.test(item.uri.trim()) ? item.uri : "#";
     clone.querySelector("h3").textContent = item.title;
     clone.querySelector("p").textContent = item.excerpt || "No excerpt available";
     clone.querySelector(".time-added").textContent = new Date(item.time_added).toLocaleDateString();
 
     const domain = item.uri.split('/')[2];
     clone.querySelector(".domain").textContent = domain || item.uri.slice(0, 10);
+    clone.querySelector(".details").href = `./items/${item.id}.html`;
     
     const tagsContainer = clone.querySelector(".tags-container");
     item.tags.forEach((tag) => {
-      const span = document.createElement("span");
-      span.className = "text-xs px-2 py-0.5 text-gray-500";
-      span.textContent = tag;
-      tagsContainer.appendChild(span);
+      const link = document.createElement("a");
+      link.className = "text-xs px-2 py-0.5 text-gray-500 hover:text-blue-600";
+      link.href = `./tags/${tag_slugs[tag]}.html`;
+      link.textContent = tag;
+      tagsContainer.appendChild(link);
     });
 
     fragment.appendChild(clone);
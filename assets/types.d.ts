@@ -7,7 +7,11 @@ interface item_tag {
   time_added: number;
   favorite: boolean;
   lang: string;
+  notes: string | null;
+  status: "normal" | "archived" | "deleted";
+  time_read: number | null;
 }
 
 
 declare let item_tags: item_tag[];
+declare let tag_slugs: Record<string, string>;
@@ -0,0 +1,5 @@
+DROP TABLE if exists secrets;
+DROP TABLE if exists item_tags;
+DROP TABLE if exists tags;
+DROP TABLE if exists items;
+DROP TABLE if exists providers;
@@ -0,0 +1 @@
+DELETE FROM providers WHERE name IN ('pocket', 'local');
@@ -0,0 +1 @@
+DELETE FROM secrets WHERE user_id = 0;
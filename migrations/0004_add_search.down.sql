@@ -0,0 +1,7 @@
+DROP TRIGGER if exists items_fts_insert;
+DROP TRIGGER if exists items_fts_update;
+DROP TRIGGER if exists items_fts_delete;
+DROP TRIGGER if exists item_tags_fts_insert;
+DROP TRIGGER if exists item_tags_fts_delete;
+DROP TRIGGER if exists item_tags_fts_update;
+DROP TABLE if exists items_fts;
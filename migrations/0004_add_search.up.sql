@@ -0,0 +1,84 @@
+CREATE VIRTUAL TABLE if not exists items_fts USING fts5(
+    title,
+    excerpt,
+    notes,
+    -- space separated tag names of the item
+    tags,
+    tokenize = 'porter unicode61'
+);
+INSERT INTO items_fts (rowid, title, excerpt, notes, tags)
+SELECT id,
+    title,
+    excerpt,
+    notes,
+    (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = items.id
+    )
+FROM items;
+CREATE TRIGGER if not exists items_fts_insert
+AFTER INSERT ON items BEGIN
+INSERT INTO items_fts (rowid, title, excerpt, notes, tags)
+VALUES (
+        new.id,
+        new.title,
+        new.excerpt,
+        new.notes,
+        (
+            SELECT group_concat(tag_name, ' ')
+            FROM item_tags
+            WHERE item_id = new.id
+        )
+    );
+END;
+CREATE TRIGGER if not exists items_fts_update
+AFTER UPDATE OF title, excerpt, notes ON items BEGIN
+UPDATE items_fts
+SET title = new.title,
+    excerpt = new.excerpt,
+    notes = new.notes
+WHERE rowid = new.id;
+END;
+CREATE TRIGGER if not exists items_fts_delete
+AFTER DELETE ON items BEGIN
+DELETE FROM items_fts
+WHERE rowid = old.id;
+END;
+CREATE TRIGGER if not exists item_tags_fts_insert
+AFTER INSERT ON item_tags BEGIN
+UPDATE items_fts
+SET tags = (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = new.item_id
+    )
+WHERE rowid = new.item_id;
+END;
+CREATE TRIGGER if not exists item_tags_fts_delete
+AFTER DELETE ON item_tags BEGIN
+UPDATE items_fts
+SET tags = (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = old.item_id
+    )
+WHERE rowid = old.item_id;
+END;
+CREATE TRIGGER if not exists item_tags_fts_update
+AFTER UPDATE ON item_tags BEGIN
+UPDATE items_fts
+SET tags = (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = old.item_id
+    )
+WHERE rowid = old.item_id;
+UPDATE items_fts
+SET tags = (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = new.item_id
+    )
+WHERE rowid = new.item_id;
+END;
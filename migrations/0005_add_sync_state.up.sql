@@ -0,0 +1,6 @@
+CREATE TABLE if not exists sync_state (
+    provider_id INTEGER PRIMARY KEY,
+    -- server time of the last sync as unix time, pocket's `since` cursor
+    since INTEGER,
+    FOREIGN KEY(provider_id) REFERENCES providers(id)
+);
@@ -0,0 +1,2 @@
+ALTER TABLE items DROP COLUMN time_read;
+ALTER TABLE items DROP COLUMN status;
@@ -0,0 +1,4 @@
+-- 0 unread, 1 archived, 2 deleted; same as pocket's item status
+ALTER TABLE items ADD COLUMN status INTEGER NOT NULL DEFAULT 0;
+-- timestamp as unix time
+ALTER TABLE items ADD COLUMN time_read INTEGER DEFAULT NULL;
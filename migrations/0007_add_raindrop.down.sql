@@ -0,0 +1,4 @@
+ALTER TABLE secrets DROP COLUMN raindrop_access_token;
+DELETE FROM sync_state WHERE provider_id = (SELECT id FROM providers WHERE name = 'raindrop');
+-- fails while raindrop items are left, they'd lose their provider
+DELETE FROM providers WHERE name = 'raindrop';
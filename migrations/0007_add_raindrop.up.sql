@@ -0,0 +1,2 @@
+INSERT INTO providers (name) VALUES ('raindrop');
+ALTER TABLE secrets ADD COLUMN raindrop_access_token TEXT;
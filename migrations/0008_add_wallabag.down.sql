@@ -0,0 +1,8 @@
+ALTER TABLE secrets DROP COLUMN wallabag_refresh_token;
+ALTER TABLE secrets DROP COLUMN wallabag_access_token;
+ALTER TABLE secrets DROP COLUMN wallabag_client_secret;
+ALTER TABLE secrets DROP COLUMN wallabag_client_id;
+ALTER TABLE secrets DROP COLUMN wallabag_url;
+DELETE FROM sync_state WHERE provider_id = (SELECT id FROM providers WHERE name = 'wallabag');
+-- fails while wallabag items are left, they'd lose their provider
+DELETE FROM providers WHERE name = 'wallabag';
@@ -0,0 +1,6 @@
+INSERT INTO providers (name) VALUES ('wallabag');
+ALTER TABLE secrets ADD COLUMN wallabag_url TEXT;
+ALTER TABLE secrets ADD COLUMN wallabag_client_id TEXT;
+ALTER TABLE secrets ADD COLUMN wallabag_client_secret TEXT;
+ALTER TABLE secrets ADD COLUMN wallabag_access_token TEXT;
+ALTER TABLE secrets ADD COLUMN wallabag_refresh_token TEXT;
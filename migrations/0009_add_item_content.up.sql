@@ -0,0 +1,130 @@
+-- Readable article text archived from the item's page
+CREATE TABLE if not exists item_content (
+    item_id INTEGER PRIMARY KEY REFERENCES items (id) ON DELETE CASCADE,
+    -- plain text, paragraphs separated by blank lines
+    text TEXT NOT NULL,
+    -- sanitized html of the article body
+    html TEXT NOT NULL,
+    time_archived INTEGER NOT NULL
+);
+-- fts5 tables can't be altered, recreate the index with the article text
+DROP TRIGGER if exists items_fts_insert;
+DROP TRIGGER if exists items_fts_update;
+DROP TRIGGER if exists items_fts_delete;
+DROP TRIGGER if exists item_tags_fts_insert;
+DROP TRIGGER if exists item_tags_fts_delete;
+DROP TRIGGER if exists item_tags_fts_update;
+DROP TABLE if exists items_fts;
+CREATE VIRTUAL TABLE items_fts USING fts5(
+    title,
+    excerpt,
+    notes,
+    -- space separated tag names of the item
+    tags,
+    content,
+    tokenize = 'porter unicode61'
+);
+INSERT INTO items_fts (rowid, title, excerpt, notes, tags, content)
+SELECT id,
+    title,
+    excerpt,
+    notes,
+    (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = items.id
+    ),
+    (
+        SELECT text
+        FROM item_content
+        WHERE item_id = items.id
+    )
+FROM items;
+CREATE TRIGGER items_fts_insert
+AFTER INSERT ON items BEGIN
+INSERT INTO items_fts (rowid, title, excerpt, notes, tags, content)
+VALUES (
+        new.id,
+        new.title,
+        new.excerpt,
+        new.notes,
+        (
+            SELECT group_concat(tag_name, ' ')
+            FROM item_tags
+            WHERE item_id = new.id
+        ),
+        (
+            SELECT text
+            FROM item_content
+            WHERE item_id = new.id
+        )
+    );
+END;
+CREATE TRIGGER items_fts_update
+AFTER UPDATE OF title, excerpt, notes ON items BEGIN
+UPDATE items_fts
+SET title = new.title,
+    excerpt = new.excerpt,
+    notes = new.notes
+WHERE rowid = new.id;
+END;
+CREATE TRIGGER items_fts_delete
+AFTER DELETE ON items BEGIN
+DELETE FROM items_fts
+WHERE rowid = old.id;
+END;
+CREATE TRIGGER item_tags_fts_insert
+AFTER INSERT ON item_tags BEGIN
+UPDATE items_fts
+SET tags = (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = new.item_id
+    )
+WHERE rowid = new.item_id;
+END;
+CREATE TRIGGER item_tags_fts_delete
+AFTER DELETE ON item_tags BEGIN
+UPDATE items_fts
+SET tags = (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = old.item_id
+    )
+WHERE rowid = old.item_id;
+END;
+CREATE TRIGGER item_tags_fts_update
+AFTER UPDATE ON item_tags BEGIN
+UPDATE items_fts
+SET tags = (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = old.item_id
+    )
+WHERE rowid = old.item_id;
+UPDATE items_fts
+SET tags = (
+        SELECT group_concat(tag_name, ' ')
+        FROM item_tags
+        WHERE item_id = new.item_id
+    )
+WHERE rowid = new.item_id;
+END;
+CREATE TRIGGER item_content_fts_insert
+AFTER INSERT ON item_content BEGIN
+UPDATE items_fts
+SET content = new.text
+WHERE rowid = new.item_id;
+END;
+CREATE TRIGGER item_content_fts_update
+AFTER UPDATE OF text ON item_content BEGIN
+UPDATE items_fts
+SET content = new.text
+WHERE rowid = new.item_id;
+END;
+CREATE TRIGGER item_content_fts_delete
+AFTER DELETE ON item_content BEGIN
+UPDATE items_fts
+SET content = NULL
+WHERE rowid = old.item_id;
+END;
@@ -0,0 +1,5 @@
+ALTER TABLE items DROP COLUMN image_url;
+ALTER TABLE items DROP COLUMN canonical_url;
+ALTER TABLE items DROP COLUMN site_name;
+ALTER TABLE items DROP COLUMN published;
+ALTER TABLE items DROP COLUMN author;
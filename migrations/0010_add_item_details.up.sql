@@ -0,0 +1,7 @@
+-- Page metadata from OpenGraph, Twitter cards and JSON-LD
+ALTER TABLE items ADD COLUMN author TEXT;
+-- unix time the page was published, not when it was saved
+ALTER TABLE items ADD COLUMN published INTEGER;
+ALTER TABLE items ADD COLUMN site_name TEXT;
+ALTER TABLE items ADD COLUMN canonical_url TEXT;
+ALTER TABLE items ADD COLUMN image_url TEXT;
@@ -0,0 +1,3 @@
+DROP INDEX if exists item_collections_collection;
+DROP TABLE if exists item_collections;
+DROP TABLE if exists collections;
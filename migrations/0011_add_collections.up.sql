@@ -0,0 +1,17 @@
+-- Hand curated reading lists, independent of the topic tags
+CREATE TABLE collections (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    description TEXT,
+    time_created INTEGER NOT NULL
+);
+CREATE TABLE item_collections (
+    item_id INTEGER NOT NULL,
+    collection_id INTEGER NOT NULL,
+    -- items are listed in the order they were added to the collection
+    time_added INTEGER NOT NULL,
+    PRIMARY KEY(item_id, collection_id),
+    FOREIGN KEY(item_id) REFERENCES items(id),
+    FOREIGN KEY(collection_id) REFERENCES collections(id)
+);
+CREATE INDEX item_collections_collection ON item_collections(collection_id);
@@ -0,0 +1,11 @@
+-- Older releases can't decrypt the secrets, they have to be set again
+UPDATE secrets
+SET pocket_consumer_key = NULL,
+    pocket_access_token = NULL,
+    raindrop_access_token = NULL,
+    wallabag_url = NULL,
+    wallabag_client_id = NULL,
+    wallabag_client_secret = NULL,
+    wallabag_access_token = NULL,
+    wallabag_refresh_token = NULL;
+DROP TABLE if exists secrets_key;
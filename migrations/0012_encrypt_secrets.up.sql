@@ -0,0 +1,9 @@
+-- How the key encrypting the values in secrets is derived, the key itself is never stored
+CREATE TABLE secrets_key (
+    id INTEGER PRIMARY KEY CHECK (id = 0),
+    -- 'passphrase' or 'keyfile'
+    source TEXT NOT NULL,
+    salt BLOB NOT NULL,
+    -- a known value encrypted with the key, tells a wrong passphrase or keyfile apart
+    key_check TEXT NOT NULL
+);
@@ -0,0 +1,58 @@
+-- Only the default accounts fit in the old tables
+CREATE TABLE sync_state_providers (
+    provider_id INTEGER PRIMARY KEY,
+    since INTEGER,
+    FOREIGN KEY(provider_id) REFERENCES providers(id)
+);
+INSERT INTO sync_state_providers (provider_id, since)
+SELECT provider_id, since
+FROM sync_state
+WHERE account = 'default';
+DROP TABLE sync_state;
+ALTER TABLE sync_state_providers RENAME TO sync_state;
+CREATE TABLE secrets (
+    user_id INTEGER PRIMARY KEY AUTOINCREMENT,
+    pocket_consumer_key TEXT,
+    pocket_access_token TEXT,
+    raindrop_access_token TEXT,
+    wallabag_url TEXT,
+    wallabag_client_id TEXT,
+    wallabag_client_secret TEXT,
+    wallabag_access_token TEXT,
+    wallabag_refresh_token TEXT
+);
+INSERT INTO secrets (user_id) VALUES (0);
+UPDATE secrets
+SET pocket_consumer_key = (
+        SELECT value FROM provider_accounts JOIN providers ON providers.id = provider_id
+        WHERE name = 'pocket' AND account = 'default' AND key = 'consumer_key'
+    ),
+    pocket_access_token = (
+        SELECT value FROM provider_accounts JOIN providers ON providers.id = provider_id
+        WHERE name = 'pocket' AND account = 'default' AND key = 'access_token'
+    ),
+    raindrop_access_token = (
+        SELECT value FROM provider_accounts JOIN providers ON providers.id = provider_id
+        WHERE name = 'raindrop' AND account = 'default' AND key = 'access_token'
+    ),
+    wallabag_url = (
+        SELECT value FROM provider_accounts JOIN providers ON providers.id = provider_id
+        WHERE name = 'wallabag' AND account = 'default' AND key = 'url'
+    ),
+    wallabag_client_id = (
+        SELECT value FROM provider_accounts JOIN providers ON providers.id = provider_id
+        WHERE name = 'wallabag' AND account = 'default' AND key = 'client_id'
+    ),
+    wallabag_client_secret = (
+        SELECT value FROM provider_accounts JOIN providers ON providers.id = provider_id
+        WHERE name = 'wallabag' AND account = 'default' AND key = 'client_secret'
+    ),
+    wallabag_access_token = (
+        SELECT value FROM provider_accounts JOIN providers ON providers.id = provider_id
+        WHERE name = 'wallabag' AND account = 'default' AND key = 'access_token'
+    ),
+    wallabag_refresh_token = (
+        SELECT value FROM provider_accounts JOIN providers ON providers.id = provider_id
+        WHERE name = 'wallabag' AND account = 'default' AND key = 'refresh_token'
+    );
+DROP TABLE provider_accounts;
@@ -0,0 +1,51 @@
+-- Secrets of any provider as key/value pairs, several accounts per provider
+CREATE TABLE provider_accounts (
+    provider_id INTEGER NOT NULL,
+    -- 'default' unless another one is picked with --account
+    account TEXT NOT NULL,
+    key TEXT NOT NULL,
+    -- encrypted, see secrets_key
+    value TEXT NOT NULL,
+    PRIMARY KEY(provider_id, account, key),
+    FOREIGN KEY(provider_id) REFERENCES providers(id)
+);
+-- The encrypted values are bound to `<provider>_<key>`, the names of the old columns
+INSERT INTO provider_accounts (provider_id, account, key, value)
+SELECT providers.id,
+    'default',
+    secret.key,
+    secret.value
+FROM (
+        SELECT 'pocket' AS provider, 'consumer_key' AS key, pocket_consumer_key AS value FROM secrets
+        UNION ALL
+        SELECT 'pocket', 'access_token', pocket_access_token FROM secrets
+        UNION ALL
+        SELECT 'raindrop', 'access_token', raindrop_access_token FROM secrets
+        UNION ALL
+        SELECT 'wallabag', 'url', wallabag_url FROM secrets
+        UNION ALL
+        SELECT 'wallabag', 'client_id', wallabag_client_id FROM secrets
+        UNION ALL
+        SELECT 'wallabag', 'client_secret', wallabag_client_secret FROM secrets
+        UNION ALL
+        SELECT 'wallabag', 'access_token', wallabag_access_token FROM secrets
+        UNION ALL
+        SELECT 'wallabag', 'refresh_token', wallabag_refresh_token FROM secrets
+    ) AS secret
+    JOIN providers ON providers.name = secret.provider
+WHERE secret.value IS NOT NULL;
+DROP TABLE secrets;
+-- Each account keeps its own sync cursor
+CREATE TABLE sync_state_accounts (
+    provider_id INTEGER NOT NULL,
+    account TEXT NOT NULL,
+    -- server time of the last sync as unix time, pocket's `since` cursor
+    since INTEGER,
+    PRIMARY KEY(provider_id, account),
+    FOREIGN KEY(provider_id) REFERENCES providers(id)
+);
+INSERT INTO sync_state_accounts (provider_id, account, since)
+SELECT provider_id, 'default', since
+FROM sync_state;
+DROP TABLE sync_state;
+ALTER TABLE sync_state_accounts RENAME TO sync_state;
@@ -0,0 +1,2 @@
+DROP INDEX if exists items_remote_id;
+ALTER TABLE items DROP COLUMN remote_id;
@@ -0,0 +1,12 @@
+-- Id of the item in its online provider, items.id is only local so the ids of different
+-- providers can't collide
+ALTER TABLE items ADD COLUMN remote_id INTEGER;
+UPDATE items
+SET remote_id = id
+WHERE provider_id != (
+        SELECT id
+        FROM providers
+        WHERE name = 'local'
+    );
+-- Local items have no remote id, NULLs never conflict
+CREATE UNIQUE INDEX items_remote_id ON items (provider_id, remote_id);
@@ -0,0 +1,4 @@
+-- Fails while two accounts of a provider share a remote id
+DROP INDEX if exists items_remote_id;
+CREATE UNIQUE INDEX items_remote_id ON items (provider_id, remote_id);
+ALTER TABLE items DROP COLUMN account;
@@ -0,0 +1,13 @@
+-- Account of the provider the item was fetched with, NULL for local items. Items fetched
+-- before there were accounts all came from the default one
+ALTER TABLE items ADD COLUMN account TEXT;
+UPDATE items
+SET account = 'default'
+WHERE provider_id != (
+        SELECT id
+        FROM providers
+        WHERE name = 'local'
+    );
+-- Remote ids are only unique within an account, two wallabag instances can share them
+DROP INDEX items_remote_id;
+CREATE UNIQUE INDEX items_remote_id ON items (provider_id, account, remote_id);
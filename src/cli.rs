@@ -1,4 +1,11 @@
-use clap::{crate_authors, crate_description, crate_version, Args, Parser, Subcommand};
+use crate::{
+    db::DEFAULT_ACCOUNT,
+    util::{output::FIELDS, parse_iso_date},
+};
+use clap::{
+    builder::PossibleValuesParser, crate_authors, crate_description, crate_version, Args,
+    Parser, Subcommand, ValueEnum,
+};
 
 #[derive(Parser)]
 #[clap(author=crate_authors!(), version=crate_version!(), about=crate_description!(), long_about = None)]
@@ -19,10 +26,58 @@ pub struct CliArgs {
 pub enum Subcommands {
     /// Pocket related actions
     Pocket {
+        /// Saved account to use, to keep several pocket accounts apart
+        #[arg(long, global = true, default_value = DEFAULT_ACCOUNT)]
+        account: String,
+
         #[clap(subcommand)]
         command: PocketCommands,
     },
 
+    /// Raindrop.io related actions
+    Raindrop {
+        /// Saved account to use, to keep several raindrop accounts apart
+        #[arg(long, global = true, default_value = DEFAULT_ACCOUNT)]
+        account: String,
+
+        #[clap(subcommand)]
+        command: RaindropCommands,
+    },
+
+    /// Wallabag related actions
+    Wallabag {
+        /// Saved account to use, to keep several wallabag accounts apart
+        #[arg(long, global = true, default_value = DEFAULT_ACCOUNT)]
+        account: String,
+
+        #[clap(subcommand)]
+        command: WallabagCommands,
+    },
+
+    /// List, rename, merge and delete tags
+    Tags {
+        #[clap(subcommand)]
+        command: TagsCommands,
+    },
+
+    /// Curated reading lists, independent of the topic tags
+    Collection {
+        #[clap(subcommand)]
+        command: CollectionCommands,
+    },
+
+    /// Migrate, back up, restore and check the database
+    Db {
+        #[clap(subcommand)]
+        command: DbCommands,
+    },
+
+    /// Online providers and their saved accounts
+    Providers {
+        #[clap(subcommand)]
+        command: ProvidersCommands,
+    },
+
     /// Add a new item to the database stored locally
     Local {
         #[clap(subcommand)]
@@ -34,6 +89,18 @@ pub enum Subcommands {
         /// Limit the maximum number of items to fetch for each provider
         #[arg(short, long)]
         limit: Option<usize>,
+
+        /// Only fetch changes since the last sync, applying edits and deletions
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "limit")]
+        sync: bool,
+
+        /// Don't download the article text of the new items
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        no_content: bool,
+
+        /// Only fetch this account of each provider, every saved account by default
+        #[arg(long)]
+        account: Option<String>,
     },
 
     /// Lists all items in the database
@@ -47,10 +114,58 @@ pub enum Subcommands {
         #[arg(short, long)]
         limit: Option<usize>,
 
+        /// Skip this many items, for paging through them with --limit
+        #[arg(long)]
+        offset: Option<usize>,
+
         /// Favorite items only (Default: false)
         #[arg(short = 'f', long, default_value = "false")]
         favorite_only: bool,
 
+        #[clap(flatten)]
+        read_state: ReadStateArgs,
+
+        #[clap(flatten)]
+        filters: ListFilterArgs,
+
+        /// Optional timezone (e.g., "America/New_York", "UTC")
+        #[arg(long)]
+        timezone: Option<String>,
+
+        /// Output format, everything but text can be piped into other tools
+        #[arg(long, value_enum, default_value_t = ListFormat::Text)]
+        format: ListFormat,
+
+        /// Fields to output separated by commas, in that order (not used by the text format)
+        /// Example: --fields id,title,tags
+        #[arg(long, value_delimiter = ',', value_parser = PossibleValuesParser::new(FIELDS))]
+        fields: Vec<String>,
+
+        /// Order of the items (Default: newest first)
+        #[arg(long, value_enum)]
+        sort: Option<ListSort>,
+
+        /// Reverse the order of the items
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        reverse: bool,
+    },
+
+    /// Full-text search over titles, excerpts, notes and tags
+    #[command(arg_required_else_help = true)]
+    Search {
+        /// Words to search for, each one is matched as a prefix
+        /// Example: research search rust async
+        #[arg(index = 1, required = true, num_args = 1..)]
+        query: Vec<String>,
+
+        /// Limit the number of results to display
+        #[arg(short, long)]
+        limit: Option<usize>,
+
+        /// Pass the query to SQLite FTS5 as is (allows OR, NOT, NEAR and column filters like title:rust)
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        raw: bool,
+
         /// Optional timezone (e.g., "America/New_York", "UTC")
         #[arg(long)]
         timezone: Option<String>,
@@ -79,6 +194,19 @@ pub enum Subcommands {
         #[arg(long, action = clap::ArgAction::SetTrue)]
         download_tailwind: bool,
 
+        #[clap(flatten)]
+        read_state: ReadStateArgs,
+
+        /// Public URL of the site, the RSS and Atom feeds are only generated with it since
+        /// their links have to be absolute
+        /// Example: --base-url https://user.github.io/my-list
+        #[arg(long, env = "RESEARCH_BASE_URL")]
+        base_url: Option<String>,
+
+        /// Number of newest items to include in each feed
+        #[arg(long, default_value = "20")]
+        feed_limit: usize,
+
         /// Optional timezone (e.g., "America/New_York", "UTC")
         #[arg(long)]
         timezone: Option<String>,
@@ -91,11 +219,27 @@ pub enum Subcommands {
         #[arg(long, action = clap::ArgAction::SetTrue)]
         raindrop: bool,
 
+        /// Export current database to the Netscape bookmark HTML format used by browsers
+        #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "raindrop")]
+        netscape: bool,
+
         /// Specify the output file (use "-" for stdout)
         #[arg(index = 1, required = true)]
         output: String,
     },
 
+    /// Import bookmarks into the local provider
+    #[command(arg_required_else_help = true)]
+    Import {
+        /// Format of the file to import
+        #[arg(long, value_enum, default_value_t = ImportFormat::Netscape)]
+        format: ImportFormat,
+
+        /// The file to import
+        #[arg(index = 1, required = true)]
+        file: String,
+    },
+
     /// Handle operations related to the research:// URL scheme
     #[command(arg_required_else_help = true)]
     Handle {
@@ -114,6 +258,313 @@ pub enum Subcommands {
 
     /// Add or update notes for an item
     Notes(NotesArgs),
+
+    /// Serve a local JSON API for saving items from bookmarklets and scripts
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1")]
+        host: String,
+
+        /// Port to listen on
+        #[arg(short, long, default_value = "7878")]
+        port: u16,
+
+        /// Token clients must send as `Authorization: Bearer <TOKEN>`, at least 16 characters
+        #[arg(long, env = "RESEARCH_TOKEN", required = true)]
+        token: String,
+
+        /// Origins of the web pages allowed to call the API, separated by commas. Other pages
+        /// can't read its responses, scripts outside a browser aren't affected
+        /// Example: --allow-origin https://news.ycombinator.com,https://arxiv.org
+        #[arg(long, env = "RESEARCH_ALLOW_ORIGIN", value_delimiter = ',')]
+        allow_origin: Vec<String>,
+    },
+
+    /// Mark an item as read and archive it
+    Archive(ArchiveArgs),
+
+    /// Move an archived item back to the unread list
+    Unarchive(ArchiveArgs),
+
+    /// Change the title, excerpt, language or tags of an item
+    Edit(EditArgs),
+
+    /// Remove an item from the database, and from its online provider when it supports it
+    Delete(DeleteArgs),
+
+    /// Download the readable article text of saved items for offline reading and search
+    ArchiveContent {
+        /// Only items that don't have archived content yet
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        missing: bool,
+    },
+}
+
+#[derive(Clone, ValueEnum)]
+pub enum ImportFormat {
+    /// Netscape bookmark HTML file exported by browsers, Pinboard, Raindrop or Pocket
+    Netscape,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ListFormat {
+    /// Human readable, one block per item
+    Text,
+    /// A JSON array
+    Json,
+    /// One JSON object per line
+    Jsonl,
+    Csv,
+    Tsv,
+    /// Aligned columns
+    Table,
+    /// A markdown table
+    Markdown,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum ListSort {
+    /// Newest first
+    Added,
+    Title,
+    /// Website the item is from, then title
+    Domain,
+}
+
+#[derive(Args)]
+pub struct ListFilterArgs {
+    /// Items with at least one of these tags, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    pub any_tag: Vec<String>,
+
+    /// Leave out items with any of these tags, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    pub exclude_tag: Vec<String>,
+
+    /// Items without tags only
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with_all = ["tags", "any_tag"])]
+    pub untagged: bool,
+
+    /// Items from this website or its subdomains
+    /// Example: --domain github.com
+    #[arg(long)]
+    pub domain: Option<String>,
+
+    /// Items of this provider, like pocket or local
+    #[arg(long)]
+    pub provider: Option<String>,
+
+    /// Items in this language, `en` also matches `en-US`
+    #[arg(long)]
+    pub lang: Option<String>,
+
+    /// Items added on or after this date (YYYY-MM-DD or RFC 3339)
+    #[arg(long, value_parser = parse_since)]
+    pub since: Option<i64>,
+
+    /// Items added on or before this date (YYYY-MM-DD or RFC 3339)
+    #[arg(long, value_parser = parse_until)]
+    pub until: Option<i64>,
+
+    /// Items with notes only
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub has_notes: bool,
+}
+
+fn parse_since(date: &str) -> Result<i64, String> {
+    parse_iso_date(date).ok_or_else(|| format!("invalid date \"{date}\", expected YYYY-MM-DD"))
+}
+
+/// Exclusive bound compared with `<`, so a plain date includes the whole day and a full
+/// timestamp includes its own second
+fn parse_until(date: &str) -> Result<i64, String> {
+    let time = parse_since(date)?;
+    Ok(if date.trim().len() == 10 {
+        time + 24 * 60 * 60
+    } else {
+        time + 1
+    })
+}
+
+#[derive(Args)]
+pub struct ReadStateArgs {
+    /// Unread items only
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "archived")]
+    pub unread: bool,
+
+    /// Archived (read) items only
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub archived: bool,
+}
+
+#[derive(Args)]
+pub struct ArchiveArgs {
+    /// URI (link) of the item
+    pub uri: String,
+}
+
+#[derive(Args)]
+pub struct EditArgs {
+    /// URI (link) or id of the item
+    pub item: String,
+
+    /// New title
+    #[arg(long)]
+    pub title: Option<String>,
+
+    /// New excerpt
+    #[arg(long)]
+    pub excerpt: Option<String>,
+
+    /// New language code, like en
+    #[arg(long)]
+    pub lang: Option<String>,
+
+    /// Tags to add, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    pub add_tag: Vec<String>,
+
+    /// Tags to remove, separated by commas
+    #[arg(long, value_delimiter = ',')]
+    pub remove_tag: Vec<String>,
+}
+
+#[derive(Args)]
+pub struct DeleteArgs {
+    /// URI (link) or id of the item
+    pub item: String,
+}
+
+#[derive(Subcommand)]
+pub enum TagsCommands {
+    /// List every tag with the number of items using it
+    List,
+
+    /// Rename a tag on every item, its descendants like `old/nlp` become `new/nlp`
+    Rename {
+        old: String,
+        new: String,
+
+        #[command(flatten)]
+        sync: TagSyncArgs,
+    },
+
+    /// Replace several tags with a single one, like `rust Rust rustlang --into rust`
+    Merge {
+        #[arg(required = true)]
+        sources: Vec<String>,
+
+        /// Tag the items end up with, created when it doesn't exist
+        #[arg(long)]
+        into: String,
+
+        #[command(flatten)]
+        sync: TagSyncArgs,
+    },
+
+    /// Remove a tag and its descendants like `tag/nlp` from every item
+    Delete {
+        tag: String,
+
+        #[command(flatten)]
+        sync: TagSyncArgs,
+    },
+
+    /// Remove the tags no item uses
+    Prune,
+}
+
+#[derive(Subcommand)]
+pub enum CollectionCommands {
+    /// Create an empty collection
+    Create {
+        name: String,
+
+        /// Shown on the collection's page of the generated site
+        #[arg(short, long)]
+        description: Option<String>,
+    },
+
+    /// Add items to a collection
+    Add {
+        collection: String,
+
+        /// URIs (links) or ids of the items
+        #[arg(required = true)]
+        items: Vec<String>,
+    },
+
+    /// Remove items from a collection, the items themselves are kept
+    Remove {
+        collection: String,
+
+        /// URIs (links) or ids of the items
+        #[arg(required = true)]
+        items: Vec<String>,
+    },
+
+    /// List the collections, or the items of one
+    List { collection: Option<String> },
+}
+
+#[derive(Subcommand)]
+pub enum ProvidersCommands {
+    /// List the providers with each saved account and whether it's authenticated
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum DbCommands {
+    /// List the migrations with when they were applied
+    Status,
+
+    /// Apply the pending migrations, every other command does it too
+    Migrate,
+
+    /// Revert the newest migrations, like before going back to an older release
+    Rollback {
+        /// Keep the migrations up to this version, 0 keeps only the first
+        #[arg(long, conflicts_with = "steps")]
+        target: Option<i64>,
+
+        /// Number of migrations to revert
+        #[arg(long, default_value_t = 1)]
+        steps: usize,
+    },
+
+    /// Copy the database to a file, safe while other commands are writing to it
+    Backup {
+        /// File to write, or a directory to create a timestamped backup in
+        path: String,
+
+        /// Overwrite the file when it exists
+        #[arg(long, action = clap::ArgAction::SetTrue)]
+        force: bool,
+    },
+
+    /// Replace the database with a backup, the current one is backed up next to it first
+    Restore { path: String },
+
+    /// Look for corruption, broken references and orphaned item tags
+    Check,
+
+    /// Shrink the database file by rebuilding it without its free space
+    Vacuum,
+}
+
+#[derive(Args)]
+pub struct TagSyncArgs {
+    /// Apply the change to the pocket tags too, same as `--sync pocket`
+    #[arg(long, action = clap::ArgAction::SetTrue, group = "sync_target")]
+    pub sync_pocket: bool,
+
+    /// Apply the change to the tags of this online provider too
+    #[arg(long, value_name = "PROVIDER", group = "sync_target")]
+    pub sync: Option<String>,
+
+    /// Saved account of the provider whose tags are changed
+    #[arg(long, default_value = DEFAULT_ACCOUNT, requires = "sync_target")]
+    pub account: String,
 }
 
 #[derive(Args)]
@@ -125,17 +576,21 @@ pub struct AuthArgs {
 
 #[derive(Args)]
 pub struct FetchArgs {
-    /// Pocket Consumer key
-    #[arg(long, env = "POCKET_CONSUMER_KEY", required = true)]
-    pub key: String,
+    /// Pocket Consumer key, the account's saved one by default
+    #[arg(long, env = "POCKET_CONSUMER_KEY")]
+    pub key: Option<String>,
 
-    /// Pocket Access token
-    #[arg(long, env = "POCKET_ACCESS_TOKEN", required = true)]
-    pub access: String,
+    /// Pocket Access token, the account's saved one by default
+    #[arg(long, env = "POCKET_ACCESS_TOKEN")]
+    pub access: Option<String>,
 
     /// Limit the maximum number of items to fetch
     #[arg(short, long)]
     pub limit: Option<usize>,
+
+    /// Only fetch changes since the last sync, applying edits and deletions
+    #[arg(long, action = clap::ArgAction::SetTrue, conflicts_with = "limit")]
+    pub sync: bool,
 }
 
 #[derive(Subcommand)]
@@ -181,6 +636,74 @@ pub struct PocketFavoriteArgs {
     pub access: Option<String>,
 }
 
+#[derive(Subcommand)]
+pub enum RaindropCommands {
+    /// Verify and save a test token
+    Auth(RaindropAuthArgs),
+
+    /// Fetch items from raindrop
+    Fetch {
+        /// Limit the maximum number of items to fetch
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+
+    /// Add an item to raindrop
+    Add(LocalAddArgs),
+
+    /// Mark an item as favorite in raindrop
+    Favorite(LocalFavoriteArgs),
+}
+
+#[derive(Args)]
+pub struct RaindropAuthArgs {
+    /// Test token (https://app.raindrop.io/settings/integrations)
+    #[arg(short, long, env = "RAINDROP_TOKEN", required = true)]
+    pub token: String,
+}
+
+#[derive(Subcommand)]
+pub enum WallabagCommands {
+    /// Log in to a wallabag instance and save the tokens
+    Auth(WallabagAuthArgs),
+
+    /// Fetch entries from wallabag
+    Fetch {
+        /// Limit the maximum number of items to fetch
+        #[arg(short, long)]
+        limit: Option<usize>,
+    },
+
+    /// Add an item to wallabag
+    Add(LocalAddArgs),
+
+    /// Mark an item as favorite (starred) in wallabag
+    Favorite(LocalFavoriteArgs),
+}
+
+#[derive(Args)]
+pub struct WallabagAuthArgs {
+    /// Root URL of the instance, like https://app.wallabag.it
+    #[arg(long, env = "WALLABAG_URL", required = true)]
+    pub url: String,
+
+    /// Client ID of an API client created at <url>/developer
+    #[arg(long, env = "WALLABAG_CLIENT_ID", required = true)]
+    pub client_id: String,
+
+    /// Client secret of the API client
+    #[arg(long, env = "WALLABAG_CLIENT_SECRET", required = true)]
+    pub client_secret: String,
+
+    /// Wallabag username
+    #[arg(short, long, env = "WALLABAG_USERNAME", required = true)]
+    pub username: String,
+
+    /// Wallabag password, only used to get the tokens
+    #[arg(short, long, env = "WALLABAG_PASSWORD", required = true)]
+    pub password: String,
+}
+
 #[derive(Args)]
 pub struct LocalAddArgs {
     /// URI (link) of the item (required)
@@ -0,0 +1,377 @@
+use std::error::Error;
+use std::ffi::{c_int, CStr, CString};
+use std::fmt::{self, Display};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use chrono::Local;
+use libsqlite3_sys as ffi;
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions};
+use sqlx::Row;
+
+use super::DB;
+
+/// How long a backup waits for another process to release its lock
+const BUSY_RETRIES: u32 = 50;
+const BUSY_WAIT: Duration = Duration::from_millis(100);
+
+/// Problems found by `research db check`, all empty for a healthy database
+#[derive(Debug, Default)]
+pub struct CheckReport {
+    /// Lines of `PRAGMA integrity_check` other than `ok`
+    pub integrity: Vec<String>,
+    /// Table, rowid and missing parent of the rows `PRAGMA foreign_key_check` reports, without
+    /// item_tags which are in `orphaned_tags`
+    pub foreign_keys: Vec<(String, i64, String)>,
+    /// item_tags rows whose item or tag doesn't exist
+    pub orphaned_tags: Vec<(i64, String)>,
+}
+
+#[derive(Debug)]
+pub enum BackupError {
+    Sqlx(sqlx::Error),
+    /// From SQLite's backup API, or opening the backup file
+    Sqlite(String),
+    /// The file to restore isn't a healthy research database
+    Invalid(String),
+}
+
+impl From<sqlx::Error> for BackupError {
+    fn from(e: sqlx::Error) -> Self {
+        BackupError::Sqlx(e)
+    }
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BackupError::Sqlx(e) => e.fmt(f),
+            BackupError::Sqlite(message) | BackupError::Invalid(message) => message.fmt(f),
+        }
+    }
+}
+
+impl Error for BackupError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BackupError::Sqlx(e) => Some(e),
+            BackupError::Sqlite(_) | BackupError::Invalid(_) => None,
+        }
+    }
+}
+
+impl CheckReport {
+    pub fn is_ok(&self) -> bool {
+        self.integrity.is_empty()
+            && self.foreign_keys.is_empty()
+            && self.orphaned_tags.is_empty()
+    }
+}
+
+impl DB {
+    /// File of the database, None for in-memory ones or when it doesn't exist
+    pub fn database_file(database_url: &str) -> Option<PathBuf> {
+        let options = SqliteConnectOptions::from_str(database_url).ok()?;
+        let path = options.get_filename();
+        path.is_file().then(|| path.to_path_buf())
+    }
+
+    /// `<name>.<timestamp>.bak` in `dir` for the database file, with a counter when there's
+    /// already a backup from the same second
+    pub fn backup_path(database_file: &Path, dir: &Path) -> PathBuf {
+        let stem = format!(
+            "{}.{}",
+            database_file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy(),
+            Local::now().format("%Y%m%d%H%M%S")
+        );
+        let mut path = dir.join(format!("{stem}.bak"));
+        let mut count = 1;
+        while path.exists() {
+            path = dir.join(format!("{stem}-{count}.bak"));
+            count += 1;
+        }
+        path
+    }
+
+    /// Copies the database to `path` with SQLite's online backup API, a consistent snapshot
+    /// even while other processes write to it. `path` is overwritten.
+    pub async fn backup(&self, path: &Path) -> Result<(), BackupError> {
+        let mut conn = self.pool.acquire().await?;
+        let mut handle = conn.lock_handle().await?;
+        let file =
+            RawConnection::open(path, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE)?;
+        copy(handle.as_raw_handle().as_ptr(), file.0).await?;
+        // The copy keeps the WAL mode of the database, a backup is better off as a single file
+        file.execute(c"PRAGMA journal_mode = DELETE")
+    }
+
+    /// Replaces the contents of the database with the backup at `path`, after checking it's
+    /// a healthy research database and backing up the current one next to it. Returns where
+    /// the current one was backed up.
+    pub async fn restore(
+        &self,
+        database_url: &str,
+        path: &Path,
+    ) -> Result<Option<PathBuf>, BackupError> {
+        let unhealthy = |reason: String| {
+            BackupError::Invalid(format!(
+                "{} isn't a healthy research database: {reason}",
+                path.display()
+            ))
+        };
+        let options = SqliteConnectOptions::new().filename(path).read_only(true);
+        let backup = DB {
+            pool: SqlitePoolOptions::new()
+                .connect_with(options)
+                .await
+                .map_err(|err| unhealthy(err.to_string()))?,
+            secrets_key: OnceLock::new(),
+        };
+        let report = backup.check().await;
+        let migrated = backup.applied_migrations().await;
+        backup.pool.close().await;
+        let report = report.map_err(|err| unhealthy(err.to_string()))?;
+        if !report.is_ok() {
+            return Err(unhealthy(
+                "run `research --db <backup> db check` to see why".to_string(),
+            ));
+        }
+        if migrated.map_or(true, |migrated| migrated.is_empty()) {
+            return Err(unhealthy("it has never been migrated".to_string()));
+        }
+
+        let current = self.backup_beside(database_url).await?;
+        let mut conn = self.pool.acquire().await?;
+        let mut handle = conn.lock_handle().await?;
+        let file = RawConnection::open(path, ffi::SQLITE_OPEN_READONLY)?;
+        copy(file.0, handle.as_raw_handle().as_ptr()).await?;
+        Ok(current)
+    }
+
+    /// Runs SQLite's integrity and foreign key checks, and looks for item_tags rows left
+    /// without their item or tag
+    pub async fn check(&self) -> Result<CheckReport, sqlx::Error> {
+        let integrity = sqlx::query("PRAGMA integrity_check")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|row| row.get::<String, _>(0))
+            .filter(|line| line != "ok")
+            .collect();
+
+        let foreign_keys = sqlx::query("PRAGMA foreign_key_check")
+            .fetch_all(&self.pool)
+            .await?
+            .into_iter()
+            .map(|row| {
+                (
+                    row.get::<String, _>("table"),
+                    row.get::<Option<i64>, _>("rowid").unwrap_or_default(),
+                    row.get::<String, _>("parent"),
+                )
+            })
+            .filter(|(table, _, _)| table != "item_tags")
+            .collect();
+
+        let orphaned_tags = sqlx::query(
+            "SELECT item_id, tag_name FROM item_tags
+            WHERE item_id NOT IN (SELECT id FROM items)
+                OR tag_name NOT IN (SELECT tag_name FROM tags)
+            ORDER BY item_id, tag_name",
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.get::<i64, _>(0), row.get::<String, _>(1)))
+        .collect();
+
+        Ok(CheckReport {
+            integrity,
+            foreign_keys,
+            orphaned_tags,
+        })
+    }
+
+    /// Rebuilds the database file without its free pages. Returns the size in bytes before
+    /// and after.
+    pub async fn vacuum(&self) -> Result<(i64, i64), sqlx::Error> {
+        let before = self.size().await?;
+        sqlx::query("VACUUM").execute(&self.pool).await?;
+        Ok((before, self.size().await?))
+    }
+
+    async fn size(&self) -> Result<i64, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.get::<i64, _>(0))
+    }
+}
+
+/// A connection outside of the pool, closed when dropped
+struct RawConnection(*mut ffi::sqlite3);
+
+impl RawConnection {
+    fn open(path: &Path, flags: c_int) -> Result<Self, BackupError> {
+        let filename = CString::new(path.to_string_lossy().as_bytes())
+            .map_err(|err| BackupError::Sqlite(format!("{}: {err}", path.display())))?;
+        let mut db = ptr::null_mut();
+        let rc =
+            unsafe { ffi::sqlite3_open_v2(filename.as_ptr(), &mut db, flags, ptr::null()) };
+        // A handle is returned even when opening fails, and has to be closed
+        let conn = Self(db);
+        if rc != ffi::SQLITE_OK {
+            return Err(sqlite_error(conn.0, path.display()));
+        }
+        Ok(conn)
+    }
+}
+
+impl RawConnection {
+    fn execute(&self, sql: &CStr) -> Result<(), BackupError> {
+        let rc = unsafe {
+            ffi::sqlite3_exec(self.0, sql.as_ptr(), None, ptr::null_mut(), ptr::null_mut())
+        };
+        if rc != ffi::SQLITE_OK {
+            return Err(sqlite_error(self.0, sql.to_string_lossy()));
+        }
+        Ok(())
+    }
+}
+
+impl Drop for RawConnection {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::sqlite3_close(self.0);
+        }
+    }
+}
+
+/// Copies every page of the main database of `source` into `dest` in a single step, so
+/// writes from other processes can't interleave with it
+async fn copy(source: *mut ffi::sqlite3, dest: *mut ffi::sqlite3) -> Result<(), BackupError> {
+    let main = c"main".as_ptr();
+    let backup = unsafe { ffi::sqlite3_backup_init(dest, main, source, main) };
+    if backup.is_null() {
+        return Err(sqlite_error(dest, "backup"));
+    }
+
+    let mut retries = 0;
+    loop {
+        match unsafe { ffi::sqlite3_backup_step(backup, -1) } {
+            ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED if retries < BUSY_RETRIES => {
+                retries += 1;
+                tokio::time::sleep(BUSY_WAIT).await;
+            }
+            _ => break,
+        }
+    }
+    // Returns the error of the last step, if any
+    let rc = unsafe { ffi::sqlite3_backup_finish(backup) };
+    if rc != ffi::SQLITE_OK {
+        return Err(sqlite_error(dest, "backup"));
+    }
+    Ok(())
+}
+
+fn sqlite_error(db: *mut ffi::sqlite3, context: impl Display) -> BackupError {
+    let message = if db.is_null() {
+        "out of memory".to_string()
+    } else {
+        unsafe { CStr::from_ptr(ffi::sqlite3_errmsg(db)) }
+            .to_string_lossy()
+            .into_owned()
+    };
+    BackupError::Sqlite(format!("{context}: {message}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use sqlx::migrate::MigrateDatabase;
+
+    use super::{BackupError, DB};
+
+    /// Empty directory for the files of a test
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("research-{}-{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    async fn file_db(path: &Path) -> (DB, String) {
+        let url = path.to_string_lossy().into_owned();
+        sqlx::Sqlite::create_database(&url).await.unwrap();
+        (DB::init(&url).await.unwrap(), url)
+    }
+
+    async fn titles(db: &DB) -> Vec<String> {
+        sqlx::query_scalar("SELECT title FROM items ORDER BY title")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn backup_and_restore_round_trip() {
+        let dir = test_dir("backup");
+        let (db, url) = file_db(&dir.join("research.sqlite")).await;
+        sqlx::query(
+            "INSERT INTO items (uri, title, provider_id)
+            VALUES ('https://a.example', 'kept', (SELECT id FROM providers WHERE name = 'local'))",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+
+        let backup = dir.join("copy.sqlite");
+        db.backup(&backup).await.unwrap();
+        sqlx::query("UPDATE items SET title = 'changed'")
+            .execute(&db.pool)
+            .await
+            .unwrap();
+
+        let current = db.restore(&url, &backup).await.unwrap().unwrap();
+        assert_eq!(titles(&db).await, ["kept"]);
+        // The database before restoring was backed up first
+        let (before, _) = file_db(&current).await;
+        assert_eq!(titles(&before).await, ["changed"]);
+        assert!(before.check().await.unwrap().is_ok());
+
+        db.pool.close().await;
+        before.pool.close().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn restore_refuses_other_databases() {
+        let dir = test_dir("restore-invalid");
+        let (db, url) = file_db(&dir.join("research.sqlite")).await;
+        let other = dir.join("other.sqlite");
+        fs::write(&other, "not a database").unwrap();
+
+        let result = db.restore(&url, &other).await;
+        assert!(matches!(result, Err(BackupError::Invalid(_))), "{result:?}");
+        // Nothing was backed up or replaced
+        let backups = fs::read_dir(&dir)
+            .unwrap()
+            .filter(|entry| entry.as_ref().unwrap().path().extension() == Some("bak".as_ref()))
+            .count();
+        assert_eq!(backups, 0);
+        assert!(db.check().await.unwrap().is_ok());
+
+        db.pool.close().await;
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
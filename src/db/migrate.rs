@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sqlx::migrate::{MigrateError, Migrator};
+use sqlx::Row;
+
+use super::{BackupError, DB};
+
+/// Every migration in `migrations/`, built into the binary
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// The version that adds `items.notes`, which older releases added with an `ALTER TABLE` outside
+/// of the migrations
+const NOTES_VERSION: i64 = 3;
+
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    /// When it was applied, in UTC as SQLite's `CURRENT_TIMESTAMP`, None while it's pending
+    pub installed_on: Option<String>,
+    /// False for versions applied by a newer release of research that this one doesn't know
+    pub known: bool,
+}
+
+impl DB {
+    /// Latest version known to this release
+    pub fn latest_migration() -> i64 {
+        MIGRATOR
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .map(|migration| migration.version)
+            .max()
+            .unwrap_or_default()
+    }
+
+    /// Known and applied migrations, ordered by version
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, sqlx::Error> {
+        let mut applied = self.applied_migrations().await?;
+        let mut status = MIGRATOR
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .map(|migration| MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                installed_on: applied
+                    .remove(&migration.version)
+                    .map(|(_, installed_on)| installed_on),
+                known: true,
+            })
+            .collect::<Vec<_>>();
+        status.extend(
+            applied
+                .into_iter()
+                .map(|(version, (description, installed_on))| MigrationStatus {
+                    version,
+                    description,
+                    installed_on: Some(installed_on),
+                    known: false,
+                }),
+        );
+        status.sort_by_key(|migration| migration.version);
+        Ok(status)
+    }
+
+    /// Applies the pending migrations, after backing up the database when it already has data,
+    /// and encrypts the secrets older releases saved in plaintext. Returns the applied versions.
+    pub async fn migrate(&self, database_url: &str) -> Result<Vec<i64>, sqlx::Error> {
+        self.adopt_notes_column().await?;
+
+        let applied = self.applied_migrations().await?;
+        // Fails before the backup, the migrator would refuse to run anyway
+        if let Some(version) = applied
+            .keys()
+            .find(|version| !MIGRATOR.version_exists(**version))
+        {
+            return Err(MigrateError::VersionMissing(*version).into());
+        }
+        let pending = MIGRATOR
+            .iter()
+            .filter(|migration| migration.migration_type.is_up_migration())
+            .filter(|migration| !applied.contains_key(&migration.version))
+            .map(|migration| migration.version)
+            .collect::<Vec<_>>();
+        if !pending.is_empty() && !applied.is_empty() {
+            if let Some(path) = self
+                .backup_beside(database_url)
+                .await
+                .map_err(|err| sqlx::Error::Io(io::Error::other(err)))?
+            {
+                eprintln!("Backed up the database to {}", path.display());
+            }
+        }
+
+        // Also checks the applied migrations weren't changed since
+        MIGRATOR.run(&self.pool).await?;
+        let encrypted = self.encrypt_plaintext_secrets().await?;
+        if encrypted > 0 {
+            eprintln!("Encrypted {encrypted} secrets stored in plaintext");
+        }
+        Ok(pending)
+    }
+
+    /// Reverts the applied migrations after `target`, newest first, after backing up the
+    /// database. Returns the reverted versions.
+    pub async fn rollback(
+        &self,
+        database_url: &str,
+        target: i64,
+    ) -> Result<Vec<i64>, sqlx::Error> {
+        let applied = self.applied_migrations().await?;
+        let mut reverted = applied
+            .into_keys()
+            .filter(|version| *version > target)
+            .collect::<Vec<_>>();
+        reverted.sort_by(|a, b| b.cmp(a));
+        if reverted.is_empty() {
+            return Ok(reverted);
+        }
+        if let Some(path) = self
+            .backup_beside(database_url)
+            .await
+            .map_err(|err| sqlx::Error::Io(io::Error::other(err)))?
+        {
+            eprintln!("Backed up the database to {}", path.display());
+        }
+
+        MIGRATOR.undo(&self.pool, target).await?;
+        Ok(reverted)
+    }
+
+    /// Version to description and time applied, empty before the first migration
+    pub(super) async fn applied_migrations(
+        &self,
+    ) -> Result<HashMap<i64, (String, String)>, sqlx::Error> {
+        let exists = sqlx::query(
+            "SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'",
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .is_some();
+        if !exists {
+            return Ok(HashMap::new());
+        }
+
+        let rows = sqlx::query(
+            "SELECT version, description, installed_on FROM _sqlx_migrations WHERE success = 1",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.get::<i64, _>("version"),
+                    (
+                        row.get::<String, _>("description"),
+                        row.get::<String, _>("installed_on"),
+                    ),
+                )
+            })
+            .collect())
+    }
+
+    /// Older releases added `items.notes` at startup without recording the migration, so
+    /// applying it again would fail on the existing column. Records it as applied instead.
+    async fn adopt_notes_column(&self) -> Result<(), sqlx::Error> {
+        let applied = self.applied_migrations().await?;
+        if applied.is_empty() || applied.contains_key(&NOTES_VERSION) {
+            return Ok(());
+        }
+        let has_notes =
+            sqlx::query("SELECT 1 FROM pragma_table_info('items') WHERE name = 'notes'")
+                .fetch_optional(&self.pool)
+                .await?
+                .is_some();
+        let migration = MIGRATOR.iter().find(|migration| {
+            migration.version == NOTES_VERSION && migration.migration_type.is_up_migration()
+        });
+        if let (true, Some(migration)) = (has_notes, migration) {
+            sqlx::query(
+                "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+                VALUES (?1, ?2, TRUE, ?3, 0)",
+            )
+            .bind(migration.version)
+            .bind(migration.description.as_ref())
+            .bind(migration.checksum.as_ref())
+            .execute(&self.pool)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Backs up the database next to itself, see [`DB::backup_path`]. None for in-memory
+    /// databases.
+    pub(super) async fn backup_beside(
+        &self,
+        database_url: &str,
+    ) -> Result<Option<PathBuf>, BackupError> {
+        let Some(file) = Self::database_file(database_url) else {
+            return Ok(None);
+        };
+        let path = Self::backup_path(&file, file.parent().unwrap_or(Path::new(".")));
+        self.backup(&path).await?;
+        Ok(Some(path))
+    }
+}
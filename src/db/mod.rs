@@ -1,4 +1,5 @@
 use std::{
+    collections::{BTreeMap, BTreeSet},
     error::Error,
     fmt::{self, Display},
 };
@@ -7,9 +8,23 @@ use chrono::{DateTime, Local, TimeZone, Utc};
 use chrono_tz::Tz;
 use csv::WriterBuilder;
 use serde::Serialize;
-use sqlx::{sqlite::SqlitePoolOptions, FromRow, Pool, Row, Sqlite};
+use sqlx::{sqlite::SqlitePoolOptions, FromRow, Pool, Row, Sqlite, SqliteConnection};
 use std::fs::File;
 use std::io;
+use std::sync::OnceLock;
+
+mod maintenance;
+mod migrate;
+mod query;
+mod secrets;
+
+pub use maintenance::BackupError;
+pub use query::{ItemOrder, ItemQuery};
+
+use crate::provider::pocket::api::ItemStatus;
+use crate::util::crypto::SecretsKey;
+use crate::util::netscape::{write_bookmarks, Bookmark};
+use crate::util::readability::ArticleContent;
 
 #[derive(Clone, FromRow, Debug)]
 #[allow(dead_code)]
@@ -26,6 +41,10 @@ pub struct Tags {
 #[derive(Clone, FromRow, Debug, Serialize)]
 pub struct ResearchItem {
     pub id: Option<i64>,
+    /// Id of the item in its online provider, None for local items
+    pub remote_id: Option<i64>,
+    /// Account of the online provider the item was saved with, None for local items
+    pub account: Option<String>,
     pub uri: String,
     pub title: String,
     pub excerpt: String,
@@ -33,6 +52,35 @@ pub struct ResearchItem {
     pub favorite: bool,
     pub lang: Option<String>,
     pub notes: Option<String>,
+    pub status: ItemStatus,
+    pub time_read: Option<i64>,
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub details: ItemDetails,
+}
+
+/// Changes made with `research edit`, fields that are None are left as they are
+#[derive(Debug, Default)]
+pub struct ItemEdit {
+    pub title: Option<String>,
+    pub excerpt: Option<String>,
+    pub lang: Option<String>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+}
+
+const DELETE_ORPHAN_TAGS: &str =
+    "DELETE FROM tags WHERE tag_name NOT IN (SELECT tag_name FROM item_tags)";
+
+/// Metadata read from the page when it's saved
+#[derive(Clone, FromRow, Debug, Default, Serialize)]
+pub struct ItemDetails {
+    pub author: Option<String>,
+    /// Unix time the page was published
+    pub published: Option<i64>,
+    pub site_name: Option<String>,
+    pub canonical_url: Option<String>,
+    pub image_url: Option<String>,
 }
 
 impl fmt::Display for ResearchItem {
@@ -46,6 +94,37 @@ impl fmt::Display for ResearchItem {
     }
 }
 
+#[cfg(test)]
+impl ResearchItem {
+    /// Local item for tests, the other fields are set with struct update syntax
+    pub(crate) fn test(uri: &str, title: &str) -> Self {
+        Self {
+            id: None,
+            remote_id: None,
+            account: None,
+            uri: uri.to_string(),
+            title: title.to_string(),
+            excerpt: String::new(),
+            time_added: 0,
+            favorite: false,
+            lang: None,
+            notes: None,
+            status: ItemStatus::Normal,
+            time_read: None,
+            details: ItemDetails::default(),
+        }
+    }
+
+    /// The item as saved by an online provider with this id, in the default account
+    pub(crate) fn with_remote_id(self, remote_id: i64) -> Self {
+        Self {
+            remote_id: Some(remote_id),
+            account: Some(DEFAULT_ACCOUNT.to_string()),
+            ..self
+        }
+    }
+}
+
 impl ResearchItem {
     /// Of the format "21 Aug'21, 5pm"
     pub fn format_time_added(&self, timezone: Option<Tz>) -> String {
@@ -63,14 +142,36 @@ impl ResearchItem {
         }
     }
 
+    /// Of the format "21 Aug 2021", the time of day is rarely accurate
+    pub fn format_published(&self) -> Option<String> {
+        let published = Utc.timestamp_opt(self.details.published?, 0).single()?;
+        Some(published.format("%d %b %Y").to_string())
+    }
+
     pub fn to_display_with_timezone(&self, timezone: Option<Tz>) -> String {
         let time_added = self.format_time_added(timezone);
+        let mut details = String::new();
+        if let Some(author) = &self.details.author {
+            details.push_str(&format!("Author: {author}\n"));
+        }
+        if let Some(site_name) = &self.details.site_name {
+            details.push_str(&format!("Site: {site_name}\n"));
+        }
+        if let Some(published) = self.format_published() {
+            details.push_str(&format!("Published: {published}\n"));
+        }
         format!(
-            "Title: {}\nURL: {}\nAdded: {}\nFavorite: {}\nLanguage: {}\nNotes: {}\nExcerpt:\n{}",
+            "Title: {}\nURL: {}\n{}Added: {}\nFavorite: {}\nStatus: {}\nLanguage: {}\nNotes: {}\nExcerpt:\n{}",
             self.title,
             self.uri,
+            details,
             time_added,
             if self.favorite { "Yes" } else { "No" },
+            match self.status {
+                ItemStatus::Archived => "Archived",
+                ItemStatus::Deleted => "Deleted",
+                ItemStatus::Normal => "Unread",
+            },
             self.lang.as_ref().unwrap_or(&"Unknown".to_string()),
             self.notes.as_ref().unwrap_or(&"None".to_string()),
             self.excerpt
@@ -78,59 +179,93 @@ impl ResearchItem {
     }
 }
 
-#[derive(FromRow, Default)]
-#[allow(dead_code)]
+#[derive(Clone, FromRow, Debug, Serialize)]
+pub struct Collection {
+    pub id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub time_created: i64,
+}
+
+#[derive(Clone, FromRow, Debug, Serialize)]
+pub struct SearchResult {
+    #[sqlx(flatten)]
+    #[serde(flatten)]
+    pub item: ResearchItem,
+    /// Best matching fragment with the matched terms wrapped in `**`
+    pub snippet: String,
+}
+
+/// Account used when no `--account` is given
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// Secrets of one account of a provider, like `access_token`
+#[derive(Clone, Debug, Default)]
 pub struct Secrets {
-    pub pocket_consumer_key: Option<String>,
-    pub pocket_access_token: Option<String>,
-    pub user_id: i64,
+    pub provider: String,
+    pub account: String,
+    pub values: BTreeMap<String, String>,
 }
 
 impl Secrets {
-    pub fn new(s: Option<Self>) -> Self {
-        s.unwrap_or_default()
+    pub fn new(provider: &str, account: &str) -> Self {
+        Self {
+            provider: provider.to_string(),
+            account: account.to_string(),
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
     }
-}
 
-const DEFAULT_USER_ID: i64 = 0;
+    pub fn with(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.values.insert(key.to_string(), value.into());
+        self
+    }
+}
 
 pub struct DB {
     pub pool: Pool<Sqlite>,
+    secrets_key: OnceLock<SecretsKey>,
 }
 
 impl DB {
+    /// Connects and applies the pending migrations
     pub async fn init(database_url: &str) -> Result<Self, sqlx::Error> {
-        let pool = SqlitePoolOptions::new().connect(database_url).await?;
-
-        let check = sqlx::query("SELECT notes FROM items LIMIT 1")
-            .fetch_optional(&pool)
-            .await;
-
-        if let Err(sqlx::Error::Database(ref dberr)) = check {
-            if dberr.message().contains("no such column") {
-                println!("Upgrading database schema to add notes support...");
-                sqlx::query("ALTER TABLE items ADD COLUMN notes TEXT DEFAULT NULL")
-                    .execute(&pool)
-                    .await?;
-                println!("Database schema upgraded successfully!");
-                println!("Please restart the application to continue.");
-                std::process::exit(0);
-            }
+        let db = Self::open(database_url).await?;
+        for version in db.migrate(database_url).await? {
+            eprintln!("Applied migration {version}");
         }
-
-        Ok(Self { pool })
+        Ok(db)
     }
 
-    pub async fn migrate(pool: &Pool<Sqlite>) -> Result<(), sqlx::Error> {
-        let _ = sqlx::migrate!("./migrations").run(pool).await;
-        Ok(())
+    /// Connects without migrating, for inspecting or changing the schema version
+    pub async fn open(database_url: &str) -> Result<Self, sqlx::Error> {
+        let pool = SqlitePoolOptions::new().connect(database_url).await?;
+        Ok(Self {
+            pool,
+            secrets_key: OnceLock::new(),
+        })
     }
 
-    pub async fn get_sqlite_version(&self) -> Result<String, sqlx::Error> {
-        let row = sqlx::query("SELECT sqlite_version()")
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(row.get::<String, _>(0))
+    /// Migrated database living in its only connection, with a random secrets key, for tests
+    #[cfg(test)]
+    pub(crate) async fn in_memory() -> Self {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let db = Self {
+            pool,
+            secrets_key: OnceLock::from(SecretsKey::random()),
+        };
+        db.migrate("sqlite::memory:").await.unwrap();
+        db
     }
 
     pub async fn get_provider_id(&self, name: &str) -> Result<i64, sqlx::Error> {
@@ -147,10 +282,13 @@ impl DB {
         tags: &[Tags],
         provider_id: i64,
     ) -> Result<(), sqlx::Error> {
+        let uri = insertable_item.uri.clone();
         let _ = sqlx::query(
-            "INSERT OR IGNORE INTO items (id, uri, title, excerpt, time_added, favorite, lang, notes, provider_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            "INSERT OR IGNORE INTO items (id, remote_id, account, uri, title, excerpt, time_added, favorite, lang, notes, status, time_read, author, published, site_name, canonical_url, image_url, provider_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
         )
         .bind(insertable_item.id)
+        .bind(insertable_item.remote_id)
+        .bind(insertable_item.account)
         .bind(insertable_item.uri)
         .bind(insertable_item.title)
         .bind(insertable_item.excerpt)
@@ -158,10 +296,22 @@ impl DB {
         .bind(insertable_item.favorite)
         .bind(insertable_item.lang)
         .bind(insertable_item.notes)
+        .bind(insertable_item.status)
+        .bind(insertable_item.time_read)
+        .bind(insertable_item.details.author)
+        .bind(insertable_item.details.published)
+        .bind(insertable_item.details.site_name)
+        .bind(insertable_item.details.canonical_url)
+        .bind(insertable_item.details.image_url)
         .bind(provider_id)
         .execute(&self.pool)
         .await?;
 
+        // Items without an id get one assigned by sqlite
+        let item_id = match insertable_item.id {
+            Some(id) => Some(id),
+            None => self.get_item_id(&uri).await?,
+        };
         for tag in tags {
             let _ = sqlx::query("INSERT OR IGNORE INTO tags (tag_name) VALUES (?)")
                 .bind(tag.tag_name.clone())
@@ -170,7 +320,7 @@ impl DB {
             let _ = sqlx::query(
                 "INSERT OR IGNORE INTO item_tags (item_id, tag_name) VALUES (?, ?)",
             )
-            .bind(insertable_item.id)
+            .bind(item_id)
             .bind(&tag.tag_name)
             .execute(&self.pool)
             .await?;
@@ -178,43 +328,270 @@ impl DB {
         Ok(())
     }
 
+    /// Inserts the item or updates it in place when it already exists, keeping local notes.
+    /// The item's tags are replaced by `tags`. Returns false when the uri belongs to an item of
+    /// another provider, which is left as it is.
+    pub async fn upsert_item(
+        &self,
+        item: ResearchItem,
+        tags: &[Tags],
+        provider_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        // An item saved again gets a new id from some providers, it's matched by its uri then
+        let result = sqlx::query(
+            "INSERT INTO items (id, remote_id, account, uri, title, excerpt, time_added, favorite, lang, notes, status, time_read, author, published, site_name, canonical_url, image_url, provider_id) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(provider_id, account, remote_id) DO UPDATE SET uri = excluded.uri, title = excluded.title, excerpt = excluded.excerpt, time_added = excluded.time_added, favorite = excluded.favorite, lang = excluded.lang, status = excluded.status, time_read = excluded.time_read,
+                author = COALESCE(excluded.author, author), published = COALESCE(excluded.published, published), site_name = COALESCE(excluded.site_name, site_name), canonical_url = COALESCE(excluded.canonical_url, canonical_url), image_url = COALESCE(excluded.image_url, image_url)
+            WHERE items.provider_id = excluded.provider_id
+            ON CONFLICT(uri) DO UPDATE SET remote_id = excluded.remote_id, account = excluded.account, title = excluded.title, excerpt = excluded.excerpt, time_added = excluded.time_added, favorite = excluded.favorite, lang = excluded.lang, status = excluded.status, time_read = excluded.time_read,
+                author = COALESCE(excluded.author, author), published = COALESCE(excluded.published, published), site_name = COALESCE(excluded.site_name, site_name), canonical_url = COALESCE(excluded.canonical_url, canonical_url), image_url = COALESCE(excluded.image_url, image_url)
+            WHERE items.provider_id = excluded.provider_id",
+        )
+        .bind(item.id)
+        .bind(item.remote_id)
+        .bind(&item.account)
+        .bind(&item.uri)
+        .bind(&item.title)
+        .bind(&item.excerpt)
+        .bind(item.time_added)
+        .bind(item.favorite)
+        .bind(&item.lang)
+        .bind(&item.notes)
+        .bind(item.status)
+        .bind(item.time_read)
+        .bind(&item.details.author)
+        .bind(item.details.published)
+        .bind(&item.details.site_name)
+        .bind(&item.details.canonical_url)
+        .bind(&item.details.image_url)
+        .bind(provider_id)
+        .execute(&mut *tx)
+        .await?;
+        if result.rows_affected() == 0 {
+            tx.commit().await?;
+            return Ok(false);
+        }
+
+        let item_id: i64 = sqlx::query_scalar("SELECT id FROM items WHERE uri = ?")
+            .bind(&item.uri)
+            .fetch_one(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM item_tags WHERE item_id = ?")
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        for tag in tags {
+            sqlx::query("INSERT OR IGNORE INTO tags (tag_name) VALUES (?)")
+                .bind(&tag.tag_name)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("INSERT OR IGNORE INTO item_tags (item_id, tag_name) VALUES (?, ?)")
+                .bind(item_id)
+                .bind(&tag.tag_name)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    pub async fn delete_item(&self, item_id: i64) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM item_tags WHERE item_id = ?")
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM item_content WHERE item_id = ?")
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM item_collections WHERE item_id = ?")
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM items WHERE id = ?")
+            .bind(item_id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(DELETE_ORPHAN_TAGS).execute(&mut *tx).await?;
+        tx.commit().await
+    }
+
+    /// Applies the changes that are set, tags left without items are removed
+    pub async fn edit_item(&self, item_id: i64, edit: &ItemEdit) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE items SET title = COALESCE(?, title), excerpt = COALESCE(?, excerpt), lang = COALESCE(?, lang) WHERE id = ?",
+        )
+        .bind(&edit.title)
+        .bind(&edit.excerpt)
+        .bind(&edit.lang)
+        .bind(item_id)
+        .execute(&mut *tx)
+        .await?;
+        for tag in &edit.add_tags {
+            sqlx::query("INSERT OR IGNORE INTO tags (tag_name) VALUES (?)")
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+            sqlx::query("INSERT OR IGNORE INTO item_tags (item_id, tag_name) VALUES (?, ?)")
+                .bind(item_id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+        for tag in &edit.remove_tags {
+            sqlx::query("DELETE FROM item_tags WHERE item_id = ? AND tag_name = ?")
+                .bind(item_id)
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+        sqlx::query(DELETE_ORPHAN_TAGS).execute(&mut *tx).await?;
+        tx.commit().await
+    }
+
+    /// Unix time of the last successful sync with the provider account
+    pub async fn get_sync_since(
+        &self,
+        provider_id: i64,
+        account: &str,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        let row =
+            sqlx::query("SELECT since FROM sync_state WHERE provider_id = ? AND account = ?")
+                .bind(provider_id)
+                .bind(account)
+                .fetch_optional(&self.pool)
+                .await?;
+        Ok(row.and_then(|r| r.get(0)))
+    }
+
+    pub async fn set_sync_since(
+        &self,
+        provider_id: i64,
+        account: &str,
+        since: i64,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO sync_state (provider_id, account, since) VALUES (?, ?, ?) ON CONFLICT(provider_id, account) DO UPDATE SET since = excluded.since",
+        )
+        .bind(provider_id)
+        .bind(account)
+        .bind(since)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     pub async fn get_all_items(
         &self,
         favorite: Option<bool>,
+        status: Option<ItemStatus>,
     ) -> Result<Vec<ResearchItem>, sqlx::Error> {
-        let query = match favorite {
-            Some(true) => "SELECT * FROM items WHERE favorite = 1 ORDER BY time_added DESC",
-            Some(false) => "SELECT * FROM items WHERE favorite = 0 ORDER BY time_added DESC",
-            None => "SELECT * FROM items ORDER BY time_added DESC",
-        };
-
-        sqlx::query_as::<_, ResearchItem>(query)
-            .fetch_all(&self.pool)
-            .await
+        self.query_items(&ItemQuery {
+            favorite,
+            status,
+            ..Default::default()
+        })
+        .await
     }
 
+    /// Items tagged with every one of `tags`, tags are paths so `ml` also matches items tagged
+    /// `ml/transformers`
     pub async fn get_all_items_by_tags(
         &self,
         tags: &[String],
         favorite: Option<bool>,
+        status: Option<ItemStatus>,
+    ) -> Result<Vec<ResearchItem>, sqlx::Error> {
+        self.query_items(&ItemQuery {
+            all_tags: tags.to_vec(),
+            favorite,
+            status,
+            ..Default::default()
+        })
+        .await
+    }
+
+    pub async fn query_items(
+        &self,
+        query: &ItemQuery,
     ) -> Result<Vec<ResearchItem>, sqlx::Error> {
-        let favorite_clause = match favorite {
-            Some(true) => "AND items.favorite = 1",
-            Some(false) => "AND items.favorite = 0",
-            None => "",
+        query
+            .select()
+            .build_query_as::<ResearchItem>()
+            .fetch_all(&self.pool)
+            .await
+    }
+
+    /// Number of items matching the query, ignoring its limit and offset
+    pub async fn count_items(&self, query: &ItemQuery) -> Result<i64, sqlx::Error> {
+        query
+            .count()
+            .build_query_scalar()
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// Full-text search over titles, excerpts, notes and tag names, best matches first.
+    /// Unless `raw` is set every word of the query is matched as a quoted prefix, so
+    /// user input can't trip over the FTS5 query syntax.
+    pub async fn search(
+        &self,
+        query: &str,
+        raw: bool,
+        limit: Option<usize>,
+    ) -> Result<Vec<SearchResult>, sqlx::Error> {
+        let query = if raw {
+            query.to_string()
+        } else {
+            to_fts_query(query)
         };
+        if query.trim().is_empty() {
+            return Ok(Vec::new());
+        }
 
-        let query = format!(
-            "SELECT items.* FROM items JOIN item_tags ON items.id = item_tags.item_id WHERE item_tags.tag_name IN ({}) {} GROUP BY items.id HAVING COUNT(DISTINCT item_tags.tag_name) = {}",
-            tags.iter().map(|t| format!("'{}'", t)).collect::<Vec<_>>().join(", "),
-            favorite_clause,
-            tags.len()
-        );
+        // bm25 weights follow the column order of items_fts: title, excerpt, notes, tags, content
+        sqlx::query_as::<_, SearchResult>(
+            "SELECT items.*, COALESCE(snippet(items_fts, -1, '**', '**', '...', 16), '') AS snippet FROM items_fts JOIN items ON items.id = items_fts.rowid WHERE items_fts MATCH ? ORDER BY bm25(items_fts, 10.0, 4.0, 2.0, 6.0, 1.0) LIMIT ?",
+        )
+        .bind(query)
+        .bind(limit.map_or(-1, |l| l as i64))
+        .fetch_all(&self.pool)
+        .await
+    }
 
-        let items = sqlx::query_as::<_, ResearchItem>(&query)
-            .fetch_all(&self.pool)
-            .await?;
-        Ok(items)
+    /// Saves the article content of the item with this uri, replacing the previous one
+    pub async fn save_item_content(
+        &self,
+        uri: &str,
+        content: &ArticleContent,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO item_content (item_id, text, html, time_archived) SELECT id, ?, ?, ? FROM items WHERE uri = ?
+            ON CONFLICT(item_id) DO UPDATE SET text = excluded.text, html = excluded.html, time_archived = excluded.time_archived",
+        )
+        .bind(&content.text)
+        .bind(&content.html)
+        .bind(chrono::Utc::now().timestamp())
+        .bind(uri)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Uris of every item, or only of the ones without archived content, newest first
+    pub async fn get_uris_for_content(
+        &self,
+        missing: bool,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        let query = if missing {
+            "SELECT uri FROM items WHERE id NOT IN (SELECT item_id FROM item_content) ORDER BY time_added DESC"
+        } else {
+            "SELECT uri FROM items ORDER BY time_added DESC"
+        };
+        sqlx::query_scalar(query).fetch_all(&self.pool).await
     }
 
     pub async fn get_item_tags(&self, item_id: i64) -> Result<Vec<Tags>, sqlx::Error> {
@@ -238,8 +615,9 @@ impl DB {
 
     pub async fn get_all_item_tags(
         &self,
+        status: Option<ItemStatus>,
     ) -> Result<Vec<(Vec<Tags>, ResearchItem)>, sqlx::Error> {
-        let items = self.get_all_items(None).await?;
+        let items = self.get_all_items(None, status).await?;
         let mut item_tags = Vec::<(Vec<Tags>, ResearchItem)>::new();
         for item in items.iter().filter(|i| i.id.is_some()) {
             let tags = self.get_item_tags(item.id.unwrap()).await?;
@@ -254,24 +632,194 @@ impl DB {
             .await
     }
 
-    pub async fn get_secrets(&self) -> Result<Secrets, sqlx::Error> {
-        let row = sqlx::query_as::<_, Secrets>("SELECT * FROM secrets")
+    /// Every tag with the number of items using it, most used first
+    pub async fn get_tag_counts(&self) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        sqlx::query_as(
+            "SELECT tags.tag_name, COUNT(item_tags.item_id) AS count FROM tags
+            LEFT JOIN item_tags ON item_tags.tag_name = tags.tag_name
+            GROUP BY tags.tag_name ORDER BY count DESC, tags.tag_name",
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    pub async fn tag_exists(&self, tag: &str) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM tags WHERE tag_name = ?)")
+            .bind(tag)
+            .fetch_one(&self.pool)
+            .await
+    }
+
+    /// The tag and its descendants, like `ml`, `ml/nlp` and `ml/nlp/transformers`
+    pub async fn get_tag_tree(&self, tag: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT tag_name FROM tags WHERE tag_name = ? OR (tag_name >= ? AND tag_name < ?)
+            ORDER BY tag_name",
+        )
+        .bind(tag)
+        .bind(format!("{tag}/"))
+        .bind(format!("{tag}0"))
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Moves the items of every source tag to `target` and removes the sources, returns the
+    /// number of items that were tagged with a source, counting items with several sources once
+    pub async fn merge_tags(
+        &self,
+        sources: &[String],
+        target: &str,
+    ) -> Result<u64, sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+        let mut items = BTreeSet::new();
+        for source in sources.iter().filter(|source| *source != target) {
+            items.extend(move_tag(&mut tx, source, target).await?);
+        }
+        tx.commit().await?;
+        Ok(items.len() as u64)
+    }
+
+    /// Renames the tag and its descendants, `ml/nlp` becomes `new/nlp` when `ml` is renamed to
+    /// `new`. Returns the number of items that had one of them.
+    pub async fn rename_tag(&self, old: &str, new: &str) -> Result<u64, sqlx::Error> {
+        let tree = self.get_tag_tree(old).await?;
+        let mut tx = self.pool.begin().await?;
+        let mut items = BTreeSet::new();
+        for tag in &tree {
+            let target = format!("{new}{}", &tag[old.len()..]);
+            items.extend(move_tag(&mut tx, tag, &target).await?);
+        }
+        tx.commit().await?;
+        Ok(items.len() as u64)
+    }
+
+    /// Removes the tag and its descendants from every item, returns the number of items that had
+    /// one of them
+    pub async fn delete_tag(&self, tag: &str) -> Result<u64, sqlx::Error> {
+        let tree = self.get_tag_tree(tag).await?;
+        let mut tx = self.pool.begin().await?;
+        let mut items = BTreeSet::new();
+        for tag in &tree {
+            let tagged: Vec<i64> = sqlx::query_scalar(
+                "DELETE FROM item_tags WHERE tag_name = ? RETURNING item_id",
+            )
+            .bind(tag)
+            .fetch_all(&mut *tx)
+            .await?;
+            items.extend(tagged);
+            sqlx::query("DELETE FROM tags WHERE tag_name = ?")
+                .bind(tag)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(items.len() as u64)
+    }
+
+    /// Removes the tags no item uses, returns how many were removed
+    pub async fn prune_tags(&self) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(DELETE_ORPHAN_TAGS).execute(&self.pool).await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn create_collection(
+        &self,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<i64, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT INTO collections (name, description, time_created) VALUES (?, ?, ?)",
+        )
+        .bind(name)
+        .bind(description)
+        .bind(chrono::Utc::now().timestamp())
+        .execute(&self.pool)
+        .await?;
+        Ok(result.last_insert_rowid())
+    }
+
+    pub async fn get_collection(&self, name: &str) -> Result<Option<Collection>, sqlx::Error> {
+        sqlx::query_as::<_, Collection>("SELECT * FROM collections WHERE name = ?")
+            .bind(name)
             .fetch_optional(&self.pool)
+            .await
+    }
+
+    /// Every collection with its number of items, by name
+    pub async fn get_collections(&self) -> Result<Vec<(Collection, i64)>, sqlx::Error> {
+        let collections = sqlx::query_as::<_, Collection>(
+            "SELECT * FROM collections ORDER BY name COLLATE NOCASE",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let mut counts = Vec::with_capacity(collections.len());
+        for collection in collections {
+            let count = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM item_collections WHERE collection_id = ?",
+            )
+            .bind(collection.id)
+            .fetch_one(&self.pool)
             .await?;
-        Ok(Secrets::new(row))
+            counts.push((collection, count));
+        }
+        Ok(counts)
     }
 
-    pub async fn set_secret(&self, secrets: Secrets) -> Result<(), sqlx::Error> {
-        let _ = sqlx::query(
-            "UPDATE secrets SET pocket_consumer_key = ?, pocket_access_token = ? WHERE user_id = (?)",
+    /// Returns false when the item was already in the collection
+    pub async fn add_to_collection(
+        &self,
+        collection_id: i64,
+        item_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO item_collections (item_id, collection_id, time_added) VALUES (?, ?, ?)",
         )
-        .bind(secrets.pocket_consumer_key)
-        .bind(secrets.pocket_access_token)
-        .bind(DEFAULT_USER_ID)
+        .bind(item_id)
+        .bind(collection_id)
+        .bind(chrono::Utc::now().timestamp())
         .execute(&self.pool)
         .await?;
+        Ok(result.rows_affected() > 0)
+    }
 
-        Ok(())
+    /// Returns false when the item wasn't in the collection
+    pub async fn remove_from_collection(
+        &self,
+        collection_id: i64,
+        item_id: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let result =
+            sqlx::query("DELETE FROM item_collections WHERE item_id = ? AND collection_id = ?")
+                .bind(item_id)
+                .bind(collection_id)
+                .execute(&self.pool)
+                .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Items of the collection in the order they were added
+    pub async fn get_collection_items(
+        &self,
+        collection_id: i64,
+        status: Option<ItemStatus>,
+    ) -> Result<Vec<ResearchItem>, sqlx::Error> {
+        sqlx::query_as::<_, ResearchItem>(
+            "SELECT items.* FROM items JOIN item_collections ON items.id = item_collections.item_id WHERE item_collections.collection_id = ?1 AND (?2 IS NULL OR items.status = ?2) ORDER BY item_collections.time_added, item_collections.rowid",
+        )
+        .bind(collection_id)
+        .bind(status)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Names of the collections the item is in, by name
+    pub async fn get_item_collections(&self, item_id: i64) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT collections.name FROM collections JOIN item_collections ON collections.id = item_collections.collection_id WHERE item_collections.item_id = ? ORDER BY collections.name COLLATE NOCASE",
+        )
+        .bind(item_id)
+        .fetch_all(&self.pool)
+        .await
     }
 
     /// Comma delimited
@@ -290,9 +838,12 @@ impl DB {
 
         wtr.write_record(["id", "folder", "url", "title", "excerpt", "note", "tags", "created"])?;
 
-        let items = self.get_all_items(None).await?;
+        let items = self.get_all_items(None, None).await?;
         for item in items {
-            let tags = self.get_item_tags(item.id.expect("No ID fetched")).await?;
+            let item_id = item.id.expect("No ID fetched");
+            let tags = self.get_item_tags(item_id).await?;
+            let collections = self.get_item_collections(item_id).await?;
+            let folder = csv_folder(&collections, &tags);
             let tags = tags
                 .iter()
                 .map(|t| t.tag_name.clone())
@@ -300,7 +851,7 @@ impl DB {
                 .join(", ");
             wtr.write_record([
                 &item.id.unwrap().to_string(),
-                "Research",
+                &folder,
                 &item.uri,
                 &item.title,
                 &item.excerpt,
@@ -314,6 +865,31 @@ impl DB {
         Ok(())
     }
 
+    /// Export to a Netscape bookmark file at the provided file path, or to stdout if no path
+    /// is provided. Every browser as well as Pinboard, Raindrop and Pocket can import it.
+    pub async fn export_to_netscape(&self, file_path: Option<&str>) -> Result<(), ExportError> {
+        let mut wtr: Box<dyn io::Write> = match file_path {
+            Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout()),
+        };
+
+        let mut bookmarks = Vec::new();
+        for item in self.get_all_items(None, None).await? {
+            let tags = self.get_item_tags(item.id.expect("No ID fetched")).await?;
+            bookmarks.push(Bookmark {
+                url: item.uri,
+                title: item.title,
+                description: Some(item.excerpt).filter(|excerpt| !excerpt.is_empty()),
+                add_date: Some(item.time_added),
+                tags: tags.into_iter().map(|t| t.tag_name).collect(),
+            });
+        }
+
+        write_bookmarks(&mut wtr, &bookmarks)?;
+        wtr.flush()?;
+        Ok(())
+    }
+
     pub async fn mark_as_favorite(&self, item_id: i64, mark: bool) -> Result<(), sqlx::Error> {
         let _ = sqlx::query("UPDATE items SET favorite = ? WHERE id = ?")
             .bind(mark)
@@ -323,6 +899,68 @@ impl DB {
         Ok(())
     }
 
+    /// Archiving marks the item as read now, unarchiving moves it back to the unread list
+    pub async fn mark_as_archived(&self, item_id: i64, mark: bool) -> Result<(), sqlx::Error> {
+        let (status, time_read) = if mark {
+            (ItemStatus::Archived, Some(chrono::Utc::now().timestamp()))
+        } else {
+            (ItemStatus::Normal, None)
+        };
+        sqlx::query("UPDATE items SET status = ?, time_read = ? WHERE id = ?")
+            .bind(status)
+            .bind(time_read)
+            .bind(item_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Name of the provider the item was saved with
+    pub async fn get_item_provider(&self, item_id: i64) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query(
+            "SELECT providers.name FROM items JOIN providers ON items.provider_id = providers.id WHERE items.id = ?",
+        )
+        .bind(item_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(|r| r.get(0)))
+    }
+
+    /// Id of the item in its online provider
+    pub async fn get_item_remote_id(&self, item_id: i64) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar::<_, Option<i64>>("SELECT remote_id FROM items WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map(Option::flatten)
+    }
+
+    /// Account of the online provider the item was saved with
+    pub async fn get_item_account(&self, item_id: i64) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar::<_, Option<String>>("SELECT account FROM items WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(&self.pool)
+            .await
+            .map(Option::flatten)
+    }
+
+    /// Id of the item the provider account knows by `remote_id`
+    pub async fn get_item_id_by_remote_id(
+        &self,
+        provider_id: i64,
+        account: &str,
+        remote_id: i64,
+    ) -> Result<Option<i64>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT id FROM items WHERE provider_id = ? AND account = ? AND remote_id = ?",
+        )
+        .bind(provider_id)
+        .bind(account)
+        .bind(remote_id)
+        .fetch_optional(&self.pool)
+        .await
+    }
+
     pub async fn get_item_id(&self, uri: &str) -> Result<Option<i64>, sqlx::Error> {
         let row = sqlx::query("SELECT id FROM items WHERE uri = ?")
             .bind(uri)
@@ -331,6 +969,14 @@ impl DB {
         Ok(row.map(|r| r.get(0)))
     }
 
+    pub async fn get_item_uri(&self, item_id: i64) -> Result<Option<String>, sqlx::Error> {
+        let row = sqlx::query("SELECT uri FROM items WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(&self.pool)
+            .await?;
+        Ok(row.map(|r| r.get(0)))
+    }
+
     pub async fn update_notes(&self, uri: &str, notes: &str) -> Result<(), sqlx::Error> {
         sqlx::query("UPDATE items SET notes = ? WHERE uri = ?")
             .bind(notes)
@@ -341,6 +987,71 @@ impl DB {
     }
 }
 
+/// Moves the items of the source tag to `target` and removes the source, returns the items
+/// that were tagged with the source
+async fn move_tag(
+    tx: &mut SqliteConnection,
+    source: &str,
+    target: &str,
+) -> Result<Vec<i64>, sqlx::Error> {
+    sqlx::query("INSERT OR IGNORE INTO tags (tag_name) VALUES (?)")
+        .bind(target)
+        .execute(&mut *tx)
+        .await?;
+    let items = sqlx::query_scalar("SELECT item_id FROM item_tags WHERE tag_name = ?")
+        .bind(source)
+        .fetch_all(&mut *tx)
+        .await?;
+    // Items already tagged with the target keep their row and lose the source one
+    sqlx::query("UPDATE OR IGNORE item_tags SET tag_name = ? WHERE tag_name = ?")
+        .bind(target)
+        .bind(source)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM item_tags WHERE tag_name = ?")
+        .bind(source)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("DELETE FROM tags WHERE tag_name = ?")
+        .bind(source)
+        .execute(&mut *tx)
+        .await?;
+    Ok(items)
+}
+
+/// Raindrop folder of an item in the CSV export. Raindrop items belong to a single folder, so
+/// this is the item's first collection, otherwise its deepest tag path like
+/// `Research/ml/transformers`, nested folders are separated by `/` in Raindrop too
+fn csv_folder(collections: &[String], tags: &[Tags]) -> String {
+    if let Some(collection) = collections.first() {
+        return collection.clone();
+    }
+    let deepest = tags
+        .iter()
+        .map(|t| t.tag_name.as_str())
+        .filter(|tag| tag.contains('/'))
+        .max_by(|a, b| {
+            let depth = |tag: &str| tag.matches('/').count();
+            depth(a).cmp(&depth(b)).then(b.cmp(a))
+        });
+    match deepest {
+        Some(tag) => format!("Research/{tag}"),
+        None => "Research".to_string(),
+    }
+}
+
+/// Turns free text into an FTS5 query where every word is a quoted prefix match,
+/// e.g. `rust "async` becomes `"rust"* """async"*`
+fn to_fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| word.trim_end_matches('*'))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 #[derive(Debug)]
 pub enum ExportError {
     Sqlx(sqlx::Error),
@@ -385,3 +1096,295 @@ impl Error for ExportError {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{to_fts_query, ItemEdit, ResearchItem, Tags, DB, DEFAULT_ACCOUNT};
+
+    fn tags(tags: &[&str]) -> Vec<Tags> {
+        tags.iter()
+            .map(|tag| Tags {
+                tag_name: tag.to_string(),
+            })
+            .collect()
+    }
+
+    async fn add_item(db: &DB, uri: &str, title: &str, item_tags: &[&str]) -> i64 {
+        let provider_id = db.get_provider_id("local").await.unwrap();
+        db.insert_item(
+            ResearchItem::test(uri, title),
+            &tags(item_tags),
+            provider_id,
+        )
+        .await
+        .unwrap();
+        db.get_item_id(uri).await.unwrap().unwrap()
+    }
+
+    async fn tag_names(db: &DB, item_id: i64) -> Vec<String> {
+        let mut tags: Vec<String> = db
+            .get_item_tags(item_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|tag| tag.tag_name)
+            .collect();
+        tags.sort();
+        tags
+    }
+
+    async fn search(db: &DB, query: &str) -> Vec<String> {
+        db.search(query, false, None)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|result| result.item.uri)
+            .collect()
+    }
+
+    #[test]
+    fn fts_query_quotes_every_word() {
+        assert_eq!(to_fts_query("rust \"async"), "\"rust\"* \"\"\"async\"*");
+        assert_eq!(
+            to_fts_query("a OR b NOT c"),
+            "\"a\"* \"OR\"* \"b\"* \"NOT\"* \"c\"*"
+        );
+        assert_eq!(
+            to_fts_query("NEAR(x y) title:z"),
+            "\"NEAR(x\"* \"y)\"* \"title:z\"*"
+        );
+        assert_eq!(to_fts_query("prefix* ** "), "\"prefix\"*");
+        assert_eq!(to_fts_query("  "), "");
+    }
+
+    #[tokio::test]
+    async fn search_accepts_query_syntax_as_text() {
+        let db = DB::in_memory().await;
+        add_item(&db, "https://a.example", "Rust \"async\" OR threads", &[]).await;
+        for query in ["\"async", "OR", "NOT (", "title:", "rust AND", "*", "-"] {
+            db.search(query, false, None).await.unwrap();
+        }
+        assert_eq!(search(&db, "\"async").await, ["https://a.example"]);
+        assert_eq!(search(&db, "rust OR").await, ["https://a.example"]);
+        assert!(search(&db, "rust NOT").await.is_empty());
+        // Raw queries are passed through, so broken syntax is an error
+        assert!(db.search("\"async", true, None).await.is_err());
+        assert_eq!(db.search("rust NOT go", true, None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn search_index_follows_item_changes() {
+        let db = DB::in_memory().await;
+        let id = add_item(&db, "https://a.example", "Attention", &["ml"]).await;
+        add_item(&db, "https://b.example", "Gardening", &[]).await;
+        assert_eq!(search(&db, "attent").await, ["https://a.example"]);
+        assert_eq!(search(&db, "ml").await, ["https://a.example"]);
+
+        let edit = ItemEdit {
+            title: Some("Transformers".to_string()),
+            add_tags: vec!["papers".to_string()],
+            remove_tags: vec!["ml".to_string()],
+            ..Default::default()
+        };
+        db.edit_item(id, &edit).await.unwrap();
+        assert!(search(&db, "attention").await.is_empty());
+        assert!(search(&db, "ml").await.is_empty());
+        assert_eq!(search(&db, "transformers").await, ["https://a.example"]);
+        assert_eq!(search(&db, "papers").await, ["https://a.example"]);
+
+        db.update_notes("https://a.example", "read the appendix")
+            .await
+            .unwrap();
+        assert_eq!(search(&db, "appendix").await, ["https://a.example"]);
+
+        db.delete_item(id).await.unwrap();
+        assert!(search(&db, "transformers").await.is_empty());
+        assert!(search(&db, "papers").await.is_empty());
+        assert_eq!(search(&db, "gardening").await, ["https://b.example"]);
+    }
+
+    #[tokio::test]
+    async fn upsert_updates_items_saved_again_with_a_new_id() {
+        let db = DB::in_memory().await;
+        let pocket = db.get_provider_id("pocket").await.unwrap();
+        let saved = db
+            .upsert_item(
+                ResearchItem::test("https://a.example", "Old").with_remote_id(10),
+                &tags(&["a"]),
+                pocket,
+            )
+            .await
+            .unwrap();
+        assert!(saved);
+        let saved = db
+            .upsert_item(
+                ResearchItem::test("https://a.example", "New").with_remote_id(11),
+                &tags(&["b"]),
+                pocket,
+            )
+            .await
+            .unwrap();
+        assert!(saved);
+
+        let id = db.get_item_id("https://a.example").await.unwrap().unwrap();
+        let items = db.get_all_items(None, None).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "New");
+        assert_eq!(tag_names(&db, id).await, ["b"]);
+    }
+
+    #[tokio::test]
+    async fn upsert_leaves_items_of_other_providers() {
+        let db = DB::in_memory().await;
+        let local_id = add_item(&db, "https://a.example", "Local", &["mine"]).await;
+        let pocket = db.get_provider_id("pocket").await.unwrap();
+        let saved = db
+            .upsert_item(
+                ResearchItem::test("https://a.example", "Pocket").with_remote_id(99),
+                &tags(&["x"]),
+                pocket,
+            )
+            .await
+            .unwrap();
+        assert!(!saved);
+
+        let items = db.get_all_items(None, None).await.unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].title, "Local");
+        assert_eq!(tag_names(&db, local_id).await, ["mine"]);
+    }
+
+    #[tokio::test]
+    async fn merge_counts_each_item_once() {
+        let db = DB::in_memory().await;
+        let both = add_item(&db, "https://a.example", "Both", &["ml", "ai"]).await;
+        let merged = add_item(&db, "https://b.example", "Merged", &["ml", "learning"]).await;
+        let one = add_item(&db, "https://c.example", "One", &["ai"]).await;
+        add_item(&db, "https://d.example", "Other", &["rust"]).await;
+
+        let sources = ["ml".to_string(), "ai".to_string(), "learning".to_string()];
+        let items = db.merge_tags(&sources, "learning").await.unwrap();
+        assert_eq!(items, 3);
+        assert_eq!(tag_names(&db, both).await, ["learning"]);
+        assert_eq!(tag_names(&db, merged).await, ["learning"]);
+        assert_eq!(tag_names(&db, one).await, ["learning"]);
+        assert!(!db.tag_exists("ml").await.unwrap());
+        assert!(!db.tag_exists("ai").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn rename_moves_descendant_tags() {
+        let db = DB::in_memory().await;
+        let root = add_item(&db, "https://a.example", "Root", &["ml"]).await;
+        let nested =
+            add_item(&db, "https://b.example", "Nested", &["ml/nlp/bert", "ml0"]).await;
+        let other = add_item(&db, "https://c.example", "Other", &["mlops", "ml/vision"]).await;
+
+        assert_eq!(
+            db.get_tag_tree("ml").await.unwrap(),
+            ["ml", "ml/nlp/bert", "ml/vision"]
+        );
+        let items = db.rename_tag("ml", "ai").await.unwrap();
+        assert_eq!(items, 3);
+        assert_eq!(tag_names(&db, root).await, ["ai"]);
+        assert_eq!(tag_names(&db, nested).await, ["ai/nlp/bert", "ml0"]);
+        assert_eq!(tag_names(&db, other).await, ["ai/vision", "mlops"]);
+        assert!(db.get_tag_tree("ml").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn delete_removes_descendant_tags() {
+        let db = DB::in_memory().await;
+        let both = add_item(&db, "https://a.example", "Both", &["ml", "ml/nlp"]).await;
+        let nested =
+            add_item(&db, "https://b.example", "Nested", &["ml/nlp/bert", "ml0"]).await;
+        let other = add_item(&db, "https://c.example", "Other", &["mlops"]).await;
+
+        assert_eq!(db.delete_tag("ml").await.unwrap(), 2);
+        assert!(tag_names(&db, both).await.is_empty());
+        assert_eq!(tag_names(&db, nested).await, ["ml0"]);
+        assert_eq!(tag_names(&db, other).await, ["mlops"]);
+        assert!(db.get_tag_tree("ml").await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn remote_ids_are_kept_per_provider() {
+        let db = DB::in_memory().await;
+        let local_id = add_item(&db, "https://local.example", "Local", &[]).await;
+        let pocket = db.get_provider_id("pocket").await.unwrap();
+        let wallabag = db.get_provider_id("wallabag").await.unwrap();
+        for (provider_id, uri) in [
+            (pocket, "https://pocket.example"),
+            (wallabag, "https://wallabag.example"),
+        ] {
+            let saved = db
+                .upsert_item(
+                    ResearchItem::test(uri, "Remote").with_remote_id(local_id),
+                    &[],
+                    provider_id,
+                )
+                .await
+                .unwrap();
+            assert!(saved);
+        }
+        // A changed url is matched by the remote id
+        db.upsert_item(
+            ResearchItem::test("https://moved.example", "Moved").with_remote_id(local_id),
+            &[],
+            wallabag,
+        )
+        .await
+        .unwrap();
+
+        let local = db.get_item_uri(local_id).await.unwrap().unwrap();
+        assert_eq!(local, "https://local.example");
+        assert_eq!(db.get_item_remote_id(local_id).await.unwrap(), None);
+        let pocket_id = db
+            .get_item_id_by_remote_id(pocket, DEFAULT_ACCOUNT, local_id)
+            .await
+            .unwrap()
+            .unwrap();
+        let wallabag_id = db
+            .get_item_id_by_remote_id(wallabag, DEFAULT_ACCOUNT, local_id)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(pocket_id, local_id);
+        assert_ne!(wallabag_id, pocket_id);
+        assert_eq!(
+            db.get_item_remote_id(wallabag_id).await.unwrap(),
+            Some(local_id)
+        );
+        let uri = db.get_item_uri(wallabag_id).await.unwrap().unwrap();
+        assert_eq!(uri, "https://moved.example");
+        assert_eq!(db.get_all_items(None, None).await.unwrap().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn remote_ids_are_kept_per_account() {
+        let db = DB::in_memory().await;
+        let wallabag = db.get_provider_id("wallabag").await.unwrap();
+        for account in ["default", "work"] {
+            let item = ResearchItem {
+                account: Some(account.to_string()),
+                ..ResearchItem::test(&format!("https://{account}.example"), account)
+                    .with_remote_id(1)
+            };
+            assert!(db.upsert_item(item, &[], wallabag).await.unwrap());
+        }
+
+        for account in ["default", "work"] {
+            let id = db
+                .get_item_id_by_remote_id(wallabag, account, 1)
+                .await
+                .unwrap()
+                .unwrap();
+            let uri = db.get_item_uri(id).await.unwrap().unwrap();
+            assert_eq!(uri, format!("https://{account}.example"));
+            let item_account = db.get_item_account(id).await.unwrap();
+            assert_eq!(item_account.as_deref(), Some(account));
+        }
+        let local_id = add_item(&db, "https://local.example", "Local", &[]).await;
+        assert_eq!(db.get_item_account(local_id).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,317 @@
+use sqlx::{QueryBuilder, Sqlite};
+
+use crate::provider::pocket::api::ItemStatus;
+
+/// Everything between `://` and the next `/` of the item's uri
+const AUTHORITY: &str = "substr(substr(items.uri, instr(items.uri, '://') + 3), 1, instr(substr(items.uri, instr(items.uri, '://') + 3) || '/', '/') - 1)";
+
+/// Host of the item's uri, lowercased and without the port
+fn host() -> String {
+    format!("lower(substr({AUTHORITY}, 1, instr({AUTHORITY} || ':', ':') - 1))")
+}
+
+/// Filters over the items table, fields left unset don't filter anything. Every value is bound
+/// as a parameter, so they can come straight from the user.
+#[derive(Debug, Default, Clone)]
+pub struct ItemQuery {
+    /// Items tagged with all of these, or with one of their descendants
+    pub all_tags: Vec<String>,
+    /// Items tagged with at least one of these, or with one of their descendants
+    pub any_tags: Vec<String>,
+    /// Items tagged with none of these, nor with their descendants
+    pub exclude_tags: Vec<String>,
+    pub untagged: bool,
+    /// Host of the uri, subdomains match too, so `example.com` matches `www.example.com`
+    pub domain: Option<String>,
+    /// Name of the provider, like pocket or local
+    pub provider: Option<String>,
+    /// Language code, `en` also matches `en-US`
+    pub lang: Option<String>,
+    /// Unix time the item was added at or after
+    pub since: Option<i64>,
+    /// Unix time the item was added before
+    pub until: Option<i64>,
+    pub favorite: Option<bool>,
+    pub status: Option<ItemStatus>,
+    pub has_notes: bool,
+    pub order: ItemOrder,
+    pub reverse: bool,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub enum ItemOrder {
+    /// Newest first
+    #[default]
+    Added,
+    Title,
+    /// Host without `www.`, then title
+    Domain,
+}
+
+impl ItemQuery {
+    /// `SELECT items.*` with the filters, order, limit and offset
+    pub(super) fn select(&self) -> QueryBuilder<'_, Sqlite> {
+        let mut query = QueryBuilder::new("SELECT items.* FROM items WHERE 1 = 1");
+        self.push_filters(&mut query);
+
+        let direction = |ascending: bool| {
+            if ascending != self.reverse {
+                "ASC"
+            } else {
+                "DESC"
+            }
+        };
+        let host = host();
+        let order = match self.order {
+            ItemOrder::Added => format!(
+                "items.time_added {0}, items.id {0}",
+                direction(false)
+            ),
+            ItemOrder::Title => format!("lower(items.title) {}", direction(true)),
+            ItemOrder::Domain => format!(
+                "CASE WHEN {host} LIKE 'www.%' THEN substr({host}, 5) ELSE {host} END {0}, lower(items.title) {0}",
+                direction(true)
+            ),
+        };
+        query.push(format!(" ORDER BY {order}"));
+
+        // SQLite only takes an offset after a limit, -1 is no limit
+        if self.limit.is_some() || self.offset.is_some() {
+            query.push(" LIMIT ");
+            query.push_bind(self.limit.map_or(-1, |limit| limit as i64));
+            query.push(" OFFSET ");
+            query.push_bind(self.offset.unwrap_or(0) as i64);
+        }
+        query
+    }
+
+    /// Number of items matching the filters, ignoring the limit and offset
+    pub(super) fn count(&self) -> QueryBuilder<'_, Sqlite> {
+        let mut query = QueryBuilder::new("SELECT COUNT(*) FROM items WHERE 1 = 1");
+        self.push_filters(&mut query);
+        query
+    }
+
+    fn push_filters<'a>(&'a self, query: &mut QueryBuilder<'a, Sqlite>) {
+        for tag in &self.all_tags {
+            query.push(
+                " AND EXISTS (SELECT 1 FROM item_tags WHERE item_tags.item_id = items.id AND ",
+            );
+            push_tags_match(query, std::slice::from_ref(tag));
+            query.push(")");
+        }
+        if !self.any_tags.is_empty() {
+            query.push(
+                " AND EXISTS (SELECT 1 FROM item_tags WHERE item_tags.item_id = items.id AND ",
+            );
+            push_tags_match(query, &self.any_tags);
+            query.push(")");
+        }
+        if !self.exclude_tags.is_empty() {
+            query.push(
+                " AND NOT EXISTS (SELECT 1 FROM item_tags WHERE item_tags.item_id = items.id AND ",
+            );
+            push_tags_match(query, &self.exclude_tags);
+            query.push(")");
+        }
+        if self.untagged {
+            query.push(
+                " AND NOT EXISTS (SELECT 1 FROM item_tags WHERE item_tags.item_id = items.id)",
+            );
+        }
+        if let Some(domain) = &self.domain {
+            let domain = domain.trim().to_lowercase();
+            let host = host();
+            query.push(format!(" AND ({host} = "));
+            query.push_bind(domain.clone());
+            query.push(format!(" OR substr({host}, -"));
+            query.push_bind(domain.chars().count() as i64 + 1);
+            query.push(") = ");
+            query.push_bind(format!(".{domain}"));
+            query.push(")");
+        }
+        if let Some(provider) = &self.provider {
+            query.push(" AND items.provider_id = (SELECT id FROM providers WHERE name = ");
+            query.push_bind(provider);
+            query.push(")");
+        }
+        if let Some(lang) = &self.lang {
+            query.push(" AND (items.lang = ");
+            query.push_bind(lang);
+            query.push(" OR substr(items.lang, 1, ");
+            query.push_bind(lang.chars().count() as i64 + 1);
+            query.push(") = ");
+            query.push_bind(format!("{lang}-"));
+            query.push(")");
+        }
+        if let Some(since) = self.since {
+            query.push(" AND items.time_added >= ");
+            query.push_bind(since);
+        }
+        if let Some(until) = self.until {
+            query.push(" AND items.time_added < ");
+            query.push_bind(until);
+        }
+        if let Some(favorite) = self.favorite {
+            query.push(" AND items.favorite = ");
+            query.push_bind(favorite);
+        }
+        if let Some(status) = self.status {
+            query.push(" AND items.status = ");
+            query.push_bind(status);
+        }
+        if self.has_notes {
+            query.push(" AND trim(COALESCE(items.notes, '')) != ''");
+        }
+    }
+}
+
+/// Matches `item_tags.tag_name` against any of the tags or their descendants: `ml` also matches
+/// `ml/transformers`, the range from `ml/` up to `ml0` ('0' follows '/' in ascii), which is a
+/// lookup in the item_tags primary key
+fn push_tags_match<'a>(query: &mut QueryBuilder<'a, Sqlite>, tags: &'a [String]) {
+    query.push("(");
+    for (i, tag) in tags.iter().enumerate() {
+        if i > 0 {
+            query.push(" OR ");
+        }
+        query.push("item_tags.tag_name = ");
+        query.push_bind(tag);
+        query.push(" OR (item_tags.tag_name >= ");
+        query.push_bind(format!("{tag}/"));
+        query.push(" AND item_tags.tag_name < ");
+        query.push_bind(format!("{tag}0"));
+        query.push(")");
+    }
+    query.push(")");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ItemQuery;
+    use crate::db::{ResearchItem, Tags, DB};
+
+    /// Tags that would break a query built by pasting them into the SQL or a LIKE pattern
+    const HOSTILE_TAGS: [&str; 6] = [
+        "it's",
+        "100%",
+        "snake_case",
+        "\"quoted\"",
+        "x'); DROP TABLE items; --",
+        "back\\slash",
+    ];
+
+    async fn add_item(db: &DB, uri: &str, tags: &[&str]) {
+        let item = ResearchItem::test(uri, uri);
+        let tags: Vec<Tags> = tags
+            .iter()
+            .map(|tag| Tags {
+                tag_name: tag.to_string(),
+            })
+            .collect();
+        let provider_id = db.get_provider_id("local").await.unwrap();
+        db.insert_item(item, &tags, provider_id).await.unwrap();
+    }
+
+    async fn uris(db: &DB, query: ItemQuery) -> Vec<String> {
+        let mut uris: Vec<String> = db
+            .query_items(&query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.uri)
+            .collect();
+        uris.sort();
+        assert_eq!(db.count_items(&query).await.unwrap(), uris.len() as i64);
+        uris
+    }
+
+    async fn hostile_db() -> DB {
+        let db = DB::in_memory().await;
+        for (i, tag) in HOSTILE_TAGS.iter().enumerate() {
+            add_item(&db, &format!("https://{i}.example"), &[tag]).await;
+        }
+        add_item(&db, "https://plain.example", &["plain", "100"]).await;
+        db
+    }
+
+    #[tokio::test]
+    async fn hostile_tags_only_match_themselves() {
+        let db = hostile_db().await;
+        for (i, tag) in HOSTILE_TAGS.iter().enumerate() {
+            let query = ItemQuery {
+                all_tags: vec![tag.to_string()],
+                ..Default::default()
+            };
+            assert_eq!(
+                uris(&db, query).await,
+                [format!("https://{i}.example")],
+                "{tag}"
+            );
+        }
+        assert_eq!(uris(&db, ItemQuery::default()).await.len(), 7);
+    }
+
+    #[tokio::test]
+    async fn wildcards_are_literal() {
+        let db = hostile_db().await;
+        for tag in ["%", "_", "*", "10_%"] {
+            let query = ItemQuery {
+                any_tags: vec![tag.to_string()],
+                ..Default::default()
+            };
+            assert!(uris(&db, query).await.is_empty(), "{tag}");
+        }
+        let query = ItemQuery {
+            exclude_tags: vec!["%".to_string()],
+            domain: Some("%".to_string()),
+            ..Default::default()
+        };
+        assert!(uris(&db, query).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn hostile_tags_can_be_excluded_and_combined() {
+        let db = hostile_db().await;
+        let query = ItemQuery {
+            any_tags: vec!["it's".to_string(), "100%".to_string()],
+            exclude_tags: vec!["100%".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(uris(&db, query).await, ["https://0.example"]);
+
+        let query = ItemQuery {
+            exclude_tags: HOSTILE_TAGS.iter().map(|tag| tag.to_string()).collect(),
+            provider: Some("local' OR '1' = '1".to_string()),
+            ..Default::default()
+        };
+        assert!(uris(&db, query).await.is_empty());
+
+        let query = ItemQuery {
+            exclude_tags: HOSTILE_TAGS.iter().map(|tag| tag.to_string()).collect(),
+            lang: Some("en' --".to_string()),
+            ..Default::default()
+        };
+        assert!(uris(&db, query).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn descendants_match_their_parent_tag() {
+        let db = DB::in_memory().await;
+        add_item(&db, "https://a.example", &["ml/transformers"]).await;
+        add_item(&db, "https://b.example", &["ml0"]).await;
+        add_item(&db, "https://c.example", &["ml%/x"]).await;
+        let query = ItemQuery {
+            all_tags: vec!["ml".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(uris(&db, query).await, ["https://a.example"]);
+        let query = ItemQuery {
+            all_tags: vec!["ml%".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(uris(&db, query).await, ["https://c.example"]);
+    }
+}
@@ -0,0 +1,264 @@
+use sqlx::Row;
+
+use super::{Secrets, DB, DEFAULT_ACCOUNT};
+use crate::util::crypto::{self, CryptoError, KeySource, SecretsKey};
+
+/// Encrypted into secrets_key.key_check to recognize the key
+const KEY_CHECK: &str = "research";
+const KEY_CHECK_FIELD: &str = "key_check";
+
+fn crypto_error(e: CryptoError) -> sqlx::Error {
+    sqlx::Error::Decode(Box::new(e))
+}
+
+/// Encrypted values are bound to their provider, account and key, so a value can't be copied
+/// to another one. Each part is prefixed with its length, names can hold any separator.
+fn field(provider: &str, account: &str, key: &str) -> String {
+    [provider, account, key]
+        .map(|part| format!("{}:{part}", part.len()))
+        .concat()
+}
+
+impl DB {
+    /// The stored secrets of the account, decrypted, without values when it was never
+    /// authenticated
+    pub async fn get_secrets(
+        &self,
+        provider: &str,
+        account: &str,
+    ) -> Result<Secrets, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT key, value FROM provider_accounts
+            WHERE provider_id = (SELECT id FROM providers WHERE name = ?) AND account = ?",
+        )
+        .bind(provider)
+        .bind(account)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut secrets = Secrets::new(provider, account);
+        for row in rows {
+            let key: String = row.get("key");
+            let stored: String = row.get("value");
+            // Plaintext values are encrypted when migrating, one left was put there by hand
+            if !crypto::is_encrypted(&stored) {
+                return Err(crypto_error(CryptoError::Malformed));
+            }
+            // The key check passed, so a value that doesn't open was tampered with
+            let value = self
+                .secrets_key()
+                .await?
+                .decrypt(&field(provider, account, &key), &stored)
+                .map_err(crypto_error)?
+                .ok_or(CryptoError::Malformed)
+                .map_err(crypto_error)?;
+            secrets.values.insert(key, value);
+        }
+        Ok(secrets)
+    }
+
+    /// Encrypts the values saved in plaintext by releases before the secrets were encrypted,
+    /// returns how many there were. The key is only derived when there are some.
+    pub(super) async fn encrypt_plaintext_secrets(&self) -> Result<usize, sqlx::Error> {
+        let rows = sqlx::query(
+            "SELECT provider_id, providers.name AS provider, account, key, value
+            FROM provider_accounts JOIN providers ON providers.id = provider_id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        let plaintext: Vec<_> = rows
+            .iter()
+            .filter(|row| !crypto::is_encrypted(row.get("value")))
+            .collect();
+        if plaintext.is_empty() {
+            return Ok(0);
+        }
+
+        let secrets_key = self.secrets_key().await?;
+        let mut tx = self.pool.begin().await?;
+        for row in &plaintext {
+            let provider: &str = row.get("provider");
+            let account: &str = row.get("account");
+            let key: &str = row.get("key");
+            let value = secrets_key.encrypt(&field(provider, account, key), row.get("value"));
+            sqlx::query(
+                "UPDATE provider_accounts SET value = ?
+                WHERE provider_id = ? AND account = ? AND key = ?",
+            )
+            .bind(value)
+            .bind(row.get::<i64, _>("provider_id"))
+            .bind(account)
+            .bind(key)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(plaintext.len())
+    }
+
+    /// Saves the values of the secrets encrypted, keeping the stored values of the others
+    pub async fn set_secret(&self, secrets: Secrets) -> Result<(), sqlx::Error> {
+        let provider_id = self.get_provider_id(&secrets.provider).await?;
+        // Outside of the transaction, the first time it saves the salt
+        let secrets_key = self.secrets_key().await?;
+        let mut tx = self.pool.begin().await?;
+        for (key, value) in &secrets.values {
+            let value =
+                secrets_key.encrypt(&field(&secrets.provider, &secrets.account, key), value);
+            sqlx::query(
+                "INSERT INTO provider_accounts (provider_id, account, key, value) VALUES (?, ?, ?, ?)
+                ON CONFLICT(provider_id, account, key) DO UPDATE SET value = excluded.value",
+            )
+            .bind(provider_id)
+            .bind(&secrets.account)
+            .bind(key)
+            .bind(value)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await
+    }
+
+    /// Keys of the values saved for the account, without decrypting them
+    pub async fn get_secret_keys(
+        &self,
+        provider: &str,
+        account: &str,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT key FROM provider_accounts
+            WHERE provider_id = (SELECT id FROM providers WHERE name = ?) AND account = ?
+            ORDER BY key",
+        )
+        .bind(provider)
+        .bind(account)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Accounts of the provider with saved secrets, the default one first
+    pub async fn get_accounts(&self, provider: &str) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT DISTINCT account FROM provider_accounts
+            WHERE provider_id = (SELECT id FROM providers WHERE name = ?)
+            ORDER BY account != ?, account",
+        )
+        .bind(provider)
+        .bind(DEFAULT_ACCOUNT)
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// The key of this database, derived once per process. The first time secrets are saved
+    /// a salt is picked and the source, passphrase or keyfile, is recorded.
+    async fn secrets_key(&self) -> Result<&SecretsKey, sqlx::Error> {
+        if let Some(key) = self.secrets_key.get() {
+            return Ok(key);
+        }
+
+        let key = loop {
+            let row = sqlx::query("SELECT source, salt, key_check FROM secrets_key")
+                .fetch_optional(&self.pool)
+                .await?;
+            if let Some(row) = row {
+                let source = KeySource::parse(row.get("source"))
+                    .ok_or(CryptoError::Malformed)
+                    .map_err(crypto_error)?;
+                let key =
+                    SecretsKey::derive(source, row.get("salt"), false).map_err(crypto_error)?;
+                let check = key
+                    .decrypt(KEY_CHECK_FIELD, row.get("key_check"))
+                    .map_err(crypto_error)?;
+                if check.as_deref() != Some(KEY_CHECK) {
+                    return Err(crypto_error(CryptoError::WrongKey(source)));
+                }
+                break key;
+            }
+
+            let source = KeySource::from_env();
+            let salt = crypto::new_salt();
+            let key = SecretsKey::derive(source, &salt, true).map_err(crypto_error)?;
+            let inserted = sqlx::query(
+                "INSERT INTO secrets_key (id, source, salt, key_check) VALUES (0, ?, ?, ?)
+                ON CONFLICT DO NOTHING",
+            )
+            .bind(source.as_str())
+            .bind(salt)
+            .bind(key.encrypt(KEY_CHECK_FIELD, KEY_CHECK))
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+            // Otherwise another process got there first, load its key
+            if inserted == 1 {
+                break key;
+            }
+        };
+        Ok(self.secrets_key.get_or_init(|| key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db::{Secrets, DB};
+    use crate::util::crypto;
+
+    async fn stored_values(db: &DB) -> Vec<String> {
+        sqlx::query_scalar("SELECT value FROM provider_accounts ORDER BY key")
+            .fetch_all(&db.pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn plaintext_secrets_are_encrypted_when_migrating() {
+        let db = DB::in_memory().await;
+        db.set_secret(Secrets::new("raindrop", "work").with("access_token", "encrypted"))
+            .await
+            .unwrap();
+        // Saved by a release from before the encryption
+        sqlx::query(
+            "INSERT INTO provider_accounts (provider_id, account, key, value)
+            SELECT id, 'default', key, value FROM providers,
+                (SELECT 'access_token' AS key, 'token' AS value
+                UNION ALL SELECT 'consumer_key', 'consumer')
+            WHERE name = 'pocket'",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        assert!(db.get_secrets("pocket", "default").await.is_err());
+
+        db.migrate("sqlite::memory:").await.unwrap();
+        assert!(stored_values(&db)
+            .await
+            .iter()
+            .all(|value| crypto::is_encrypted(value)));
+        let pocket = db.get_secrets("pocket", "default").await.unwrap();
+        assert_eq!(pocket.get("access_token"), Some("token"));
+        assert_eq!(pocket.get("consumer_key"), Some("consumer"));
+        let raindrop = db.get_secrets("raindrop", "work").await.unwrap();
+        assert_eq!(raindrop.get("access_token"), Some("encrypted"));
+
+        let encrypted = stored_values(&db).await;
+        assert_eq!(db.encrypt_plaintext_secrets().await.unwrap(), 0);
+        assert_eq!(stored_values(&db).await, encrypted);
+    }
+
+    #[tokio::test]
+    async fn secrets_copied_to_another_account_are_rejected() {
+        let db = DB::in_memory().await;
+        db.set_secret(Secrets::new("pocket", "work").with("access_token", "work token"))
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO provider_accounts (provider_id, account, key, value)
+            SELECT provider_id, 'default', key, value FROM provider_accounts",
+        )
+        .execute(&db.pool)
+        .await
+        .unwrap();
+        let work = db.get_secrets("pocket", "work").await.unwrap();
+        assert_eq!(work.get("access_token"), Some("work token"));
+        assert!(db.get_secrets("pocket", "default").await.is_err());
+    }
+}
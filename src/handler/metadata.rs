@@ -0,0 +1,318 @@
+use scraper::{Html, Selector};
+use serde_json::Value;
+use url::Url;
+
+use crate::db::ItemDetails;
+use crate::util::parse_iso_date;
+use crate::util::readability::ArticleContent;
+
+#[derive(Debug)]
+pub struct WebpageMetadata {
+    pub title: String,
+    pub description: String,
+    pub lang: Option<String>,
+    pub details: ItemDetails,
+    /// Readable article body, only for html pages
+    pub content: Option<ArticleContent>,
+}
+
+/// Reads the OpenGraph, Twitter card and JSON-LD metadata of the page, falling back to
+/// `<title>` and `meta[name=description]`. Relative urls are resolved against `url`.
+pub fn extract_metadata(document: &Html, url: &Url) -> WebpageMetadata {
+    let ld = LinkedData::new(document);
+
+    let title = meta(document, "og:title")
+        .or_else(|| meta(document, "twitter:title"))
+        .or_else(|| ld.string("headline"))
+        .or_else(|| ld.string("name"))
+        .or_else(|| extract_title(document))
+        .unwrap_or_default();
+    let description = meta(document, "og:description")
+        .or_else(|| meta(document, "twitter:description"))
+        .or_else(|| meta(document, "description"))
+        .or_else(|| ld.string("description"))
+        .unwrap_or_default();
+    let lang = select_attr(document, "html[lang]", "lang")
+        .or_else(|| ld.string("inLanguage"))
+        .or_else(|| meta(document, "og:locale").map(|locale| locale.replace('_', "-")));
+
+    let author = ld
+        .name("author")
+        .or_else(|| meta(document, "article:author"))
+        .or_else(|| meta(document, "author"))
+        .or_else(|| meta(document, "twitter:creator"));
+    let published = meta(document, "article:published_time")
+        .or_else(|| ld.string("datePublished"))
+        .or_else(|| meta(document, "date"))
+        .and_then(|date| parse_iso_date(&date));
+    let site_name = meta(document, "og:site_name")
+        .or_else(|| ld.name("publisher"))
+        .or_else(|| meta(document, "application-name"));
+    let canonical_url = select_attr(document, "link[rel='canonical']", "href")
+        .or_else(|| meta(document, "og:url"))
+        .and_then(|canonical| url.join(&canonical).ok())
+        .map(String::from);
+    let image_url = meta(document, "og:image")
+        .or_else(|| meta(document, "og:image:url"))
+        .or_else(|| meta(document, "twitter:image"))
+        .or_else(|| ld.image())
+        .and_then(|image| url.join(&image).ok())
+        .map(String::from);
+
+    WebpageMetadata {
+        title,
+        description,
+        lang,
+        details: ItemDetails {
+            author,
+            published,
+            site_name,
+            canonical_url,
+            image_url,
+        },
+        content: None,
+    }
+}
+
+fn extract_title(document: &Html) -> Option<String> {
+    let title_selector = Selector::parse("title").unwrap();
+    document
+        .select(&title_selector)
+        .next()
+        .map(|el| el.text().collect::<String>())
+        .and_then(non_empty)
+}
+
+/// Content of `<meta property=key>` (OpenGraph) or `<meta name=key>` (everything else)
+fn meta(document: &Html, key: &str) -> Option<String> {
+    let selector = Selector::parse("meta[content]").unwrap();
+    document
+        .select(&selector)
+        .find(|el| {
+            let value = el.value();
+            [value.attr("property"), value.attr("name")]
+                .into_iter()
+                .flatten()
+                .any(|name| name.eq_ignore_ascii_case(key))
+        })
+        .and_then(|el| el.value().attr("content"))
+        .and_then(|content| non_empty(content.to_string()))
+}
+
+fn select_attr(document: &Html, selector: &str, attr: &str) -> Option<String> {
+    let selector = Selector::parse(selector).unwrap();
+    document
+        .select(&selector)
+        .find_map(|el| el.value().attr(attr))
+        .and_then(|value| non_empty(value.to_string()))
+}
+
+fn non_empty(s: String) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// The main schema.org object of the page's `<script type="application/ld+json">` blocks
+struct LinkedData(Option<Value>);
+
+/// Types whose properties describe the page itself
+const PAGE_TYPES: &[&str] = &[
+    "Article",
+    "NewsArticle",
+    "BlogPosting",
+    "TechArticle",
+    "ScholarlyArticle",
+    "Report",
+    "WebPage",
+    "VideoObject",
+];
+
+impl LinkedData {
+    fn new(document: &Html) -> Self {
+        let selector = Selector::parse("script[type='application/ld+json']").unwrap();
+        let mut objects = Vec::new();
+        for script in document.select(&selector) {
+            if let Ok(value) = serde_json::from_str::<Value>(&script.text().collect::<String>())
+            {
+                flatten_graph(value, &mut objects);
+            }
+        }
+
+        let is_page = |object: &Value| match &object["@type"] {
+            Value::String(t) => PAGE_TYPES.contains(&t.as_str()),
+            Value::Array(types) => types
+                .iter()
+                .any(|t| t.as_str().is_some_and(|t| PAGE_TYPES.contains(&t))),
+            _ => false,
+        };
+        // Prefer articles over the generic WebPage object
+        let main = objects
+            .iter()
+            .find(|object| is_page(object) && object["@type"] != "WebPage")
+            .or_else(|| objects.iter().find(|object| is_page(object)))
+            .cloned();
+        LinkedData(main)
+    }
+
+    fn string(&self, key: &str) -> Option<String> {
+        self.0.as_ref()?[key]
+            .as_str()
+            .map(String::from)
+            .and_then(non_empty)
+    }
+
+    /// A person or organization, given as a name or an object with a name, possibly in a list
+    fn name(&self, key: &str) -> Option<String> {
+        let value = &self.0.as_ref()?[key];
+        let value = match value {
+            Value::Array(values) => values.first()?,
+            value => value,
+        };
+        match value {
+            Value::String(name) => non_empty(name.clone()),
+            Value::Object(object) => object
+                .get("name")?
+                .as_str()
+                .map(String::from)
+                .and_then(non_empty),
+            _ => None,
+        }
+    }
+
+    /// Given as a url, an ImageObject or a list of either
+    fn image(&self) -> Option<String> {
+        let value = &self.0.as_ref()?["image"];
+        let value = match value {
+            Value::Array(values) => values.first()?,
+            value => value,
+        };
+        match value {
+            Value::String(url) => non_empty(url.clone()),
+            Value::Object(object) => object.get("url")?.as_str().map(String::from),
+            _ => None,
+        }
+    }
+}
+
+fn flatten_graph(value: Value, objects: &mut Vec<Value>) {
+    match value {
+        Value::Array(values) => {
+            for value in values {
+                flatten_graph(value, objects);
+            }
+        }
+        Value::Object(mut object) => {
+            if let Some(graph) = object.remove("@graph") {
+                flatten_graph(graph, objects);
+            }
+            objects.push(Value::Object(object));
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::extract_metadata;
+    use scraper::Html;
+    use url::Url;
+
+    const OPEN_GRAPH: &str = r#"<html lang="en-GB"><head>
+  <title>Ignored title | Site</title>
+  <meta property="og:title" content="  The OpenGraph title ">
+  <meta property="og:description" content="The OpenGraph description">
+  <meta name="description" content="Ignored description">
+  <meta property="og:site_name" content="The Site">
+  <meta property="article:author" content="Ada Lovelace">
+  <meta property="article:published_time" content="2024-03-01T10:00:00Z">
+  <meta property="og:image" content="/images/cover.png">
+  <link rel="canonical" href="/posts/canonical">
+</head><body></body></html>"#;
+
+    /// A news site with its metadata only in JSON-LD, the article next to its WebPage
+    const LINKED_DATA: &str = r#"<html><head>
+  <title>Fallback title</title>
+  <script type="application/ld+json">{ not json</script>
+  <script type="application/ld+json">{
+    "@context": "https://schema.org",
+    "@graph": [
+      { "@type": "WebPage", "name": "The page", "description": "Page description" },
+      {
+        "@type": ["NewsArticle"],
+        "headline": "The headline",
+        "description": "Article description",
+        "inLanguage": "fr",
+        "datePublished": "2023-05-02T09:00:00",
+        "author": [{ "@type": "Person", "name": "Grace Hopper" }, { "name": "Someone else" }],
+        "publisher": { "@type": "Organization", "name": "The Paper" },
+        "image": { "@type": "ImageObject", "url": "https://cdn.example/photo.jpg" }
+      }
+    ]
+  }</script>
+</head><body></body></html>"#;
+
+    const BARE: &str = r#"<html><head>
+  <title>
+    Only a title
+  </title>
+  <meta name="description" content="Only a description">
+  <meta property="og:locale" content="pt_BR">
+  <meta name="date" content="2024-01-31">
+  <meta name="author" content="">
+</head><body></body></html>"#;
+
+    fn url() -> Url {
+        Url::parse("https://site.example/posts/article?ref=feed").unwrap()
+    }
+
+    #[test]
+    fn open_graph_is_preferred() {
+        let metadata = extract_metadata(&Html::parse_document(OPEN_GRAPH), &url());
+        assert_eq!(metadata.title, "The OpenGraph title");
+        assert_eq!(metadata.description, "The OpenGraph description");
+        assert_eq!(metadata.lang.as_deref(), Some("en-GB"));
+        assert_eq!(metadata.details.author.as_deref(), Some("Ada Lovelace"));
+        assert_eq!(metadata.details.published, Some(1709287200));
+        assert_eq!(metadata.details.site_name.as_deref(), Some("The Site"));
+        assert_eq!(
+            metadata.details.canonical_url.as_deref(),
+            Some("https://site.example/posts/canonical")
+        );
+        assert_eq!(
+            metadata.details.image_url.as_deref(),
+            Some("https://site.example/images/cover.png")
+        );
+    }
+
+    #[test]
+    fn linked_data_describes_the_article() {
+        let metadata = extract_metadata(&Html::parse_document(LINKED_DATA), &url());
+        assert_eq!(metadata.title, "The headline");
+        assert_eq!(metadata.description, "Article description");
+        assert_eq!(metadata.lang.as_deref(), Some("fr"));
+        assert_eq!(metadata.details.author.as_deref(), Some("Grace Hopper"));
+        assert_eq!(metadata.details.published, Some(1683018000));
+        assert_eq!(metadata.details.site_name.as_deref(), Some("The Paper"));
+        assert_eq!(metadata.details.canonical_url, None);
+        assert_eq!(
+            metadata.details.image_url.as_deref(),
+            Some("https://cdn.example/photo.jpg")
+        );
+    }
+
+    #[test]
+    fn plain_tags_are_the_fallback() {
+        let metadata = extract_metadata(&Html::parse_document(BARE), &url());
+        assert_eq!(metadata.title, "Only a title");
+        assert_eq!(metadata.description, "Only a description");
+        assert_eq!(metadata.lang.as_deref(), Some("pt-BR"));
+        assert_eq!(metadata.details.author, None);
+        assert_eq!(metadata.details.published, Some(1706659200));
+        assert_eq!(metadata.details.site_name, None);
+        assert_eq!(metadata.details.image_url, None);
+
+        let empty = extract_metadata(&Html::parse_document("<html></html>"), &url());
+        assert_eq!(empty.title, "");
+        assert_eq!(empty.lang, None);
+    }
+}
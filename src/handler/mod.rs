@@ -1,18 +1,24 @@
+mod metadata;
 mod register;
 mod unregister;
 
 use reqwest::header::CONTENT_TYPE;
-use scraper::{Html, Selector};
+use scraper::Html;
+use std::error::Error;
+use std::fmt;
 use std::path::Path;
 use std::process::Command;
 
+pub use metadata::{extract_metadata, WebpageMetadata};
 pub use register::platform_register_url;
 pub use unregister::platform_unregister_url;
 use url::Url;
 
 use crate::{
-    db::{Tags, DB},
-    provider::{local::LocalItem, Insertable, OnlineProvider, ProviderPocket},
+    db::{ItemDetails, Tags, DB, DEFAULT_ACCOUNT},
+    provider::{self, local::LocalItem, Insertable, OnlineProvider},
+    util::pdf::extract_pdf,
+    util::readability::{extract_article, ArticleContent},
 };
 
 pub async fn handle_url(url: &str) -> Result<(), sqlx::Error> {
@@ -61,15 +67,11 @@ pub async fn handle_url(url: &str) -> Result<(), sqlx::Error> {
     Ok(())
 }
 
-#[derive(Debug)]
-pub struct WebpageMetadata {
-    pub title: String,
-    pub description: String,
-}
-
 pub async fn fetch_metadata(url: &str) -> Result<WebpageMetadata, Box<dyn std::error::Error>> {
     // Make the HTTP request
     let response = reqwest::get(url).await?;
+    let final_url = response.url().clone();
+    let success = response.status().is_success();
 
     // Get the content type
     let content_type = response
@@ -84,45 +86,53 @@ pub async fn fetch_metadata(url: &str) -> Result<WebpageMetadata, Box<dyn std::e
         let html_content = response.text().await?;
         let document = Html::parse_document(&html_content);
 
-        let title = extract_title(&document);
-        let description = extract_description(&document);
-
-        Ok(WebpageMetadata { title, description })
+        let mut metadata = extract_metadata(&document, &final_url);
+        // Error pages are not worth archiving
+        metadata.content = extract_article(&document, Some(&final_url)).filter(|_| success);
+        Ok(metadata)
     } else {
         // For non-HTML content, use the URL's filename and MIME type
         let file_name = Path::new(url)
             .file_name()
             .and_then(|f| f.to_str())
-            .unwrap_or("");
-        let mime_type = content_type.split(';').next().unwrap_or("");
+            .unwrap_or("")
+            .to_string();
+        let mime_type = content_type.split(';').next().unwrap_or("").to_string();
 
-        Ok(WebpageMetadata {
-            title: file_name.to_string(),
-            description: format!("File type: {}", mime_type),
+        let is_pdf = mime_type == "application/pdf"
+            || (mime_type == "application/octet-stream" && file_name.ends_with(".pdf"));
+        let pdf = if is_pdf && success {
+            let bytes = response.bytes().await?;
+            tokio::task::spawn_blocking(move || extract_pdf(&bytes)).await?
+        } else {
+            None
+        };
+
+        Ok(match pdf {
+            Some(pdf) => WebpageMetadata {
+                title: pdf.title.unwrap_or(file_name),
+                description: pdf
+                    .excerpt
+                    .unwrap_or_else(|| format!("File type: {}", mime_type)),
+                lang: pdf.lang,
+                details: ItemDetails {
+                    author: pdf.author,
+                    published: pdf.published,
+                    ..Default::default()
+                },
+                content: pdf.text.map(ArticleContent::from_text),
+            },
+            None => WebpageMetadata {
+                title: file_name,
+                description: format!("File type: {}", mime_type),
+                lang: None,
+                details: ItemDetails::default(),
+                content: None,
+            },
         })
     }
 }
 
-fn extract_title(document: &Html) -> String {
-    let title_selector = Selector::parse("title").unwrap();
-    document
-        .select(&title_selector)
-        .next()
-        .and_then(|el| el.text().next())
-        .unwrap_or("")
-        .to_string()
-}
-
-fn extract_description(document: &Html) -> String {
-    let description_selector = Selector::parse("meta[name='description']").unwrap();
-    document
-        .select(&description_selector)
-        .next()
-        .and_then(|el| el.value().attr("content"))
-        .unwrap_or("")
-        .to_string()
-}
-
 /// the url looks like research://save?url=https%3A%2F%2Fwww.rust-lang.org&provider=local&tags=rust,programming&db_path=/path/to/db
 async fn handle_research_url(parsed_url: Url) -> Result<(), sqlx::Error> {
     let query_params: Vec<(String, String)> = parsed_url
@@ -169,86 +179,22 @@ async fn handle_research_url(parsed_url: Url) -> Result<(), sqlx::Error> {
             err
         })?;
 
-    let provider_id = db
-        .get_provider_id(provider.unwrap_or(&"local".to_string()))
-        .await
-        .unwrap_or_else(|_| panic!("Failed to get provider ID for {:?}", provider));
-    println!("Provider ID: {:?}", provider_id);
-
-    // Fetch metadata from the URL
-    let metadata = fetch_metadata(url)
-        .await
-        .map_err(|e| sqlx::Error::Protocol(format!("Failed to fetch metadata: {}", e)))?;
-    println!("Metadata: {:?}", metadata);
-
-    let mut id: Option<i64> = None;
-
-    match provider {
-        Some(p) => match p.as_str() {
-            "local" => {}
-            "pocket" => {
-                let secrets = db.get_secrets().await?;
-                let consumer_key = secrets
-                    .pocket_consumer_key
-                    .expect("Missing pocket consumer key");
-                let access_token = secrets
-                    .pocket_access_token
-                    .expect("Missing pocket access token");
-                let provider = ProviderPocket {
-                    consumer_key,
-                    access_token: Some(access_token),
-                    ..Default::default()
-                };
-                id = provider
-                    .add_item(url, tags.clone().unwrap_or_default())
-                    .await
-                    .map_err(|e| {
-                        eprintln!("Failed to add item to pocket: {}", e);
-                        sqlx::Error::Protocol("Failed to add item to pocket".into())
-                    })?;
-            }
-            _ => {
-                eprintln!("Provider \"{:?}\" not supported", p);
-                return Err(sqlx::Error::Protocol("Provider not supported".into()));
-            }
-        },
-        None => {
-            eprintln!("Provider not specified using default provider \"local\"");
-        }
-    }
-
-    let tags: Vec<Tags> = tags
-        .unwrap_or_default()
-        .iter()
-        .map(|t| Tags {
-            tag_name: t.to_string(),
-        })
-        .collect();
+    let result = save_item(
+        &db,
+        url,
+        provider.map(|p| p.as_str()),
+        tags.clone().unwrap_or_default(),
+    )
+    .await;
+    let tags = tags.unwrap_or_default();
 
-    let local_item = LocalItem {
-        id,
-        uri: url.to_string(),
-        title: Some(metadata.title),
-        excerpt: Some(metadata.description),
-        time_added: chrono::Utc::now().timestamp(),
-        tags: tags.clone(),
-    };
-
-    println!("Inserting item into database");
-    let result = db
-        .insert_item(local_item.to_research_item(), &tags, provider_id)
-        .await;
-
-    let (title, message) = match result {
+    let (title, message) = match &result {
         Ok(_) => (
             "Research URL Handler - Success",
             format!(
                 "Successfully saved:\n{}\nTags: {} {}",
                 url.chars().take(50).collect::<String>(),
-                tags.iter()
-                    .map(|t| t.tag_name.clone())
-                    .collect::<Vec<_>>()
-                    .join(", "),
+                tags.join(", "),
                 provider.unwrap_or(&"None".to_string())
             ),
         ),
@@ -294,6 +240,171 @@ async fn handle_research_url(parsed_url: Url) -> Result<(), sqlx::Error> {
             .expect("Failed to send notification");
     }
 
-    println!("Item inserted successfully");
+    if result.is_ok() {
+        println!("Item inserted successfully");
+    }
     Ok(())
 }
+
+#[derive(Debug)]
+pub enum ItemError {
+    Sqlx(sqlx::Error),
+    /// The url to save isn't an http(s) one
+    InvalidUrl(String),
+    UnknownProvider(String),
+    /// The page to save couldn't be fetched
+    Fetch {
+        url: String,
+        message: String,
+    },
+    /// The online provider refused the request or couldn't be reached
+    Provider {
+        name: String,
+        message: String,
+    },
+}
+
+impl From<sqlx::Error> for ItemError {
+    fn from(e: sqlx::Error) -> Self {
+        ItemError::Sqlx(e)
+    }
+}
+
+impl fmt::Display for ItemError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ItemError::Sqlx(e) => e.fmt(f),
+            ItemError::InvalidUrl(url) => {
+                write!(f, "Invalid url \"{url}\", expected an http(s) one")
+            }
+            ItemError::UnknownProvider(name) => write!(f, "Provider \"{name}\" not supported"),
+            ItemError::Fetch { url, message } => write!(f, "Failed to fetch {url}: {message}"),
+            ItemError::Provider { name, message } => {
+                write!(f, "{name} request failed: {message}")
+            }
+        }
+    }
+}
+
+impl Error for ItemError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ItemError::Sqlx(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Saves the url with the given provider ("local" when not specified), fetching its metadata
+/// and adding it to the online provider first. Returns the id of the saved item
+pub async fn save_item(
+    db: &DB,
+    url: &str,
+    provider: Option<&str>,
+    tags: Vec<&str>,
+) -> Result<Option<i64>, ItemError> {
+    match Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {}
+        _ => return Err(ItemError::InvalidUrl(url.to_string())),
+    }
+    let name = provider.unwrap_or("local");
+    if name != "local" && provider::registration(name).is_none() {
+        return Err(ItemError::UnknownProvider(name.to_string()));
+    }
+    let provider_id = db.get_provider_id(name).await.map_err(|e| match e {
+        sqlx::Error::RowNotFound => ItemError::UnknownProvider(name.to_string()),
+        e => e.into(),
+    })?;
+
+    // Fetch metadata from the URL
+    let metadata = fetch_metadata(url).await.map_err(|e| ItemError::Fetch {
+        url: url.to_string(),
+        message: e.to_string(),
+    })?;
+
+    let mut remote_id: Option<i64> = None;
+    let mut account = None;
+
+    match provider {
+        None => {
+            eprintln!("Provider not specified using default provider \"local\"");
+        }
+        Some("local") => {}
+        Some(name) => {
+            let provider = online_provider(db, name, DEFAULT_ACCOUNT).await?;
+            remote_id = provider
+                .add_item(url, tags.clone())
+                .await
+                .map_err(|e| provider_error(name, e))?;
+            account = Some(DEFAULT_ACCOUNT.to_string());
+        }
+    }
+
+    let tags: Vec<Tags> = tags
+        .iter()
+        .map(|t| Tags {
+            tag_name: t.to_string(),
+        })
+        .collect();
+
+    let local_item = LocalItem {
+        remote_id,
+        account,
+        uri: url.to_string(),
+        title: Some(metadata.title),
+        excerpt: Some(metadata.description),
+        time_added: chrono::Utc::now().timestamp(),
+        tags: tags.clone(),
+        lang: metadata.lang,
+        details: metadata.details,
+    };
+
+    println!("Inserting item into database");
+    db.insert_item(local_item.to_research_item(), &tags, provider_id)
+        .await?;
+    if let Some(content) = &metadata.content {
+        db.save_item_content(url, content).await?;
+    }
+    Ok(db.get_item_id(url).await?)
+}
+
+/// Marks the item as favorite, in its online provider too
+pub async fn set_favorite(db: &DB, item_id: i64, mark: bool) -> Result<(), ItemError> {
+    let provider = db.get_item_provider(item_id).await?;
+    if let Some(name) = provider.as_deref().filter(|name| *name != "local") {
+        let remote_id =
+            db.get_item_remote_id(item_id)
+                .await?
+                .ok_or_else(|| ItemError::Provider {
+                    name: name.to_string(),
+                    message: format!("item {item_id} has no id in {name}"),
+                })?;
+        let account = db.get_item_account(item_id).await?;
+        online_provider(db, name, account.as_deref().unwrap_or(DEFAULT_ACCOUNT))
+            .await?
+            .mark_as_favorite(remote_id, mark)
+            .await
+            .map_err(|e| provider_error(name, e))?;
+    }
+    Ok(db.mark_as_favorite(item_id, mark).await?)
+}
+
+/// The registered provider with this name, using the account
+async fn online_provider(
+    db: &DB,
+    name: &str,
+    account: &str,
+) -> Result<Box<dyn OnlineProvider>, ItemError> {
+    let registration = provider::registration(name)
+        .ok_or_else(|| ItemError::UnknownProvider(name.to_string()))?;
+    (registration.connect)(db, account)
+        .await
+        .map_err(|e| provider_error(name, e))
+}
+
+fn provider_error(name: &str, e: Box<dyn Error>) -> ItemError {
+    ItemError::Provider {
+        name: name.to_string(),
+        message: e.to_string(),
+    }
+}
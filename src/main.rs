@@ -1,27 +1,40 @@
 use crate::assets::css::build_css;
-use crate::provider::{Insertable, OnlineProvider, ProviderPocket};
+use crate::provider::{
+    Insertable, OnlineProvider, ProviderPocket, ProviderRaindrop, ProviderWallabag,
+};
 use chrono_tz::Tz;
 use clap::Parser;
 use cli::{
-    AuthArgs, CliArgs, FetchArgs, LocalAddArgs, LocalCommands, LocalFavoriteArgs, NotesArgs,
-    PocketAddArgs, PocketCommands, PocketFavoriteArgs, Subcommands,
+    ArchiveArgs, AuthArgs, CliArgs, CollectionCommands, DbCommands, DeleteArgs, EditArgs,
+    FetchArgs, ImportFormat, ListFormat, ListSort, LocalAddArgs, LocalCommands,
+    LocalFavoriteArgs, NotesArgs, PocketAddArgs, PocketCommands, PocketFavoriteArgs,
+    ProvidersCommands, RaindropAuthArgs, RaindropCommands, ReadStateArgs, Subcommands,
+    TagSyncArgs, TagsCommands, WallabagCommands,
+};
+use db::{
+    Collection, ItemDetails, ItemEdit, ItemOrder, ItemQuery, ResearchItem, Secrets, Tags, DB,
+    DEFAULT_ACCOUNT,
 };
-use db::{ResearchItem, Tags, DB};
 use provider::local::LocalItem;
-use site::Site;
+use provider::pocket::api::ItemStatus;
+use site::{FeedOptions, Site};
 use sqlx::migrate::MigrateDatabase;
+use std::collections::{BTreeSet, HashSet};
 use std::env;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use tokio::fs::{create_dir, metadata, read_to_string, File};
+use tokio::fs::{create_dir, create_dir_all, metadata, read_to_string, File};
 use tokio::io::AsyncWriteExt;
-use util::absolute_path;
+use util::netscape::parse_bookmarks;
+use util::output::{self, ListedItem};
+use util::{absolute_path, tag_ancestors};
 
 mod assets;
 mod cli;
 mod db;
 mod handler;
 mod provider;
+mod server;
 mod site;
 mod util;
 
@@ -30,39 +43,116 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli_args = CliArgs::parse();
 
     match &cli_args.subcommand {
-        Some(Subcommands::Pocket { command }) => {
-            handle_pocket_command(command, &cli_args).await?
+        Some(Subcommands::Pocket { account, command }) => {
+            handle_pocket_command(command, account, &cli_args).await?
+        }
+        Some(Subcommands::Raindrop { account, command }) => {
+            handle_raindrop_command(command, account, &cli_args).await?
+        }
+        Some(Subcommands::Wallabag { account, command }) => {
+            handle_wallabag_command(command, account, &cli_args).await?
+        }
+        Some(Subcommands::Tags { command }) => handle_tags_command(command, &cli_args).await?,
+        Some(Subcommands::Collection { command }) => {
+            handle_collection_command(command, &cli_args).await?
+        }
+        Some(Subcommands::Db { command }) => handle_db_command(command, &cli_args).await?,
+        Some(Subcommands::Providers { command }) => {
+            handle_providers_command(command, &cli_args).await?
         }
         Some(Subcommands::Local { command }) => {
             handle_local_command(command, &cli_args).await?
         }
-        Some(Subcommands::Fetch { limit }) => handle_fetch_command(&cli_args, *limit).await?,
+        Some(Subcommands::Fetch {
+            limit,
+            sync,
+            no_content,
+            account,
+        }) => handle_fetch_command(&cli_args, account, *limit, *sync, !*no_content).await?,
         Some(Subcommands::List {
             tags,
             limit,
+            offset,
             favorite_only,
+            read_state,
+            filters,
+            timezone,
+            format,
+            fields,
+            sort,
+            reverse,
+        }) => {
+            let timezone = timezone
+                .as_ref()
+                .and_then(|tz_str| Tz::from_str(tz_str).ok());
+            let query = ItemQuery {
+                all_tags: tags.clone().unwrap_or_default(),
+                any_tags: filters.any_tag.clone(),
+                exclude_tags: filters.exclude_tag.clone(),
+                untagged: filters.untagged,
+                domain: filters.domain.clone(),
+                provider: filters.provider.clone(),
+                lang: filters.lang.clone(),
+                since: filters.since,
+                until: filters.until,
+                favorite: favorite_only.then_some(true),
+                status: read_state_filter(read_state),
+                has_notes: filters.has_notes,
+                order: match sort {
+                    None | Some(ListSort::Added) => ItemOrder::Added,
+                    Some(ListSort::Title) => ItemOrder::Title,
+                    Some(ListSort::Domain) => ItemOrder::Domain,
+                },
+                reverse: *reverse,
+                limit: *limit,
+                offset: *offset,
+            };
+            handle_list_command(&cli_args, &query, timezone, *format, fields).await?
+        }
+        Some(Subcommands::Search {
+            query,
+            limit,
+            raw,
             timezone,
         }) => {
-            let favorite = if *favorite_only { Some(true) } else { None };
             let timezone = timezone
                 .as_ref()
                 .and_then(|tz_str| Tz::from_str(tz_str).ok());
-            handle_list_command(&cli_args, tags.as_ref(), favorite, *limit, timezone).await?
+            handle_search_command(&cli_args, &query.join(" "), *raw, *limit, timezone).await?
         }
         Some(Subcommands::Init { path }) => handle_init_command(path, &cli_args).await?,
         Some(Subcommands::Generate {
             output,
             assets,
             download_tailwind,
+            read_state,
+            base_url,
+            feed_limit,
             timezone,
         }) => {
             let timezone = timezone
                 .as_ref()
                 .and_then(|tz_str| Tz::from_str(tz_str).ok());
-            handle_generate_command(output, assets, *download_tailwind, timezone, &cli_args)
-                .await?
+            let feed_options = base_url.as_ref().map(|base_url| FeedOptions {
+                base_url: base_url.clone(),
+                limit: *feed_limit,
+            });
+            handle_generate_command(
+                output,
+                assets,
+                *download_tailwind,
+                read_state_filter(read_state),
+                timezone,
+                feed_options.as_ref(),
+                &cli_args,
+            )
+            .await?
         }
-        Some(Subcommands::Export { raindrop, output }) => {
+        Some(Subcommands::Export {
+            raindrop,
+            netscape,
+            output,
+        }) => {
             if *raindrop {
                 let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
 
@@ -72,8 +162,20 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                     db.export_to_csv(Some(output)).await?;
                     println!("Exported to {output}");
                 }
+            } else if *netscape {
+                let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+
+                if output == "-" {
+                    db.export_to_netscape(None).await?;
+                } else {
+                    db.export_to_netscape(Some(output)).await?;
+                    println!("Exported to {output}");
+                }
             }
         }
+        Some(Subcommands::Import { format, file }) => {
+            handle_import_command(&cli_args, format, file).await?
+        }
         Some(Subcommands::Handle {
             register,
             unregister,
@@ -89,13 +191,35 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
         Some(Subcommands::Notes(NotesArgs { url, notes })) => {
             let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
-            if db.get_item_id(&url).await?.is_none() {
+            if db.get_item_id(url).await?.is_none() {
                 eprintln!("Item with URL {} not found in the database", url);
                 return Ok(());
             }
-            db.update_notes(&url, &notes).await?;
+            db.update_notes(url, notes).await?;
             println!("Notes updated successfully!");
         }
+        Some(Subcommands::Serve {
+            host,
+            port,
+            token,
+            allow_origin,
+        }) => {
+            let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+            server::serve(db, host, *port, token, allow_origin).await?
+        }
+        Some(Subcommands::Archive(args)) => {
+            handle_archive_command(&cli_args, args, true).await?
+        }
+        Some(Subcommands::Unarchive(args)) => {
+            handle_archive_command(&cli_args, args, false).await?
+        }
+        Some(Subcommands::Edit(args)) => handle_edit_command(&cli_args, args).await?,
+        Some(Subcommands::Delete(args)) => handle_delete_command(&cli_args, args).await?,
+        Some(Subcommands::ArchiveContent { missing }) => {
+            let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+            let uris = db.get_uris_for_content(*missing).await?;
+            archive_contents(&db, &uris).await?;
+        }
         None => {
             eprintln!("No subcommand provided");
             eprintln!("Please provide a subcommand");
@@ -105,6 +229,352 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+async fn handle_tags_command(
+    command: &TagsCommands,
+    cli_args: &CliArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    match command {
+        TagsCommands::List => {
+            for (tag, count) in db.get_tag_counts().await? {
+                println!("{count:>6}  {tag}");
+            }
+        }
+        TagsCommands::Rename { old, new, sync } => {
+            // Descendants are renamed along, `ml/nlp` becomes `<new>/nlp`
+            let renames: Vec<(String, String)> = db
+                .get_tag_tree(old)
+                .await?
+                .into_iter()
+                .map(|tag| {
+                    let target = format!("{new}{}", &tag[old.len()..]);
+                    (tag, target)
+                })
+                .collect();
+            if renames.is_empty() {
+                return Err(format!("Tag \"{old}\" not found").into());
+            }
+            for (_, target) in &renames {
+                if db.tag_exists(target).await? {
+                    return Err(format!(
+                        "Tag \"{target}\" already exists, use `tags merge` to combine them"
+                    )
+                    .into());
+                }
+            }
+            if let Some(provider) = tag_sync_provider(&db, sync).await? {
+                for (tag, target) in &renames {
+                    provider.rename_tag(tag, target).await?;
+                }
+            }
+            let items = db.rename_tag(old, new).await?;
+            println!("Renamed \"{old}\" to \"{new}\" on {items} items");
+        }
+        TagsCommands::Merge {
+            sources,
+            into,
+            sync,
+        } => {
+            if let Some(provider) = tag_sync_provider(&db, sync).await? {
+                for source in sources.iter().filter(|source| *source != into) {
+                    provider.rename_tag(source, into).await?;
+                }
+            }
+            let items = db.merge_tags(sources, into).await?;
+            println!(
+                "Merged {} into \"{into}\" on {items} items",
+                sources.join(", ")
+            );
+        }
+        TagsCommands::Delete { tag, sync } => {
+            let tree = db.get_tag_tree(tag).await?;
+            if tree.is_empty() {
+                return Err(format!("Tag \"{tag}\" not found").into());
+            }
+            if let Some(provider) = tag_sync_provider(&db, sync).await? {
+                for tag in &tree {
+                    provider.delete_tag(tag).await?;
+                }
+            }
+            let items = db.delete_tag(tag).await?;
+            println!("Removed \"{tag}\" from {items} items");
+        }
+        TagsCommands::Prune => {
+            let tags = db.prune_tags().await?;
+            println!("Removed {tags} unused tags");
+        }
+    }
+    Ok(())
+}
+
+async fn handle_collection_command(
+    command: &CollectionCommands,
+    cli_args: &CliArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    match command {
+        CollectionCommands::Create { name, description } => {
+            if db.get_collection(name).await?.is_some() {
+                return Err(format!("Collection \"{name}\" already exists").into());
+            }
+            let id = db.create_collection(name, description.as_deref()).await?;
+            println!("Created collection \"{name}\" ({id})");
+        }
+        CollectionCommands::Add { collection, items } => {
+            let collection = find_collection(&db, collection).await?;
+            for item in items {
+                let Some(item_id) = resolve_item(&db, item).await? else {
+                    eprintln!("Item {item} not found in the database");
+                    continue;
+                };
+                if db.add_to_collection(collection.id, item_id).await? {
+                    println!("Added {item} to \"{}\"", collection.name);
+                } else {
+                    println!("{item} is already in \"{}\"", collection.name);
+                }
+            }
+        }
+        CollectionCommands::Remove { collection, items } => {
+            let collection = find_collection(&db, collection).await?;
+            for item in items {
+                let Some(item_id) = resolve_item(&db, item).await? else {
+                    eprintln!("Item {item} not found in the database");
+                    continue;
+                };
+                if db.remove_from_collection(collection.id, item_id).await? {
+                    println!("Removed {item} from \"{}\"", collection.name);
+                } else {
+                    println!("{item} is not in \"{}\"", collection.name);
+                }
+            }
+        }
+        CollectionCommands::List { collection: None } => {
+            for (collection, count) in db.get_collections().await? {
+                match collection.description {
+                    Some(description) => {
+                        println!("{count:>6}  {}: {description}", collection.name)
+                    }
+                    None => println!("{count:>6}  {}", collection.name),
+                }
+            }
+        }
+        CollectionCommands::List {
+            collection: Some(collection),
+        } => {
+            let collection = find_collection(&db, collection).await?;
+            let items = db.get_collection_items(collection.id, None).await?;
+            println!("Collection: {}", collection.name);
+            println!("Displaying {} items:", items.len());
+            for item in items {
+                println!("Research Item");
+                println!("-------------");
+                if let Some(id) = item.id {
+                    println!("ID: {}", id);
+                }
+                println!("{}", item.to_display_with_timezone(None));
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn find_collection(
+    db: &DB,
+    name: &str,
+) -> Result<Collection, Box<dyn std::error::Error>> {
+    db.get_collection(name).await?.ok_or_else(|| {
+        format!("Collection \"{name}\" not found, create it with `collection create`").into()
+    })
+}
+
+/// Provider given with `--sync-pocket` or `--sync`. It's updated before the database, so a
+/// failed request leaves both unchanged
+async fn tag_sync_provider(
+    db: &DB,
+    sync: &TagSyncArgs,
+) -> Result<Option<Box<dyn OnlineProvider>>, Box<dyn std::error::Error>> {
+    let name = match &sync.sync {
+        Some(name) => name.as_str(),
+        None if sync.sync_pocket => "pocket",
+        None => return Ok(None),
+    };
+    let registration =
+        provider::registration(name).ok_or(format!("Unknown online provider \"{name}\""))?;
+    let provider = (registration.connect)(db, &sync.account).await?;
+    if !provider.supports_tags() {
+        return Err(format!("{name} doesn't support changing tags").into());
+    }
+    Ok(Some(provider))
+}
+
+async fn handle_providers_command(
+    providers_command: &ProvidersCommands,
+    cli_args: &CliArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    match providers_command {
+        ProvidersCommands::List => {
+            println!("{:<10}  {:<12}  STATUS", "PROVIDER", "ACCOUNT");
+            for registration in provider::PROVIDERS {
+                let name = registration.name;
+                let accounts = db.get_accounts(name).await?;
+                if accounts.is_empty() {
+                    println!(
+                        "{name:<10}  {:<12}  not authenticated, run `{name} auth`",
+                        "-"
+                    );
+                }
+                for account in accounts {
+                    let keys = db.get_secret_keys(name, &account).await?;
+                    let missing = registration.missing_secrets(&keys);
+                    let status = if missing.is_empty() {
+                        "authenticated".to_string()
+                    } else {
+                        format!("missing {}", missing.join(", "))
+                    };
+                    println!("{name:<10}  {account:<12}  {status}");
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn handle_db_command(
+    command: &DbCommands,
+    cli_args: &CliArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Opened without migrating, so the status shows what's pending
+    let db = DB::open(&cli_args.db).await.map_err(handle_db_error)?;
+    match command {
+        DbCommands::Status => {
+            let status = db.migration_status().await?;
+            let width = status
+                .iter()
+                .map(|migration| migration.description.len())
+                .max()
+                .unwrap_or_default();
+            for migration in &status {
+                let state = match (&migration.installed_on, migration.known) {
+                    (Some(installed_on), true) => format!("applied {installed_on}"),
+                    (Some(installed_on), false) => {
+                        format!("applied {installed_on} by a newer release")
+                    }
+                    (None, _) => "pending".to_string(),
+                };
+                println!(
+                    "{:>4}  {:<width$}  {state}",
+                    migration.version, migration.description
+                );
+            }
+            let pending = status
+                .iter()
+                .filter(|migration| migration.installed_on.is_none())
+                .count();
+            if pending > 0 {
+                println!("\n{pending} pending, run `research db migrate` to apply them");
+            }
+        }
+        DbCommands::Migrate => {
+            let applied = db.migrate(&cli_args.db).await.map_err(handle_db_error)?;
+            if applied.is_empty() {
+                println!(
+                    "Database is up to date at version {}",
+                    DB::latest_migration()
+                );
+            }
+            for version in applied {
+                println!("Applied migration {version}");
+            }
+        }
+        DbCommands::Rollback { target, steps } => {
+            let target = match target {
+                Some(target) => *target,
+                None => {
+                    let mut applied = db
+                        .migration_status()
+                        .await?
+                        .into_iter()
+                        .filter(|migration| migration.installed_on.is_some())
+                        .map(|migration| migration.version)
+                        .collect::<Vec<_>>();
+                    applied.sort_by(|a, b| b.cmp(a));
+                    // Below the first version when every migration is reverted
+                    applied.get(*steps).copied().unwrap_or(-1)
+                }
+            };
+            let reverted = db
+                .rollback(&cli_args.db, target)
+                .await
+                .map_err(handle_db_error)?;
+            if reverted.is_empty() {
+                println!("Nothing to roll back");
+                return Ok(());
+            }
+            for version in reverted {
+                println!("Reverted migration {version}");
+            }
+            println!(
+                "Other commands of this release apply them again, use an older release now"
+            );
+        }
+        DbCommands::Backup { path, force } => {
+            let mut path = PathBuf::from(path);
+            if path.is_dir() {
+                let file =
+                    DB::database_file(&cli_args.db).ok_or("The database isn't a file")?;
+                path = DB::backup_path(&file, &path);
+            } else if path.exists() && !force {
+                return Err(format!(
+                    "{} already exists, use --force to overwrite it",
+                    path.display()
+                )
+                .into());
+            }
+            db.backup(&path).await?;
+            println!("Backed up the database to {}", path.display());
+        }
+        DbCommands::Restore { path } => {
+            let path = Path::new(path);
+            if !path.is_file() {
+                return Err(format!("Backup not found: {}", path.display()).into());
+            }
+            if let Some(current) = db.restore(&cli_args.db, path).await? {
+                println!("Backed up the current database to {}", current.display());
+            }
+            println!("Restored the database from {}", path.display());
+        }
+        DbCommands::Check => {
+            let report = db.check().await?;
+            for line in &report.integrity {
+                println!("Integrity: {line}");
+            }
+            for (table, rowid, parent) in &report.foreign_keys {
+                println!("Foreign key: {table} row {rowid} points to a missing {parent} row");
+            }
+            for (item_id, tag) in &report.orphaned_tags {
+                println!("Orphaned item tag: item {item_id}, tag {tag:?}");
+            }
+            if !report.is_ok() {
+                let problems = report.integrity.len()
+                    + report.foreign_keys.len()
+                    + report.orphaned_tags.len();
+                return Err(format!("Found {problems} problems").into());
+            }
+            println!("No problems found");
+        }
+        DbCommands::Vacuum => {
+            let (before, after) = db.vacuum().await?;
+            println!(
+                "Vacuumed the database from {} to {} KiB",
+                before / 1024,
+                after / 1024
+            );
+        }
+    }
+    Ok(())
+}
+
 async fn handle_local_command(
     command: &LocalCommands,
     cli_args: &CliArgs,
@@ -129,20 +599,22 @@ async fn handle_local_command(
 
             let metadata = handler::fetch_metadata(uri).await?;
             let local_item = LocalItem {
-                id: None,
+                remote_id: None,
+                account: None,
                 uri: uri.to_string(),
-                title: Some(title.clone().map_or(metadata.title, |title| title.clone())),
-                excerpt: Some(
-                    excerpt
-                        .clone()
-                        .map_or(metadata.description, |excerpt| excerpt.clone()),
-                ),
+                title: Some(title.clone().unwrap_or(metadata.title.clone())),
+                excerpt: Some(excerpt.clone().unwrap_or(metadata.description.clone())),
                 time_added: chrono::Utc::now().timestamp(),
                 tags: tags.clone(),
+                lang: metadata.lang.clone(),
+                details: metadata.details.clone(),
             };
 
             db.insert_item(local_item.to_research_item(), &tags, provider_id)
                 .await?;
+            if let Some(content) = &metadata.content {
+                db.save_item_content(uri, content).await?;
+            }
             println!("Inserted document successfully!");
         }
         LocalCommands::List => {
@@ -166,6 +638,7 @@ async fn handle_local_command(
 
 async fn handle_pocket_command(
     pocket_command: &PocketCommands,
+    account: &str,
     cli_args: &CliArgs,
 ) -> Result<(), Box<dyn std::error::Error>> {
     match pocket_command {
@@ -177,13 +650,23 @@ async fn handle_pocket_command(
                 ..Default::default()
             };
             let secrets = provider.authenticate().await?;
-            db.set_secret(secrets).await?;
+            db.set_secret(Secrets {
+                account: account.to_string(),
+                ..secrets
+            })
+            .await?;
             println!("Success: Access token saved to the database! You can now run `pocket fetch` to fetch items from Pocket.")
         }
-        PocketCommands::Fetch(FetchArgs { key, access, limit }) => {
-            // Handle fetching items from Pocket with the provided keys
-            fetch_from_pocket(&cli_args.db, key.to_string(), access.to_string(), *limit)
-                .await?;
+        PocketCommands::Fetch(FetchArgs {
+            key,
+            access,
+            limit,
+            sync,
+        }) => {
+            // Handle fetching items from Pocket with the saved or provided keys
+            let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+            let provider = pocket_provider(&db, account, key, access).await?;
+            fetch_from_provider(&db, &provider, account, *limit, *sync).await?;
         }
         PocketCommands::Add(PocketAddArgs {
             add_args: LocalAddArgs { uri, tag, .. },
@@ -192,19 +675,8 @@ async fn handle_pocket_command(
         }) => {
             // Handle adding an item to Pocket with the provided URI and tags
             let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
-            let secrets = db.get_secrets().await?;
-            let consumer_key = secrets.pocket_consumer_key.or(key.clone()).expect(
-                "Consumer key not found in the database, consider generating one from https://getpocket.com/developer/apps/new and running `pocket auth`",
-            );
-            let access_token = secrets.pocket_access_token.or(access.clone()).expect(
-                "Access token not found in the database, consider running 'pocket auth'",
-            );
-            let provider = ProviderPocket {
-                consumer_key,
-                access_token: Some(access_token),
-                ..Default::default()
-            };
-            let item_id = provider
+            let provider = pocket_provider(&db, account, key, access).await?;
+            let remote_id = provider
                 .add_item(
                     uri,
                     tag.as_ref().map_or(Vec::new(), |tags| {
@@ -225,18 +697,26 @@ async fn handle_pocket_command(
             println!("Saving item to database");
             let metadata = handler::fetch_metadata(uri).await?;
             let insertable_item = ResearchItem {
-                id: item_id,
+                id: None,
+                remote_id,
+                account: Some(account.to_string()),
                 uri: uri.to_string(),
-                title: metadata.title,
-                excerpt: metadata.description,
+                title: metadata.title.clone(),
+                excerpt: metadata.description.clone(),
                 time_added: chrono::Utc::now().timestamp(),
                 favorite: false,
-                lang: None,
+                lang: metadata.lang.clone(),
                 notes: None,
+                status: ItemStatus::Normal,
+                time_read: None,
+                details: metadata.details.clone(),
             };
             let provider_id = db.get_provider_id("pocket").await?;
             println!("Item: {insertable_item:?}");
             db.insert_item(insertable_item, &tags, provider_id).await?;
+            if let Some(content) = &metadata.content {
+                db.save_item_content(uri, content).await?;
+            }
         }
         PocketCommands::Favorite(PocketFavoriteArgs {
             fav_args:
@@ -249,23 +729,14 @@ async fn handle_pocket_command(
         }) => {
             // Handle adding an item to Pocket with the provided URI and tags
             let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
-            let secrets = db.get_secrets().await?;
-            let consumer_key = secrets.pocket_consumer_key.or(key.clone()).expect(
-                "Consumer key not found in the database, consider generating one from https://getpocket.com/developer/apps/new and running `pocket auth`",
-            );
-            let access_token = secrets.pocket_access_token.or(access.clone()).expect(
-                "Access token not found in the database, consider running 'pocket auth'",
-            );
-            let provider = ProviderPocket {
-                consumer_key,
-                access_token: Some(access_token),
-                ..Default::default()
-            };
+            let provider = pocket_provider(&db, account, key, access).await?;
             let item_id = db
                 .get_item_id(uri)
                 .await?
                 .expect("Item uri not found in the database");
-            provider.mark_as_favorite(item_id, *favorite).await?;
+            provider
+                .mark_as_favorite(remote_id(&db, item_id).await?, *favorite)
+                .await?;
             db.mark_as_favorite(item_id, *favorite).await?;
             println!("Item marked as favorite: {favorite}");
         }
@@ -273,54 +744,497 @@ async fn handle_pocket_command(
     Ok(())
 }
 
+async fn handle_raindrop_command(
+    raindrop_command: &RaindropCommands,
+    account: &str,
+    cli_args: &CliArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    if let RaindropCommands::Auth(RaindropAuthArgs { token }) = raindrop_command {
+        let provider = ProviderRaindrop {
+            access_token: token.to_string(),
+            ..Default::default()
+        };
+        let secrets = provider.authenticate().await?;
+        db.set_secret(Secrets {
+            account: account.to_string(),
+            ..secrets
+        })
+        .await?;
+        println!("Success: Token saved to the database! You can now run `raindrop fetch` to fetch items from Raindrop.");
+        return Ok(());
+    }
+
+    let provider = ProviderRaindrop::from_secrets(&db.get_secrets("raindrop", account).await?)
+        .ok_or(format!(
+            "Raindrop token of the {account} account not found in the database, consider running `raindrop --account {account} auth`"
+        ))?;
+    match raindrop_command {
+        RaindropCommands::Auth(_) => unreachable!(),
+        RaindropCommands::Fetch { limit } => {
+            fetch_from_provider(&db, &provider, account, *limit, false).await?;
+        }
+        RaindropCommands::Add(LocalAddArgs { uri, tag, .. }) => {
+            let tags: Vec<&str> = tag.as_ref().map_or(Vec::new(), |tags| {
+                tags.iter().map(|tag| tag.as_str()).collect()
+            });
+            let remote_id = provider.add_item(uri, tags.clone()).await?;
+            save_added_item(&db, "raindrop", account, remote_id, uri, &tags).await?;
+        }
+        RaindropCommands::Favorite(LocalFavoriteArgs { uri, mark }) => {
+            let item_id = db
+                .get_item_id(uri)
+                .await?
+                .expect("Item uri not found in the database");
+            provider
+                .mark_as_favorite(remote_id(&db, item_id).await?, *mark)
+                .await?;
+            db.mark_as_favorite(item_id, *mark).await?;
+            println!("Item marked as favorite: {mark}");
+        }
+    }
+    Ok(())
+}
+
+async fn handle_wallabag_command(
+    wallabag_command: &WallabagCommands,
+    account: &str,
+    cli_args: &CliArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    if let WallabagCommands::Auth(args) = wallabag_command {
+        let provider = ProviderWallabag {
+            base_url: args.url.trim_end_matches('/').to_string(),
+            client_id: args.client_id.to_string(),
+            client_secret: args.client_secret.to_string(),
+            username: args.username.to_string(),
+            password: args.password.to_string(),
+            ..Default::default()
+        };
+        let secrets = provider.authenticate().await?;
+        db.set_secret(Secrets {
+            account: account.to_string(),
+            ..secrets
+        })
+        .await?;
+        println!("Success: Tokens saved to the database! You can now run `wallabag fetch` to fetch items from Wallabag.");
+        return Ok(());
+    }
+
+    let provider = ProviderWallabag::connect(&db, account).await?;
+    match wallabag_command {
+        WallabagCommands::Auth(_) => unreachable!(),
+        WallabagCommands::Fetch { limit } => {
+            fetch_from_provider(&db, &provider, account, *limit, false).await?;
+        }
+        WallabagCommands::Add(LocalAddArgs { uri, tag, .. }) => {
+            let tags: Vec<&str> = tag.as_ref().map_or(Vec::new(), |tags| {
+                tags.iter().map(|tag| tag.as_str()).collect()
+            });
+            let remote_id = provider.add_item(uri, tags.clone()).await?;
+            save_added_item(&db, "wallabag", account, remote_id, uri, &tags).await?;
+        }
+        WallabagCommands::Favorite(LocalFavoriteArgs { uri, mark }) => {
+            let item_id = db
+                .get_item_id(uri)
+                .await?
+                .expect("Item uri not found in the database");
+            provider
+                .mark_as_favorite(remote_id(&db, item_id).await?, *mark)
+                .await?;
+            db.mark_as_favorite(item_id, *mark).await?;
+            println!("Item marked as favorite: {mark}");
+        }
+    }
+    Ok(())
+}
+
+/// Saves an item just added to an online provider, with the page metadata
+async fn save_added_item(
+    db: &DB,
+    provider: &str,
+    account: &str,
+    remote_id: Option<i64>,
+    uri: &str,
+    tags: &[&str],
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Saving item to database");
+    let metadata = handler::fetch_metadata(uri).await?;
+    let local_item = LocalItem {
+        remote_id,
+        account: Some(account.to_string()),
+        uri: uri.to_string(),
+        title: Some(metadata.title),
+        excerpt: Some(metadata.description),
+        time_added: chrono::Utc::now().timestamp(),
+        tags: tags
+            .iter()
+            .map(|tag| Tags {
+                tag_name: tag.to_string(),
+            })
+            .collect(),
+        lang: metadata.lang,
+        details: metadata.details,
+    };
+    let provider_id = db.get_provider_id(provider).await?;
+    db.insert_item(
+        local_item.to_research_item(),
+        &local_item.to_tags(),
+        provider_id,
+    )
+    .await?;
+    if let Some(content) = &metadata.content {
+        db.save_item_content(uri, content).await?;
+    }
+    Ok(())
+}
+
+/// Pocket provider using the secrets saved for the account, falling back to the given keys
+async fn pocket_provider(
+    db: &DB,
+    account: &str,
+    key: &Option<String>,
+    access: &Option<String>,
+) -> Result<ProviderPocket, Box<dyn std::error::Error>> {
+    let secrets = db.get_secrets("pocket", account).await?;
+    let consumer_key = secrets
+        .get("consumer_key")
+        .map(str::to_string)
+        .or(key.clone())
+        .ok_or(
+        "Consumer key not found in the database, consider generating one from https://getpocket.com/developer/apps/new and running `pocket auth`",
+    )?;
+    let access_token = secrets
+        .get("access_token")
+        .map(str::to_string)
+        .or(access.clone())
+        .ok_or("Access token not found in the database, consider running 'pocket auth'")?;
+    Ok(ProviderPocket {
+        consumer_key,
+        access_token: Some(access_token),
+        ..Default::default()
+    })
+}
+
+async fn handle_archive_command(
+    cli_args: &CliArgs,
+    args: &ArchiveArgs,
+    archive: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    let Some(item_id) = db.get_item_id(&args.uri).await? else {
+        eprintln!("Item with URL {} not found in the database", args.uri);
+        return Ok(());
+    };
+
+    if let Some(provider) = item_provider(&db, item_id).await? {
+        if provider.supports_archive() {
+            provider
+                .mark_as_archived(remote_id(&db, item_id).await?, archive)
+                .await?;
+        }
+    }
+    db.mark_as_archived(item_id, archive).await?;
+    println!("Item archived: {archive}");
+    Ok(())
+}
+
+/// Online provider of the item, connected with the account the item was saved with. None for
+/// local items, and for items whose provider can't be connected to, so they're still changed
+/// locally
+async fn item_provider(
+    db: &DB,
+    item_id: i64,
+) -> Result<Option<Box<dyn OnlineProvider>>, Box<dyn std::error::Error>> {
+    let Some(registration) = db
+        .get_item_provider(item_id)
+        .await?
+        .as_deref()
+        .and_then(provider::registration)
+    else {
+        return Ok(None);
+    };
+    let account = db
+        .get_item_account(item_id)
+        .await?
+        .unwrap_or_else(|| DEFAULT_ACCOUNT.to_string());
+    match (registration.connect)(db, &account).await {
+        Ok(provider) => Ok(Some(provider)),
+        Err(e) => {
+            eprintln!(
+                "Only changing the item locally, {} can't be connected to: {e}",
+                registration.name
+            );
+            Ok(None)
+        }
+    }
+}
+
+/// Id of the item in its online provider
+async fn remote_id(db: &DB, item_id: i64) -> Result<i64, Box<dyn std::error::Error>> {
+    Ok(db
+        .get_item_remote_id(item_id)
+        .await?
+        .ok_or(format!("Item {item_id} has no id in its provider"))?)
+}
+
+/// Id of the item with this id or uri
+async fn resolve_item(db: &DB, item: &str) -> Result<Option<i64>, sqlx::Error> {
+    if let Ok(id) = item.parse::<i64>() {
+        if db.get_item_uri(id).await?.is_some() {
+            return Ok(Some(id));
+        }
+    }
+    db.get_item_id(item).await
+}
+
+async fn handle_edit_command(
+    cli_args: &CliArgs,
+    args: &EditArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    let Some(item_id) = resolve_item(&db, &args.item).await? else {
+        eprintln!("Item {} not found in the database", args.item);
+        return Ok(());
+    };
+
+    let edit = ItemEdit {
+        title: args.title.clone(),
+        excerpt: args.excerpt.clone(),
+        lang: args.lang.clone(),
+        add_tags: args.add_tag.clone(),
+        remove_tags: args.remove_tag.clone(),
+    };
+    if let Some(provider) = item_provider(&db, item_id).await? {
+        let tags_changed = !edit.add_tags.is_empty() || !edit.remove_tags.is_empty();
+        if provider.supports_tags() && tags_changed {
+            provider
+                .update_tags(
+                    remote_id(&db, item_id).await?,
+                    &edit.add_tags,
+                    &edit.remove_tags,
+                )
+                .await?;
+        }
+        // Providers don't take a new title, excerpt or language
+        let details_changed =
+            edit.title.is_some() || edit.excerpt.is_some() || edit.lang.is_some();
+        if details_changed || (tags_changed && !provider.supports_tags()) {
+            eprintln!(
+                "Changes are only saved locally, the next fetch from {} may overwrite them",
+                provider.name()
+            );
+        }
+    }
+    db.edit_item(item_id, &edit).await?;
+    println!("Item {item_id} updated");
+    Ok(())
+}
+
+async fn handle_delete_command(
+    cli_args: &CliArgs,
+    args: &DeleteArgs,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    let Some(item_id) = resolve_item(&db, &args.item).await? else {
+        eprintln!("Item {} not found in the database", args.item);
+        return Ok(());
+    };
+
+    if let Some(provider) = item_provider(&db, item_id).await? {
+        if provider.supports_delete() {
+            provider.delete_item(remote_id(&db, item_id).await?).await?;
+        } else {
+            eprintln!(
+                "Only deleted locally, the next fetch from {} adds it back",
+                provider.name()
+            );
+        }
+    }
+    db.delete_item(item_id).await?;
+    println!("Item {item_id} deleted");
+    Ok(())
+}
+
 async fn handle_fetch_command(
     cli_args: &CliArgs,
+    account: &Option<String>,
     limit: Option<usize>,
+    sync: bool,
+    content: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Handle fetching data from authenticated providers
     let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
-    let secrets = db.get_secrets().await?;
-    let consumer_key = secrets.pocket_consumer_key.expect("Consumer key not found in the database, consider generating one from https://getpocket.com/developer/apps/new and running `pocket auth`");
-    let access_token = secrets
-        .pocket_access_token
-        .expect("Access token not found in the database, consider running 'pocket auth'");
-    fetch_from_pocket(&cli_args.db, consumer_key, access_token, limit).await?;
+    let mut fetched = false;
+    // Items already missing content before this fetch are left to `archive-content --missing`
+    let missing_before: HashSet<String> =
+        db.get_uris_for_content(true).await?.into_iter().collect();
+
+    for registration in provider::PROVIDERS {
+        for account in fetch_accounts(&db, registration.name, account).await? {
+            let keys = db.get_secret_keys(registration.name, &account).await?;
+            if !registration.missing_secrets(&keys).is_empty() {
+                continue;
+            }
+            eprintln!("Fetching from {}, account {account}", registration.name);
+            let provider = (registration.connect)(&db, &account).await?;
+            fetch_from_provider(&db, provider.as_ref(), &account, limit, sync).await?;
+            fetched = true;
+        }
+    }
+
+    if !fetched {
+        let commands = provider::PROVIDERS
+            .iter()
+            .map(|registration| format!("`{} auth`", registration.name))
+            .collect::<Vec<_>>();
+        eprintln!(
+            "No authenticated providers found, consider running {}",
+            commands.join(", ")
+        );
+    } else if content {
+        let new_uris = db
+            .get_uris_for_content(true)
+            .await?
+            .into_iter()
+            .filter(|uri| !missing_before.contains(uri))
+            .collect::<Vec<_>>();
+        archive_contents(&db, &new_uris).await?;
+    }
+    Ok(())
+}
+
+/// Downloads and saves the article content of each uri, pages that fail are skipped
+async fn archive_contents(db: &DB, uris: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut archived = 0;
+    for (i, uri) in uris.iter().enumerate() {
+        eprintln!("[{}/{}] {}", i + 1, uris.len(), uri);
+        match handler::fetch_metadata(uri).await {
+            Ok(metadata) => match metadata.content {
+                Some(content) => {
+                    db.save_item_content(uri, &content).await?;
+                    archived += 1;
+                }
+                None => eprintln!("No readable content found"),
+            },
+            Err(e) => eprintln!("Failed to fetch the page: {e}"),
+        }
+    }
+    println!("Archived content of {archived} out of {} items", uris.len());
     Ok(())
 }
 
 async fn handle_list_command(
     cli_args: &CliArgs,
-    tags: Option<&Vec<String>>,
-    favorite: Option<bool>,
-    limit: Option<usize>,
+    query: &ItemQuery,
     timezone: Option<Tz>,
+    format: ListFormat,
+    fields: &[String],
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Handle listing items in the database
     let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
-    let mut items: Vec<ResearchItem>;
-    if let Some(tags) = tags {
-        items = db.get_all_items_by_tags(tags, favorite).await?;
-        println!("Tags: {:?}", tags);
-        println!("Total items: {}", items.len());
-        if let Some(limit) = limit {
-            items.truncate(limit);
-        }
-    } else {
-        items = db.get_all_items(favorite).await?;
-        println!("Total items: {}", items.len());
-        if let Some(limit) = limit {
-            items.truncate(limit);
+    let mut items = Vec::new();
+    for item in db.query_items(query).await? {
+        let tags = match item.id {
+            Some(id) => db.get_item_tags(id).await?,
+            None => Vec::new(),
+        };
+        items.push(ListedItem::new(
+            item,
+            tags.into_iter().map(|t| t.tag_name).collect(),
+        ));
+    }
+
+    let fields = fields.iter().map(String::as_str).collect::<Vec<_>>();
+    let mut out = std::io::stdout().lock();
+    match format {
+        ListFormat::Text => {
+            if !query.all_tags.is_empty() {
+                println!("Tags: {:?}", query.all_tags);
+            }
+            println!("Total items: {}", db.count_items(query).await?);
+            println!("Displaying {} items:", items.len());
+            for ListedItem { item, .. } in items {
+                println!("Research Item");
+                println!("-------------");
+                if let Some(id) = item.id {
+                    println!("ID: {}", id);
+                }
+                println!("{}", item.to_display_with_timezone(timezone));
+            }
         }
+        ListFormat::Json => output::write_json(&mut out, &items, &fields, false)?,
+        ListFormat::Jsonl => output::write_json(&mut out, &items, &fields, true)?,
+        ListFormat::Csv => output::write_delimited(&mut out, &items, &fields, b',')?,
+        ListFormat::Tsv => output::write_delimited(&mut out, &items, &fields, b'\t')?,
+        ListFormat::Table => output::write_table(&mut out, &items, &fields, timezone)?,
+        ListFormat::Markdown => output::write_markdown(&mut out, &items, &fields, timezone)?,
     }
-    println!("Displaying {} items:", items.len());
-    for item in items {
+    Ok(())
+}
+
+async fn handle_search_command(
+    cli_args: &CliArgs,
+    query: &str,
+    raw: bool,
+    limit: Option<usize>,
+    timezone: Option<Tz>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    let results = db
+        .search(query, raw, limit)
+        .await
+        .map_err(handle_db_error)?;
+    println!("Query: {query}");
+    println!("Displaying {} items:", results.len());
+    for result in results {
         println!("Research Item");
         println!("-------------");
-        if let Some(id) = item.id {
+        if let Some(id) = result.item.id {
             println!("ID: {}", id);
         }
-        println!("{}", item.to_display_with_timezone(timezone));
+        println!("{}", result.item.to_display_with_timezone(timezone));
+        println!("Match: {}", result.snippet);
+    }
+    Ok(())
+}
+
+async fn handle_import_command(
+    cli_args: &CliArgs,
+    format: &ImportFormat,
+    file: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
+    let provider_id = db.get_provider_id("local").await?;
+
+    let bookmarks = match format {
+        ImportFormat::Netscape => parse_bookmarks(&read_to_string(file).await?),
+    };
+    eprintln!("Bookmarks: {}", bookmarks.len());
+
+    for bookmark in bookmarks {
+        let tags: Vec<Tags> = bookmark
+            .tags
+            .into_iter()
+            .map(|tag_name| Tags { tag_name })
+            .collect();
+        let local_item = LocalItem {
+            remote_id: None,
+            account: None,
+            uri: bookmark.url,
+            title: Some(bookmark.title).filter(|title| !title.is_empty()),
+            excerpt: bookmark.description,
+            time_added: bookmark
+                .add_date
+                .unwrap_or_else(|| chrono::Utc::now().timestamp()),
+            tags: tags.clone(),
+            lang: None,
+            details: ItemDetails::default(),
+        };
+        db.insert_item(local_item.to_research_item(), &tags, provider_id)
+            .await?;
     }
+    println!("Imported bookmarks successfully!");
     Ok(())
 }
 
@@ -334,8 +1248,7 @@ async fn handle_init_command(
     };
     eprintln!("Creating new database: {db_url}");
     sqlx::Sqlite::create_database(&db_url).await?;
-    let pool = sqlx::SqlitePool::connect(&db_url).await?;
-    DB::migrate(&pool).await?;
+    DB::init(&db_url).await.map_err(handle_db_error)?;
     eprintln!("Database created and migrated successfully!");
     Ok(())
 }
@@ -344,7 +1257,9 @@ async fn handle_generate_command(
     output_dir: &str,
     assets_dir: &str,
     download_tailwind: bool,
+    status: Option<ItemStatus>,
     timezone: Option<Tz>,
+    feed_options: Option<&FeedOptions>,
     cli_args: &CliArgs,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // Handle generating a static site with the provided options
@@ -364,10 +1279,44 @@ async fn handle_generate_command(
     }
 
     let db = DB::init(&cli_args.db).await.map_err(handle_db_error)?;
-    let tags = db.get_all_tags().await?;
-    let item_tags = db.get_all_item_tags().await?;
+    // Parents of tag paths get a page too, even when no item is tagged with them directly
+    let tags = db
+        .get_all_tags()
+        .await?
+        .iter()
+        .flat_map(|tag| tag_ancestors(&tag.tag_name).chain([tag.tag_name.as_str()]))
+        .map(String::from)
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|tag_name| Tags { tag_name })
+        .collect::<Vec<_>>();
+    let item_tags = db.get_all_item_tags(status).await?;
+    let mut tag_items = Vec::new();
+    for tag in &tags {
+        let items = db
+            .get_all_items_by_tags(std::slice::from_ref(&tag.tag_name), None, status)
+            .await?;
+        tag_items.push((tag.clone(), items));
+    }
 
-    let site = Site::build(&tags, &item_tags, "./assets", timezone)?;
+    let mut collection_items = Vec::new();
+    for (collection, _) in db.get_collections().await? {
+        let items = db.get_collection_items(collection.id, status).await?;
+        collection_items.push((collection, items));
+    }
+
+    if feed_options.is_none() {
+        eprintln!("No --base-url provided, skipping the RSS and Atom feeds since feed readers need absolute links");
+    }
+    let site = Site::build(
+        &tags,
+        &item_tags,
+        &tag_items,
+        &collection_items,
+        "./assets",
+        timezone,
+        feed_options,
+    )?;
 
     eprintln!("Output directory: {output_dir:?}");
     let mut index = File::create(output_dir.join("index.html")).await?;
@@ -376,6 +1325,44 @@ async fn handle_generate_command(
     let mut search = File::create(output_dir.join("search.html")).await?;
     search.write_all(site.search_html.as_bytes()).await?;
 
+    if let Some(atom_feed) = &site.atom_feed {
+        let mut atom = File::create(output_dir.join("feed.xml")).await?;
+        atom.write_all(atom_feed.as_bytes()).await?;
+    }
+
+    if let Some(rss_feed) = &site.rss_feed {
+        let mut rss = File::create(output_dir.join("rss.xml")).await?;
+        rss.write_all(rss_feed.as_bytes()).await?;
+    }
+
+    let mut tags_index = File::create(output_dir.join("tags.html")).await?;
+    tags_index.write_all(site.tags_html.as_bytes()).await?;
+
+    let tags_dir = output_dir.join("tags");
+    create_dir_all(&tags_dir).await?;
+    for (slug, tag_html) in &site.tag_pages {
+        let mut tag = File::create(tags_dir.join(format!("{slug}.html"))).await?;
+        tag.write_all(tag_html.as_bytes()).await?;
+    }
+    for (slug, tag_feed) in &site.tag_feeds {
+        let mut feed = File::create(tags_dir.join(format!("{slug}.xml"))).await?;
+        feed.write_all(tag_feed.as_bytes()).await?;
+    }
+
+    let collections_dir = output_dir.join("collections");
+    create_dir_all(&collections_dir).await?;
+    for (slug, collection_html) in &site.collection_pages {
+        let mut collection = File::create(collections_dir.join(format!("{slug}.html"))).await?;
+        collection.write_all(collection_html.as_bytes()).await?;
+    }
+
+    let items_dir = output_dir.join("items");
+    create_dir_all(&items_dir).await?;
+    for (id, item_html) in &site.item_pages {
+        let mut item = File::create(items_dir.join(format!("{id}.html"))).await?;
+        item.write_all(item_html.as_bytes()).await?;
+    }
+
     build_css(
         output_dir,
         &absolute_path(
@@ -396,45 +1383,134 @@ async fn handle_generate_command(
     Ok(())
 }
 
-async fn fetch_from_pocket(
-    db_url: &str,
-    consumer_key: String,
-    access_token: String,
+/// The given account, or every account of the provider with saved secrets
+async fn fetch_accounts(
+    db: &DB,
+    provider: &str,
+    account: &Option<String>,
+) -> Result<Vec<String>, sqlx::Error> {
+    match account {
+        Some(account) => Ok(vec![account.clone()]),
+        None => db.get_accounts(provider).await,
+    }
+}
+
+/// Fetches the items of the account. Providers with a change feed only add new items here and
+/// apply changes when syncing, the others have every item updated in place.
+async fn fetch_from_provider(
+    db: &DB,
+    provider: &dyn OnlineProvider,
+    account: &str,
     limit: Option<usize>,
+    sync: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    let provider = ProviderPocket {
-        consumer_key,
-        access_token: Some(access_token),
-        ..Default::default()
-    };
-
-    let db = DB::init(db_url).await?;
-    println!("Sqlite version: {}", db.get_sqlite_version().await?);
+    let provider_id = db.get_provider_id(provider.name()).await?;
+    if sync && provider.supports_sync() {
+        return sync_from_provider(db, provider, provider_id, account).await;
+    } else if sync {
+        eprintln!(
+            "Incremental sync isn't supported by {}, fetching all items",
+            provider.name()
+        );
+    }
 
-    let provider_id = db.get_provider_id("pocket").await?;
     let items = provider.fetch_items(limit).await?;
     eprintln!("Items: {}", items.len());
 
     for item in items {
-        let insertable_item = item.to_research_item();
-        let tags = item.to_tags();
-        let title = &insertable_item.title.chars().take(8).collect::<String>();
-        eprint!("{:?} ", title);
-        db.insert_item(insertable_item, &tags, provider_id).await?;
+        let research_item = ResearchItem {
+            account: Some(account.to_string()),
+            ..item.to_research_item()
+        };
+        if provider.supports_sync() {
+            db.insert_item(research_item, &item.to_tags(), provider_id)
+                .await?;
+        } else {
+            let uri = research_item.uri.clone();
+            if !db
+                .upsert_item(research_item, &item.to_tags(), provider_id)
+                .await?
+            {
+                eprintln!("Skipped {uri}, it's already saved from another provider");
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn sync_from_provider(
+    db: &DB,
+    provider: &dyn OnlineProvider,
+    provider_id: i64,
+    account: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let since = db.get_sync_since(provider_id, account).await?;
+    match since {
+        Some(since) => eprintln!("Syncing changes since {since}"),
+        None => eprintln!("No previous sync found, syncing all items"),
     }
+    let synced = provider.sync_items(since).await?;
 
+    let mut skipped = 0;
+    for item in &synced.items {
+        let research_item = ResearchItem {
+            account: Some(account.to_string()),
+            ..item.to_research_item()
+        };
+        let uri = research_item.uri.clone();
+        if !db
+            .upsert_item(research_item, &item.to_tags(), provider_id)
+            .await?
+        {
+            eprintln!("Skipped {uri}, it's already saved from another provider");
+            skipped += 1;
+        }
+    }
+    let mut deleted = 0;
+    for remote_id in &synced.deleted {
+        if let Some(item_id) = db
+            .get_item_id_by_remote_id(provider_id, account, *remote_id)
+            .await?
+        {
+            db.delete_item(item_id).await?;
+            deleted += 1;
+        }
+    }
+    if let Some(next_since) = synced.next_since {
+        db.set_sync_since(provider_id, account, next_since).await?;
+    }
+    eprintln!(
+        "Synced items: {} updated, {skipped} skipped, {deleted} deleted",
+        synced.items.len() - skipped
+    );
     Ok(())
 }
 
+fn read_state_filter(read_state: &ReadStateArgs) -> Option<ItemStatus> {
+    if read_state.unread {
+        Some(ItemStatus::Normal)
+    } else if read_state.archived {
+        Some(ItemStatus::Archived)
+    } else {
+        None
+    }
+}
+
 fn handle_db_error(err: sqlx::Error) -> sqlx::Error {
     match &err {
         sqlx::Error::Database(dberr) => {
-            if dberr.message().contains("no such column") {
-                eprintln!("Database schema needs updating. Please run:");
-                eprintln!("pocket-research init <path-to-db>");
-                eprintln!("\nThis will apply all pending migrations.");
+            eprintln!("Database error: {}", dberr);
+        }
+        sqlx::Error::Migrate(migrate_err) => {
+            eprintln!("Couldn't migrate the database: {}", migrate_err);
+            if let sqlx::migrate::MigrateError::VersionMissing(_) = **migrate_err {
+                eprintln!("It was migrated by a newer release of research, update research or");
+                eprintln!(
+                    "roll it back with that release: research db rollback --target <version>"
+                );
             } else {
-                eprintln!("Database error: {}", dberr);
+                eprintln!("Check `research db status`, the .bak file next to the database is");
+                eprintln!("a copy from before migrating");
             }
         }
         sqlx::Error::PoolTimedOut => {
@@ -1,35 +1,38 @@
-use crate::db::{ResearchItem, Tags};
+use crate::db::{ItemDetails, ResearchItem, Tags};
+use crate::provider::pocket::api::ItemStatus;
 
-use super::{Insertable, Provider};
-
-#[derive(Debug, Default)]
-pub struct ProviderLocal;
+use super::Insertable;
 
 pub struct LocalItem {
-    // shouldn't be needed for local items
-    pub id: Option<i64>,
+    /// Id the online provider gave the item, None for local items
+    pub remote_id: Option<i64>,
+    /// Account of the online provider the item was added to
+    pub account: Option<String>,
     pub uri: String,
     pub title: Option<String>,
     pub excerpt: Option<String>,
     pub time_added: i64,
     pub tags: Vec<Tags>,
-}
-
-impl Provider for ProviderLocal {
-    type Item = LocalItem;
+    pub lang: Option<String>,
+    pub details: ItemDetails,
 }
 
 impl Insertable for LocalItem {
     fn to_research_item(&self) -> crate::db::ResearchItem {
         ResearchItem {
-            id: self.id,
+            id: None,
+            remote_id: self.remote_id,
+            account: self.account.clone(),
             uri: self.uri.clone(),
             title: self.title.clone().unwrap_or("Untitled".to_string()),
             excerpt: self.excerpt.clone().unwrap_or("".to_string()),
             time_added: self.time_added,
             favorite: false,
-            lang: Some("en".into()),
+            lang: self.lang.clone().or_else(|| Some("en".into())),
             notes: None,
+            status: ItemStatus::Normal,
+            time_read: None,
+            details: self.details.clone(),
         }
     }
 
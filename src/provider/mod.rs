@@ -1,32 +1,206 @@
-use crate::db::{ResearchItem, Secrets, Tags};
+use std::error::Error;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::db::{ResearchItem, Secrets, Tags, DB, DEFAULT_ACCOUNT};
 
 pub use pocket::ProviderPocket;
+pub use raindrop::ProviderRaindrop;
+pub use wallabag::ProviderWallabag;
 pub mod local;
 pub mod pocket;
+pub mod raindrop;
+pub mod wallabag;
+
+/// Online providers research knows about. A new provider plugs in by adding its registration
+/// here, along with a migration inserting it into the providers table.
+pub const PROVIDERS: &[Registration] = &[
+    pocket::REGISTRATION,
+    raindrop::REGISTRATION,
+    wallabag::REGISTRATION,
+];
+
+/// Future returned by [`OnlineProvider`], boxed so the trait can be used as `dyn OnlineProvider`
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+pub type ProviderResult<T> = Result<T, Box<dyn Error>>;
 
 pub trait Insertable {
+    /// The item with its remote id, the account it was fetched with is set by the caller
     fn to_research_item(&self) -> ResearchItem;
     fn to_tags(&self) -> Vec<Tags>;
 }
 
-pub trait Provider {
-    type Item;
+/// Items changed since the previous sync, see [`OnlineProvider::sync_items`]
+pub struct SyncedItems {
+    pub items: Vec<Box<dyn Insertable>>,
+    /// Ids of the deleted items in the provider
+    pub deleted: Vec<i64>,
+    /// Cursor for the next sync
+    pub next_since: Option<i64>,
 }
 
-pub trait OnlineProvider: Provider {
-    async fn authenticate(&self) -> Result<Secrets, Box<dyn std::error::Error>>;
-    async fn fetch_items(
+/// Items are passed by their id in the provider, [`ResearchItem::remote_id`], never by the
+/// local id
+pub trait OnlineProvider: Send + Sync {
+    /// Name of the provider in the providers table
+    fn name(&self) -> &'static str;
+
+    fn authenticate(&self) -> BoxFuture<'_, ProviderResult<Secrets>>;
+
+    fn fetch_items(
         &self,
         limit: Option<usize>,
-    ) -> Result<Vec<Self::Item>, Box<dyn std::error::Error>>;
-    async fn add_item(
-        &self,
-        uri: &str,
-        tags: Vec<&str>,
-    ) -> Result<Option<i64>, Box<dyn std::error::Error>>;
-    async fn mark_as_favorite(
-        &self,
-        item_id: i64,
-        mark: bool,
-    ) -> Result<(), Box<dyn std::error::Error>>;
+    ) -> BoxFuture<'_, ProviderResult<Vec<Box<dyn Insertable>>>>;
+
+    /// Whether [`OnlineProvider::sync_items`] is supported, otherwise every item is fetched
+    fn supports_sync(&self) -> bool {
+        false
+    }
+
+    /// Items added, changed, archived or deleted after `since`. Passing `None` fetches
+    /// everything.
+    fn sync_items(&self, _since: Option<i64>) -> BoxFuture<'_, ProviderResult<SyncedItems>> {
+        let message = format!("{} doesn't support incremental sync", self.name());
+        Box::pin(async move { Err(message.into()) })
+    }
+
+    fn add_item<'a>(
+        &'a self,
+        uri: &'a str,
+        tags: Vec<&'a str>,
+    ) -> BoxFuture<'a, ProviderResult<Option<i64>>>;
+
+    fn mark_as_favorite(&self, item_id: i64, mark: bool) -> BoxFuture<'_, ProviderResult<()>>;
+
+    /// Whether items can be archived in the provider, otherwise they're only archived locally
+    fn supports_archive(&self) -> bool {
+        true
+    }
+
+    fn mark_as_archived(&self, item_id: i64, mark: bool) -> BoxFuture<'_, ProviderResult<()>>;
+
+    /// Whether items can be deleted in the provider, otherwise they're only deleted locally
+    fn supports_delete(&self) -> bool {
+        false
+    }
+
+    fn delete_item(&self, _item_id: i64) -> BoxFuture<'_, ProviderResult<()>> {
+        let message = format!("{} doesn't support deleting items", self.name());
+        Box::pin(async move { Err(message.into()) })
+    }
+
+    /// Whether tags can be changed in the provider, otherwise they're only changed locally
+    fn supports_tags(&self) -> bool {
+        false
+    }
+
+    fn update_tags<'a>(
+        &'a self,
+        _item_id: i64,
+        _add: &'a [String],
+        _remove: &'a [String],
+    ) -> BoxFuture<'a, ProviderResult<()>> {
+        let message = format!("{} doesn't support changing tags", self.name());
+        Box::pin(async move { Err(message.into()) })
+    }
+
+    /// Renames the tag on every item of the account
+    fn rename_tag<'a>(
+        &'a self,
+        _old: &'a str,
+        _new: &'a str,
+    ) -> BoxFuture<'a, ProviderResult<()>> {
+        let message = format!("{} doesn't support changing tags", self.name());
+        Box::pin(async move { Err(message.into()) })
+    }
+
+    /// Removes the tag from every item of the account
+    fn delete_tag<'a>(&'a self, _tag: &'a str) -> BoxFuture<'a, ProviderResult<()>> {
+        let message = format!("{} doesn't support changing tags", self.name());
+        Box::pin(async move { Err(message.into()) })
+    }
+}
+
+/// Provider of an account, from the secrets saved for it
+pub type Connect =
+    for<'a> fn(&'a DB, &'a str) -> BoxFuture<'a, ProviderResult<Box<dyn OnlineProvider>>>;
+
+/// Entry of an online provider in [`PROVIDERS`]
+pub struct Registration {
+    /// Name of the provider in the providers table and of its subcommand
+    pub name: &'static str,
+    /// Secrets an account needs before the provider can be used
+    pub required_secrets: &'static [&'static str],
+    pub connect: Connect,
+}
+
+impl Registration {
+    /// Required secrets missing from the keys saved for an account
+    pub fn missing_secrets(&self, saved: &[String]) -> Vec<&'static str> {
+        self.required_secrets
+            .iter()
+            .filter(|key| !saved.iter().any(|saved| saved == *key))
+            .copied()
+            .collect()
+    }
+}
+
+/// Registration of the online provider with this name
+pub fn registration(name: &str) -> Option<&'static Registration> {
+    PROVIDERS
+        .iter()
+        .find(|registration| registration.name == name)
+}
+
+/// Error for an account without the secrets of the provider
+pub fn not_authenticated(provider: &str, account: &str) -> Box<dyn Error> {
+    let account_arg = if account == DEFAULT_ACCOUNT {
+        String::new()
+    } else {
+        format!(" --account {account}")
+    };
+    format!(
+        "{provider} secrets of the {account} account not found in the database, consider running `{provider}{account_arg} auth`"
+    )
+    .into()
+}
+
+/// Serves a mocked provider API on a free local port, returns its base url
+#[cfg(test)]
+pub async fn spawn_mock(app: axum::Router) -> String {
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    format!("http://{addr}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{registration, OnlineProvider, ProviderPocket, PROVIDERS};
+    use crate::db::{Secrets, DB, DEFAULT_ACCOUNT};
+
+    #[tokio::test]
+    async fn providers_connect_with_the_saved_secrets() {
+        let db = DB::in_memory().await;
+        for registration in PROVIDERS {
+            let error = (registration.connect)(&db, "work").await.err().unwrap();
+            let hint = format!("`{} --account work auth`", registration.name);
+            assert!(error.to_string().contains(&hint), "{error}");
+        }
+
+        db.set_secret(Secrets::new("raindrop", DEFAULT_ACCOUNT).with("access_token", "token"))
+            .await
+            .unwrap();
+        let connect = registration("raindrop").unwrap().connect;
+        let raindrop = connect(&db, DEFAULT_ACCOUNT).await.unwrap();
+        assert_eq!(raindrop.name(), "raindrop");
+        assert!(!raindrop.supports_delete() && !raindrop.supports_tags());
+        let error = raindrop.delete_item(1).await.unwrap_err();
+        assert_eq!(error.to_string(), "raindrop doesn't support deleting items");
+        let error = raindrop.rename_tag("ml", "ai").await.unwrap_err();
+        assert_eq!(error.to_string(), "raindrop doesn't support changing tags");
+
+        let pocket: Box<dyn OnlineProvider> = Box::new(ProviderPocket::default());
+        assert!(pocket.supports_delete() && pocket.supports_tags());
+    }
 }
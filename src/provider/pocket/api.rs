@@ -1,4 +1,5 @@
 use crate::util::serialize::from_str;
+use crate::util::serialize::map_or_empty_array;
 use crate::util::serialize::option_bool_from_int_string;
 use crate::util::serialize::option_status_from_int_string;
 use crate::util::serialize::option_string_date_unix_timestamp_format;
@@ -65,7 +66,7 @@ pub async fn login(
             ("redirect_uri", "0.0.0.0".into()),
         ];
         let mut url = Url::parse("https://getpocket.com/auth/authorize").unwrap();
-        url.query_pairs_mut().extend_pairs(params.into_iter());
+        url.query_pairs_mut().extend_pairs(params);
         url
     };
 
@@ -134,19 +135,24 @@ struct PocketGetParams {
     detail_type: Option<PocketGetDetail>,
     count: Option<u32>,
     offset: Option<u32>,
+    since: Option<i64>,
 }
 
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
 enum PocketResponse {
-    Success(PocketGetResponse),
+    // Tried first since every field of a success is optional, successes send `"error": null`
     Error { error: String },
+    Success(PocketGetResponse),
 }
 
 #[derive(Deserialize, Debug)]
 struct PocketGetResponse {
-    list: Option<serde_json::Map<String, Value>>,
+    #[serde(default, deserialize_with = "map_or_empty_array")]
+    list: serde_json::Map<String, Value>,
     // status: Option<u16>,
+    /// Server time of the response, sent either as a number or a string
+    since: Option<Value>,
 }
 
 #[derive(Deserialize, Debug, Clone, PartialEq)]
@@ -188,8 +194,9 @@ pub struct PocketItem {
     pub status: Option<ItemStatus>,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Clone, Copy, sqlx::Type)]
 #[serde(rename_all = "lowercase")]
+#[repr(i64)]
 pub enum ItemStatus {
     Normal = 0,
     Archived = 1,
@@ -204,8 +211,32 @@ pub async fn get(
     client: &reqwest::Client,
     limit: Option<usize>,
 ) -> Result<Vec<PocketItem>, Box<dyn std::error::Error>> {
+    let (all_items, _) = get_since(access_token, consumer_key, client, limit, None).await?;
+    let all_items: Vec<PocketItem> = all_items
+        .into_iter()
+        .filter(|item| item.status != Some(ItemStatus::Deleted))
+        .collect();
+
+    println!(
+        "Finished fetching Pocket items. Total items: {}",
+        all_items.len()
+    );
+    Ok(all_items)
+}
+
+/// Fetches the items changed after `since` (all items when `None`), including archived
+/// and deleted ones so they can be applied locally.
+/// Returns the items along with the `since` value to use for the next sync
+pub async fn get_since(
+    access_token: &str,
+    consumer_key: &str,
+    client: &reqwest::Client,
+    limit: Option<usize>,
+    since: Option<i64>,
+) -> Result<(Vec<PocketItem>, Option<i64>), Box<dyn std::error::Error>> {
     println!("Starting to fetch Pocket items");
     let mut all_items = Vec::new();
+    let mut next_since = None;
     let mut offset = 0;
     let count = 30; // Maximum items per request
     let mut empty_responses = 0;
@@ -222,6 +253,7 @@ pub async fn get(
                 detail_type: Some(PocketGetDetail::Complete),
                 count: Some(count),
                 offset: Some(offset),
+                since,
             },
         };
 
@@ -242,75 +274,76 @@ pub async fn get(
 
         match resp {
             PocketResponse::Success(resp_json) => {
-                if let Some(list) = resp_json.list {
-                    let items: Vec<PocketItem> = list
-                        .into_iter()
-                        .filter_map(|(key, value)| {
-                            match serde_json::from_value::<PocketItem>(value.clone()) {
-                                Ok(item) => Some(item),
-                                Err(e) => {
-                                    // Print the problematic fields
-                                    eprintln!("Failed to parse item {}: {}", key, e);
-                                    if let Some(obj) = value.as_object() {
-                                        for (field, field_value) in obj {
-                                            if let Err(field_err) =
-                                                serde_json::from_value::<serde_json::Value>(
-                                                    field_value.clone(),
-                                                )
-                                            {
-                                                eprintln!(
-                                                    "  Field '{}' error: {}",
-                                                    field, field_err
-                                                );
-                                            }
+                // Keep the server time of the first page so nothing changed while paging is missed
+                if next_since.is_none() {
+                    next_since = resp_json.since.as_ref().and_then(|since| {
+                        since
+                            .as_i64()
+                            .or_else(|| since.as_str().and_then(|s| s.parse().ok()))
+                    });
+                }
+                let items: Vec<PocketItem> = resp_json
+                    .list
+                    .into_iter()
+                    .filter_map(|(key, value)| {
+                        match serde_json::from_value::<PocketItem>(value.clone()) {
+                            Ok(item) => Some(item),
+                            Err(e) => {
+                                // Print the problematic fields
+                                eprintln!("Failed to parse item {}: {}", key, e);
+                                if let Some(obj) = value.as_object() {
+                                    for (field, field_value) in obj {
+                                        if let Err(field_err) =
+                                            serde_json::from_value::<serde_json::Value>(
+                                                field_value.clone(),
+                                            )
+                                        {
+                                            eprintln!(
+                                                "  Field '{}' error: {}",
+                                                field, field_err
+                                            );
                                         }
                                     }
-                                    eprintln!("Raw JSON: {}", value);
-                                    None
                                 }
+                                eprintln!("Raw JSON: {}", value);
+                                None
                             }
-                        })
-                        .collect();
-
-                    let items_count = items.len();
-                    println!("Parsed {} items from response", items_count);
-
-                    if items.is_empty() {
-                        empty_responses += 1;
-                        println!(
-                            "Received empty list. Empty response count: {}",
-                            empty_responses
-                        );
-                        if empty_responses >= max_empty_responses {
-                            println!("Reached maximum number of consecutive empty responses. Breaking loop.");
-                            break;
                         }
-                    } else {
-                        empty_responses = 0; // Reset the counter when we receive items
-                        all_items.extend(items);
-                    }
+                    })
+                    .collect();
 
-                    offset += count;
-                    println!("Total items fetched so far: {}", all_items.len());
+                let items_count = items.len();
+                println!("Parsed {} items from response", items_count);
 
-                    // Check if we've reached the limit
-                    if let Some(limit) = limit {
-                        if all_items.len() >= limit {
-                            println!("Reached item limit. Breaking loop.");
-                            all_items.truncate(limit);
-                            break;
-                        }
-                    }
-                } else {
+                if items.is_empty() {
+                    empty_responses += 1;
                     println!(
-                        "Received response with no list field. Continuing to next request."
+                        "Received empty list. Empty response count: {}",
+                        empty_responses
                     );
+                    if empty_responses >= max_empty_responses {
+                        println!("Reached maximum number of consecutive empty responses. Breaking loop.");
+                        break;
+                    }
+                } else {
+                    empty_responses = 0; // Reset the counter when we receive items
+                    all_items.extend(items);
+                }
+
+                offset += count;
+                println!("Total items fetched so far: {}", all_items.len());
+
+                // Check if we've reached the limit
+                if let Some(limit) = limit {
+                    if all_items.len() >= limit {
+                        println!("Reached item limit. Breaking loop.");
+                        all_items.truncate(limit);
+                        break;
+                    }
                 }
             }
             PocketResponse::Error { error } => {
-                eprintln!("API returned error: {}", error);
-                // Instead of returning immediately, we'll log the error and continue
-                println!("Continuing to next request despite error.");
+                return Err(format!("Pocket API returned an error: {error}").into());
             }
         }
 
@@ -318,16 +351,7 @@ pub async fn get(
         tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
     }
 
-    let all_items: Vec<PocketItem> = all_items
-        .into_iter()
-        .filter(|item| item.status != Some(ItemStatus::Deleted))
-        .collect();
-
-    println!(
-        "Finished fetching Pocket items. Total items: {}",
-        all_items.len()
-    );
-    Ok(all_items)
+    Ok((all_items, next_since))
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -383,48 +407,56 @@ pub async fn add(
 }
 
 #[derive(Serialize)]
-struct PocketSendRequest {
-    actions: Vec<PocketFavoriteRequest>,
+struct PocketSendRequest<A> {
+    actions: Vec<A>,
 }
 
+/// @refer https://getpocket.com/developer/docs/v3/modify
 #[derive(Serialize)]
-#[serde(rename_all = "lowercase")]
+#[serde(rename_all = "snake_case")]
 enum SendAction {
     Favorite,
     Unfavorite,
+    Archive,
+    Readd,
+    TagsAdd,
+    TagsRemove,
+    Delete,
 }
 
 #[derive(Serialize)]
-struct PocketFavoriteRequest {
+struct PocketSendAction {
     #[serde(serialize_with = "serialize_as_string")]
     item_id: i64,
     action: SendAction,
     time: Option<String>,
+    /// Comma separated, only for the tags actions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tags: Option<String>,
 }
 
-pub async fn favorite(
+/// Actions on a tag of every item, they don't take an item_id
+#[derive(Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+enum PocketTagAction<'a> {
+    TagRename { old_tag: &'a str, new_tag: &'a str },
+    TagDelete { tag: &'a str },
+}
+
+/// Performs the actions through the `send` endpoint, `description` is used for logging
+async fn send<A: Serialize>(
     client: &reqwest::Client,
     access_token: &str,
     consumer_key: &str,
-    item_id: i64,
-    mark: bool,
+    actions: Vec<A>,
+    description: &str,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Starting Pocket favorite request");
+    println!("Starting Pocket {description} request");
 
     let body = &PocketRequest {
         access_token,
         consumer_key,
-        request: PocketSendRequest {
-            actions: vec![PocketFavoriteRequest {
-                item_id,
-                time: None,
-                action: if mark {
-                    SendAction::Favorite
-                } else {
-                    SendAction::Unfavorite
-                },
-            }],
-        },
+        request: PocketSendRequest { actions },
     };
 
     let response = client
@@ -435,14 +467,158 @@ pub async fn favorite(
         .await?;
 
     if response.status().is_success() {
-        println!("Successfully marked item as favorite in Pocket");
+        println!("Successfully sent {description} request to Pocket");
         Ok(())
     } else {
         let error_message = format!(
-            "Failed to mark item as favorite in Pocket. Status: {}",
+            "Failed to {description} item in Pocket. Status: {}",
             response.status()
         );
         println!("{}", error_message);
         Err(error_message.into())
     }
 }
+
+pub async fn favorite(
+    client: &reqwest::Client,
+    access_token: &str,
+    consumer_key: &str,
+    item_id: i64,
+    mark: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let action = PocketSendAction {
+        item_id,
+        time: None,
+        tags: None,
+        action: if mark {
+            SendAction::Favorite
+        } else {
+            SendAction::Unfavorite
+        },
+    };
+    send(client, access_token, consumer_key, vec![action], "favorite").await
+}
+
+/// Archives the item, or moves it back to the unread list with `mark` unset
+pub async fn archive(
+    client: &reqwest::Client,
+    access_token: &str,
+    consumer_key: &str,
+    item_id: i64,
+    mark: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let action = PocketSendAction {
+        item_id,
+        time: None,
+        tags: None,
+        action: if mark {
+            SendAction::Archive
+        } else {
+            SendAction::Readd
+        },
+    };
+    send(client, access_token, consumer_key, vec![action], "archive").await
+}
+
+/// Adds and removes tags in a single request, nothing is sent when both are empty
+pub async fn update_tags(
+    client: &reqwest::Client,
+    access_token: &str,
+    consumer_key: &str,
+    item_id: i64,
+    add: &[String],
+    remove: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let actions = [(SendAction::TagsAdd, add), (SendAction::TagsRemove, remove)]
+        .into_iter()
+        .filter(|(_, tags)| !tags.is_empty())
+        .map(|(action, tags)| PocketSendAction {
+            item_id,
+            action,
+            time: None,
+            tags: Some(tags.join(",")),
+        })
+        .collect::<Vec<_>>();
+    if actions.is_empty() {
+        return Ok(());
+    }
+    send(client, access_token, consumer_key, actions, "tags update").await
+}
+
+pub async fn delete(
+    client: &reqwest::Client,
+    access_token: &str,
+    consumer_key: &str,
+    item_id: i64,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let action = PocketSendAction {
+        item_id,
+        action: SendAction::Delete,
+        time: None,
+        tags: None,
+    };
+    send(client, access_token, consumer_key, vec![action], "delete").await
+}
+
+/// Renames the tag on every item, it is merged when `new_tag` already exists
+pub async fn tag_rename(
+    client: &reqwest::Client,
+    access_token: &str,
+    consumer_key: &str,
+    old_tag: &str,
+    new_tag: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let action = PocketTagAction::TagRename { old_tag, new_tag };
+    send(
+        client,
+        access_token,
+        consumer_key,
+        vec![action],
+        "tag rename",
+    )
+    .await
+}
+
+pub async fn tag_delete(
+    client: &reqwest::Client,
+    access_token: &str,
+    consumer_key: &str,
+    tag: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let action = PocketTagAction::TagDelete { tag };
+    send(
+        client,
+        access_token,
+        consumer_key,
+        vec![action],
+        "tag delete",
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PocketResponse;
+
+    #[test]
+    fn empty_list_is_no_items() {
+        let resp: PocketResponse = serde_json::from_str(
+            r#"{"status": 2, "list": [], "error": null, "since": 1700000000}"#,
+        )
+        .unwrap();
+        let PocketResponse::Success(resp) = resp else {
+            panic!("parsed as an error");
+        };
+        assert!(resp.list.is_empty());
+        assert_eq!(
+            resp.since.and_then(|since| since.as_i64()),
+            Some(1700000000)
+        );
+    }
+
+    #[test]
+    fn error_response() {
+        let resp: PocketResponse = serde_json::from_str(r#"{"error": "Bad request"}"#).unwrap();
+        assert!(matches!(resp, PocketResponse::Error { .. }));
+    }
+}
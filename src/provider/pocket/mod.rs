@@ -1,10 +1,34 @@
-use super::{Insertable, OnlineProvider, Provider, ResearchItem};
-use crate::db::{Secrets, Tags};
-use api::{add, favorite, get, login, PocketItem};
+use super::{
+    not_authenticated, BoxFuture, Insertable, OnlineProvider, ProviderResult, Registration,
+    ResearchItem, SyncedItems,
+};
+use crate::db::{ItemDetails, Secrets, Tags, DB, DEFAULT_ACCOUNT};
+use api::{
+    add, archive, delete, favorite, get, get_since, login, tag_delete, tag_rename, update_tags,
+    ItemStatus, PocketItem,
+};
 use chrono::Utc;
 
 pub mod api;
 
+pub const REGISTRATION: Registration = Registration {
+    name: "pocket",
+    required_secrets: &["consumer_key", "access_token"],
+    connect,
+};
+
+fn connect<'a>(
+    db: &'a DB,
+    account: &'a str,
+) -> BoxFuture<'a, ProviderResult<Box<dyn OnlineProvider>>> {
+    Box::pin(async move {
+        let secrets = db.get_secrets("pocket", account).await?;
+        let provider = ProviderPocket::from_secrets(&secrets)
+            .ok_or_else(|| not_authenticated("pocket", account))?;
+        Ok(Box::new(provider) as Box<dyn OnlineProvider>)
+    })
+}
+
 #[derive(Debug, Default)]
 pub struct ProviderPocket {
     pub consumer_key: String,
@@ -12,58 +36,166 @@ pub struct ProviderPocket {
     pub client: reqwest::Client,
 }
 
-impl Provider for ProviderPocket {
-    type Item = PocketItem;
+impl ProviderPocket {
+    /// Provider using the keys saved with `pocket auth`, if any
+    pub fn from_secrets(secrets: &Secrets) -> Option<Self> {
+        Some(Self {
+            consumer_key: secrets.get("consumer_key")?.to_string(),
+            access_token: Some(secrets.get("access_token")?.to_string()),
+            ..Default::default()
+        })
+    }
 }
 
 impl OnlineProvider for ProviderPocket {
-    async fn authenticate(&self) -> Result<Secrets, Box<dyn std::error::Error>> {
-        let access_token = login(&self.client, &self.consumer_key).await?;
-        Ok(Secrets {
-            pocket_consumer_key: Some(self.consumer_key.clone()),
-            pocket_access_token: Some(access_token),
-            ..Default::default()
+    fn name(&self) -> &'static str {
+        "pocket"
+    }
+
+    fn authenticate(&self) -> BoxFuture<'_, ProviderResult<Secrets>> {
+        Box::pin(async move {
+            let access_token = login(&self.client, &self.consumer_key).await?;
+            Ok(Secrets::new("pocket", DEFAULT_ACCOUNT)
+                .with("consumer_key", &self.consumer_key)
+                .with("access_token", access_token))
         })
     }
 
-    async fn fetch_items(
+    fn fetch_items(
         &self,
         limit: Option<usize>,
-    ) -> Result<Vec<PocketItem>, Box<dyn std::error::Error>> {
-        let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
-        get(access_token, &self.consumer_key, &self.client, limit).await
+    ) -> BoxFuture<'_, ProviderResult<Vec<Box<dyn Insertable>>>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            let items = get(access_token, &self.consumer_key, &self.client, limit).await?;
+            Ok(items
+                .into_iter()
+                .map(|item| Box::new(item) as Box<dyn Insertable>)
+                .collect())
+        })
     }
 
-    async fn add_item(
-        &self,
-        uri: &str,
-        tags: Vec<&str>,
-    ) -> Result<Option<i64>, Box<dyn std::error::Error>> {
-        let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
-        let add_request = api::PocketAddRequest {
-            url: uri,
-            title: None,
-            tags: Some(&tags),
-        };
-        let item_id = add(&self.client, access_token, &self.consumer_key, add_request).await?;
-        Ok(Some(item_id))
-    }
-
-    async fn mark_as_favorite(
-        &self,
+    fn supports_sync(&self) -> bool {
+        true
+    }
+
+    fn sync_items(&self, since: Option<i64>) -> BoxFuture<'_, ProviderResult<SyncedItems>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            let (items, next_since) =
+                get_since(access_token, &self.consumer_key, &self.client, None, since).await?;
+            let (deleted, items): (Vec<_>, Vec<_>) = items
+                .into_iter()
+                .partition(|item| item.status == Some(ItemStatus::Deleted));
+            Ok(SyncedItems {
+                items: items
+                    .into_iter()
+                    .map(|item| Box::new(item) as Box<dyn Insertable>)
+                    .collect(),
+                deleted: deleted.iter().map(|item| item.item_id as i64).collect(),
+                next_since,
+            })
+        })
+    }
+
+    fn add_item<'a>(
+        &'a self,
+        uri: &'a str,
+        tags: Vec<&'a str>,
+    ) -> BoxFuture<'a, ProviderResult<Option<i64>>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            let add_request = api::PocketAddRequest {
+                url: uri,
+                title: None,
+                tags: Some(&tags),
+            };
+            let item_id =
+                add(&self.client, access_token, &self.consumer_key, add_request).await?;
+            Ok(Some(item_id))
+        })
+    }
+
+    fn mark_as_favorite(&self, item_id: i64, mark: bool) -> BoxFuture<'_, ProviderResult<()>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            favorite(
+                &self.client,
+                access_token,
+                &self.consumer_key,
+                item_id,
+                mark,
+            )
+            .await?;
+            Ok(())
+        })
+    }
+
+    fn mark_as_archived(&self, item_id: i64, mark: bool) -> BoxFuture<'_, ProviderResult<()>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            archive(
+                &self.client,
+                access_token,
+                &self.consumer_key,
+                item_id,
+                mark,
+            )
+            .await
+        })
+    }
+
+    fn supports_delete(&self) -> bool {
+        true
+    }
+
+    fn delete_item(&self, item_id: i64) -> BoxFuture<'_, ProviderResult<()>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            delete(&self.client, access_token, &self.consumer_key, item_id).await
+        })
+    }
+
+    fn supports_tags(&self) -> bool {
+        true
+    }
+
+    fn update_tags<'a>(
+        &'a self,
         item_id: i64,
-        mark: bool,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
-        favorite(
-            &self.client,
-            access_token,
-            &self.consumer_key,
-            item_id,
-            mark,
-        )
-        .await?;
-        Ok(())
+        add: &'a [String],
+        remove: &'a [String],
+    ) -> BoxFuture<'a, ProviderResult<()>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            update_tags(
+                &self.client,
+                access_token,
+                &self.consumer_key,
+                item_id,
+                add,
+                remove,
+            )
+            .await
+        })
+    }
+
+    fn rename_tag<'a>(
+        &'a self,
+        old: &'a str,
+        new: &'a str,
+    ) -> BoxFuture<'a, ProviderResult<()>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            tag_rename(&self.client, access_token, &self.consumer_key, old, new).await
+        })
+    }
+
+    fn delete_tag<'a>(&'a self, tag: &'a str) -> BoxFuture<'a, ProviderResult<()>> {
+        Box::pin(async move {
+            let access_token = self.access_token.as_ref().ok_or("Access token not found")?;
+            tag_delete(&self.client, access_token, &self.consumer_key, tag).await
+        })
     }
 }
 
@@ -88,7 +220,9 @@ impl Insertable for PocketItem {
             .map_or("#".into(), |url| url.to_string());
 
         ResearchItem {
-            id: Some(self.item_id as i64),
+            id: None,
+            remote_id: Some(self.item_id as i64),
+            account: None,
             uri,
             title,
             excerpt: self.excerpt.as_ref().map_or("".to_string(), |s| s.clone()),
@@ -96,6 +230,9 @@ impl Insertable for PocketItem {
             favorite: self.favorite.unwrap_or(false),
             lang: self.lang.clone(),
             notes: None,
+            status: self.status.unwrap_or(ItemStatus::Normal),
+            time_read: self.time_read.map(|time| time.timestamp()),
+            details: ItemDetails::default(),
         }
     }
 
@@ -0,0 +1,227 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+pub const API_URL: &str = "https://api.raindrop.io/rest/v1";
+
+/// Maximum allowed by the API
+const PER_PAGE: usize = 50;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct CollectionRef {
+    #[serde(rename = "$id")]
+    pub id: i64,
+}
+
+/// @refer https://developer.raindrop.io/v1/raindrops
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Raindrop {
+    #[serde(rename = "_id")]
+    pub id: i64,
+    pub link: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub excerpt: String,
+    #[serde(default)]
+    pub note: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// ISO 8601 creation date
+    pub created: Option<String>,
+    #[serde(default)]
+    pub important: bool,
+    pub collection: Option<CollectionRef>,
+    /// Title of the collection the raindrop is in, resolved after fetching
+    #[serde(skip)]
+    pub collection_title: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RaindropsResponse {
+    #[serde(default)]
+    items: Vec<Raindrop>,
+    count: Option<usize>,
+}
+
+#[derive(Deserialize, Debug)]
+struct Collection {
+    #[serde(rename = "_id")]
+    id: i64,
+    title: String,
+}
+
+#[derive(Deserialize, Debug)]
+struct CollectionsResponse {
+    #[serde(default)]
+    items: Vec<Collection>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RaindropResponse {
+    item: Raindrop,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct UserResponse {
+    user: serde_json::Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateRaindropRequest<'a> {
+    link: &'a str,
+    tags: &'a [&'a str],
+    /// Asks raindrop to fill in the title, excerpt and cover in the background
+    please_parse: serde_json::Value,
+}
+
+#[derive(Serialize)]
+struct UpdateRaindropRequest {
+    important: bool,
+}
+
+fn check_status(
+    response: reqwest::Response,
+    description: &str,
+) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(format!(
+            "Failed to {description} in Raindrop. Status: {}",
+            response.status()
+        )
+        .into())
+    }
+}
+
+/// Checks that the test token is valid by fetching the current user
+pub async fn verify_token(
+    client: &reqwest::Client,
+    base_url: &str,
+    access_token: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let response = client
+        .get(format!("{base_url}/user"))
+        .bearer_auth(access_token)
+        .send()
+        .await?;
+    let response = check_status(response, "verify token")?;
+    response.json::<UserResponse>().await?;
+    Ok(())
+}
+
+/// Titles of all root and nested collections by id
+/// @refer https://developer.raindrop.io/v1/collections/methods
+pub async fn get_collections(
+    client: &reqwest::Client,
+    base_url: &str,
+    access_token: &str,
+) -> Result<HashMap<i64, String>, Box<dyn std::error::Error>> {
+    let mut collections = HashMap::new();
+    for path in ["collections", "collections/childrens"] {
+        let response = client
+            .get(format!("{base_url}/{path}"))
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+        let response = check_status(response, "fetch collections")?;
+        let response = response.json::<CollectionsResponse>().await?;
+        collections.extend(response.items.into_iter().map(|c| (c.id, c.title)));
+    }
+    Ok(collections)
+}
+
+/// Pages through every raindrop outside the trash, newest first
+pub async fn get(
+    client: &reqwest::Client,
+    base_url: &str,
+    access_token: &str,
+    limit: Option<usize>,
+) -> Result<Vec<Raindrop>, Box<dyn std::error::Error>> {
+    let collections = get_collections(client, base_url, access_token).await?;
+
+    let mut all_items = Vec::new();
+    let mut page = 0;
+    loop {
+        let response = client
+            .get(format!("{base_url}/raindrops/0"))
+            .query(&[
+                ("page", page.to_string()),
+                ("perpage", PER_PAGE.to_string()),
+                ("sort", "-created".to_string()),
+            ])
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+        let response = check_status(response, "fetch raindrops")?;
+        let response = response.json::<RaindropsResponse>().await?;
+
+        let items_count = response.items.len();
+        all_items.extend(response.items);
+
+        if let Some(limit) = limit {
+            if all_items.len() >= limit {
+                all_items.truncate(limit);
+                break;
+            }
+        }
+        if items_count < PER_PAGE || response.count.is_some_and(|c| all_items.len() >= c) {
+            break;
+        }
+        page += 1;
+    }
+
+    for item in all_items.iter_mut() {
+        item.collection_title = item
+            .collection
+            .as_ref()
+            .and_then(|c| collections.get(&c.id))
+            .cloned();
+    }
+
+    Ok(all_items)
+}
+
+/// Saves the link to the unsorted collection, returns the id of the new raindrop
+pub async fn add(
+    client: &reqwest::Client,
+    base_url: &str,
+    access_token: &str,
+    link: &str,
+    tags: &[&str],
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let response = client
+        .post(format!("{base_url}/raindrop"))
+        .bearer_auth(access_token)
+        .json(&CreateRaindropRequest {
+            link,
+            tags,
+            please_parse: serde_json::json!({}),
+        })
+        .send()
+        .await?;
+    // Awaited separately so no error is held across the body await
+    let response = check_status(response, "add item")?;
+    let response = response.json::<RaindropResponse>().await?;
+    Ok(response.item.id)
+}
+
+/// Raindrop calls favorites "important"
+pub async fn set_important(
+    client: &reqwest::Client,
+    base_url: &str,
+    access_token: &str,
+    id: i64,
+    important: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let response = client
+        .put(format!("{base_url}/raindrop/{id}"))
+        .bearer_auth(access_token)
+        .json(&UpdateRaindropRequest { important })
+        .send()
+        .await?;
+    check_status(response, "mark item as favorite")?;
+    Ok(())
+}
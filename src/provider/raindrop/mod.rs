@@ -0,0 +1,335 @@
+use super::{
+    not_authenticated, BoxFuture, Insertable, OnlineProvider, ProviderResult, Registration,
+    ResearchItem,
+};
+use crate::db::{ItemDetails, Secrets, Tags, DB, DEFAULT_ACCOUNT};
+use crate::provider::pocket::api::ItemStatus;
+use api::{add, get, set_important, verify_token, Raindrop};
+use chrono::{DateTime, Utc};
+
+pub mod api;
+
+pub const REGISTRATION: Registration = Registration {
+    name: "raindrop",
+    required_secrets: &["access_token"],
+    connect,
+};
+
+fn connect<'a>(
+    db: &'a DB,
+    account: &'a str,
+) -> BoxFuture<'a, ProviderResult<Box<dyn OnlineProvider>>> {
+    Box::pin(async move {
+        let secrets = db.get_secrets("raindrop", account).await?;
+        let provider = ProviderRaindrop::from_secrets(&secrets)
+            .ok_or_else(|| not_authenticated("raindrop", account))?;
+        Ok(Box::new(provider) as Box<dyn OnlineProvider>)
+    })
+}
+
+#[derive(Debug)]
+pub struct ProviderRaindrop {
+    /// Test token from https://app.raindrop.io/settings/integrations
+    pub access_token: String,
+    /// Overridable so the API can be mocked
+    pub base_url: String,
+    pub client: reqwest::Client,
+}
+
+impl Default for ProviderRaindrop {
+    fn default() -> Self {
+        Self {
+            access_token: String::new(),
+            base_url: api::API_URL.to_string(),
+            client: reqwest::Client::default(),
+        }
+    }
+}
+
+impl ProviderRaindrop {
+    /// Provider using the token saved with `raindrop auth`, if any
+    pub fn from_secrets(secrets: &Secrets) -> Option<Self> {
+        secrets.get("access_token").map(|access_token| Self {
+            access_token: access_token.to_string(),
+            ..Default::default()
+        })
+    }
+}
+
+impl OnlineProvider for ProviderRaindrop {
+    fn name(&self) -> &'static str {
+        "raindrop"
+    }
+
+    fn authenticate(&self) -> BoxFuture<'_, ProviderResult<Secrets>> {
+        Box::pin(async move {
+            verify_token(&self.client, &self.base_url, &self.access_token).await?;
+            Ok(Secrets::new("raindrop", DEFAULT_ACCOUNT)
+                .with("access_token", &self.access_token))
+        })
+    }
+
+    fn fetch_items(
+        &self,
+        limit: Option<usize>,
+    ) -> BoxFuture<'_, ProviderResult<Vec<Box<dyn Insertable>>>> {
+        Box::pin(async move {
+            let items = get(&self.client, &self.base_url, &self.access_token, limit).await?;
+            Ok(items
+                .into_iter()
+                .map(|item| Box::new(item) as Box<dyn Insertable>)
+                .collect())
+        })
+    }
+
+    fn add_item<'a>(
+        &'a self,
+        uri: &'a str,
+        tags: Vec<&'a str>,
+    ) -> BoxFuture<'a, ProviderResult<Option<i64>>> {
+        Box::pin(async move {
+            let id = add(&self.client, &self.base_url, &self.access_token, uri, &tags).await?;
+            Ok(Some(id))
+        })
+    }
+
+    fn mark_as_favorite(&self, item_id: i64, mark: bool) -> BoxFuture<'_, ProviderResult<()>> {
+        Box::pin(set_important(
+            &self.client,
+            &self.base_url,
+            &self.access_token,
+            item_id,
+            mark,
+        ))
+    }
+
+    fn supports_archive(&self) -> bool {
+        false
+    }
+
+    fn mark_as_archived(
+        &self,
+        _item_id: i64,
+        _mark: bool,
+    ) -> BoxFuture<'_, ProviderResult<()>> {
+        Box::pin(async { Err("Raindrop doesn't support archiving items".into()) })
+    }
+}
+
+impl Insertable for Raindrop {
+    fn to_research_item(&self) -> ResearchItem {
+        let title = Some(self.title.clone())
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| "Untitled".to_string());
+
+        ResearchItem {
+            id: None,
+            remote_id: Some(self.id),
+            account: None,
+            uri: self.link.clone(),
+            title,
+            excerpt: self.excerpt.clone(),
+            time_added: self
+                .created
+                .as_deref()
+                .and_then(|created| DateTime::parse_from_rfc3339(created).ok())
+                .map_or_else(|| Utc::now().timestamp(), |created| created.timestamp()),
+            favorite: self.important,
+            lang: None,
+            notes: Some(self.note.clone()).filter(|note| !note.is_empty()),
+            status: ItemStatus::Normal,
+            time_read: None,
+            details: ItemDetails::default(),
+        }
+    }
+
+    /// The raindrop's tags followed by the title of its collection
+    fn to_tags(&self) -> Vec<Tags> {
+        self.tags
+            .iter()
+            .chain(self.collection_title.iter())
+            .map(|tag| Tags {
+                tag_name: tag.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Path, Query, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post, put};
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+
+    use super::ProviderRaindrop;
+    use crate::provider::{spawn_mock, OnlineProvider};
+
+    const TOKEN: &str = "test-token";
+    /// More than one page of raindrops
+    const TOTAL: i64 = 60;
+
+    /// Requests the mock received that change raindrops
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    fn authorized(headers: &HeaderMap) -> Result<(), StatusCode> {
+        match headers.get("authorization") {
+            Some(value) if value == &format!("Bearer {TOKEN}") => Ok(()),
+            _ => Err(StatusCode::UNAUTHORIZED),
+        }
+    }
+
+    async fn raindrops(
+        headers: HeaderMap,
+        Query(query): Query<Vec<(String, String)>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        authorized(&headers)?;
+        let param = |name: &str| {
+            query
+                .iter()
+                .find(|(key, _)| key == name)
+                .map(|(_, value)| value.parse::<i64>().unwrap())
+                .unwrap()
+        };
+        let (page, per_page) = (param("page"), param("perpage"));
+        let items: Vec<Value> = (page * per_page..TOTAL.min((page + 1) * per_page))
+            .map(|id| {
+                json!({
+                    "_id": id,
+                    "link": format!("https://example.com/{id}"),
+                    "title": if id == 0 { "" } else { "Post" },
+                    "tags": ["rust"],
+                    "created": "2024-05-01T10:00:00.000Z",
+                    "important": id == 1,
+                    "collection": { "$id": id % 3 },
+                })
+            })
+            .collect();
+        Ok(Json(json!({ "items": items, "count": TOTAL })))
+    }
+
+    async fn mock_api(received: Received) -> String {
+        let app = Router::new()
+            .route(
+                "/user",
+                get(|headers: HeaderMap| async move {
+                    authorized(&headers).map(|_| Json(json!({ "user": { "_id": 1 } })))
+                }),
+            )
+            .route(
+                "/collections",
+                get(|| async { Json(json!({ "items": [{ "_id": 1, "title": "Reading" }] })) }),
+            )
+            .route(
+                "/collections/childrens",
+                get(|| async { Json(json!({ "items": [{ "_id": 2, "title": "Papers" }] })) }),
+            )
+            .route("/raindrops/0", get(raindrops))
+            .route(
+                "/raindrop",
+                post(
+                    |State(received): State<Received>, Json(body): Json<Value>| async move {
+                        let link = body["link"].clone();
+                        received.lock().unwrap().push(("POST".to_string(), body));
+                        Json(json!({ "item": { "_id": 99, "link": link } }))
+                    },
+                ),
+            )
+            .route(
+                "/raindrop/{id}",
+                put(
+                    |State(received): State<Received>,
+                     Path(id): Path<i64>,
+                     Json(body): Json<Value>| async move {
+                        received.lock().unwrap().push((format!("PUT {id}"), body));
+                        Json(json!({ "result": true }))
+                    },
+                ),
+            )
+            .with_state(received);
+        spawn_mock(app).await
+    }
+
+    fn provider(base_url: String, access_token: &str) -> ProviderRaindrop {
+        ProviderRaindrop {
+            access_token: access_token.to_string(),
+            base_url,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn authenticate_checks_the_token() {
+        let base_url = mock_api(Received::default()).await;
+        let secrets = provider(base_url.clone(), TOKEN)
+            .authenticate()
+            .await
+            .unwrap();
+        assert_eq!(secrets.get("access_token"), Some(TOKEN));
+
+        let error = provider(base_url, "wrong")
+            .authenticate()
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("401"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn fetch_items_pages_and_resolves_collections() {
+        let base_url = mock_api(Received::default()).await;
+        let items = provider(base_url.clone(), TOKEN)
+            .fetch_items(None)
+            .await
+            .unwrap();
+        assert_eq!(items.len(), TOTAL as usize);
+
+        let first = items[0].to_research_item();
+        assert_eq!(first.remote_id, Some(0));
+        assert_eq!(first.uri, "https://example.com/0");
+        assert_eq!(first.title, "Untitled");
+        assert_eq!(first.time_added, 1714557600);
+        let tags = |index: usize| -> Vec<String> {
+            items[index]
+                .to_tags()
+                .into_iter()
+                .map(|tag| tag.tag_name)
+                .collect()
+        };
+        assert_eq!(tags(0), ["rust"]);
+        assert_eq!(tags(1), ["rust", "Reading"]);
+        assert_eq!(tags(2), ["rust", "Papers"]);
+        assert!(items[1].to_research_item().favorite);
+        assert_eq!(items[59].to_research_item().remote_id, Some(59));
+
+        let items = provider(base_url, TOKEN)
+            .fetch_items(Some(55))
+            .await
+            .unwrap();
+        assert_eq!(items.len(), 55);
+    }
+
+    #[tokio::test]
+    async fn add_and_favorite_items() {
+        let received = Received::default();
+        let raindrop = provider(mock_api(received.clone()).await, TOKEN);
+
+        let id = raindrop
+            .add_item("https://example.com/new", vec!["rust", "db"])
+            .await
+            .unwrap();
+        assert_eq!(id, Some(99));
+        raindrop.mark_as_favorite(99, true).await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(received[0].0, "POST");
+        assert_eq!(received[0].1["link"], "https://example.com/new");
+        assert_eq!(received[0].1["tags"], json!(["rust", "db"]));
+        assert_eq!(
+            received[1],
+            ("PUT 99".to_string(), json!({ "important": true }))
+        );
+    }
+}
@@ -0,0 +1,223 @@
+use serde::{Deserialize, Serialize};
+
+const PER_PAGE: usize = 50;
+
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct WallabagTag {
+    pub label: String,
+}
+
+/// @refer https://app.wallabag.it/api/doc
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub id: i64,
+    pub url: String,
+    pub title: Option<String>,
+    /// Article HTML extracted by wallabag
+    pub content: Option<String>,
+    #[serde(default)]
+    pub is_archived: i64,
+    #[serde(default)]
+    pub is_starred: i64,
+    #[serde(default)]
+    pub tags: Vec<WallabagTag>,
+    /// Like 2024-01-31T10:00:00+0100
+    pub created_at: Option<String>,
+    pub archived_at: Option<String>,
+    pub language: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TokenResponse {
+    pub access_token: String,
+    pub refresh_token: String,
+    /// Seconds the access token is valid for
+    pub expires_in: Option<i64>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EntriesEmbedded {
+    #[serde(default)]
+    items: Vec<Entry>,
+}
+
+#[derive(Deserialize, Debug)]
+struct EntriesResponse {
+    pages: usize,
+    #[serde(rename = "_embedded")]
+    embedded: EntriesEmbedded,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "grant_type", rename_all = "snake_case")]
+enum TokenRequest<'a> {
+    Password {
+        client_id: &'a str,
+        client_secret: &'a str,
+        username: &'a str,
+        password: &'a str,
+    },
+    RefreshToken {
+        client_id: &'a str,
+        client_secret: &'a str,
+        refresh_token: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct CreateEntryRequest<'a> {
+    url: &'a str,
+    /// Comma separated
+    tags: String,
+}
+
+#[derive(Serialize)]
+pub struct UpdateEntryRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub starred: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub archive: Option<u8>,
+}
+
+fn check_status(
+    response: reqwest::Response,
+    description: &str,
+) -> Result<reqwest::Response, Box<dyn std::error::Error>> {
+    if response.status().is_success() {
+        Ok(response)
+    } else {
+        Err(format!(
+            "Failed to {description} in Wallabag. Status: {}",
+            response.status()
+        )
+        .into())
+    }
+}
+
+async fn request_token(
+    client: &reqwest::Client,
+    base_url: &str,
+    request: &TokenRequest<'_>,
+) -> Result<TokenResponse, Box<dyn std::error::Error>> {
+    let response = client
+        .post(format!("{base_url}/oauth/v2/token"))
+        .form(request)
+        .send()
+        .await?;
+    let response = check_status(response, "get an access token")?;
+    Ok(response.json::<TokenResponse>().await?)
+}
+
+/// OAuth2 password grant, the password itself is never stored
+pub async fn login(
+    client: &reqwest::Client,
+    base_url: &str,
+    client_id: &str,
+    client_secret: &str,
+    username: &str,
+    password: &str,
+) -> Result<TokenResponse, Box<dyn std::error::Error>> {
+    let request = TokenRequest::Password {
+        client_id,
+        client_secret,
+        username,
+        password,
+    };
+    request_token(client, base_url, &request).await
+}
+
+/// Access tokens expire after an hour, the refresh token is replaced too
+pub async fn refresh(
+    client: &reqwest::Client,
+    base_url: &str,
+    client_id: &str,
+    client_secret: &str,
+    refresh_token: &str,
+) -> Result<TokenResponse, Box<dyn std::error::Error>> {
+    let request = TokenRequest::RefreshToken {
+        client_id,
+        client_secret,
+        refresh_token,
+    };
+    request_token(client, base_url, &request).await
+}
+
+/// Pages through every entry, newest first
+pub async fn get(
+    client: &reqwest::Client,
+    base_url: &str,
+    access_token: &str,
+    limit: Option<usize>,
+) -> Result<Vec<Entry>, Box<dyn std::error::Error>> {
+    let mut all_items = Vec::new();
+    let mut page = 1;
+    loop {
+        let response = client
+            .get(format!("{base_url}/api/entries.json"))
+            .query(&[
+                ("page", page.to_string()),
+                ("perPage", PER_PAGE.to_string()),
+                ("sort", "created".to_string()),
+                ("order", "desc".to_string()),
+            ])
+            .bearer_auth(access_token)
+            .send()
+            .await?;
+        let response = check_status(response, "fetch entries")?;
+        let response = response.json::<EntriesResponse>().await?;
+
+        all_items.extend(response.embedded.items);
+
+        if let Some(limit) = limit {
+            if all_items.len() >= limit {
+                all_items.truncate(limit);
+                break;
+            }
+        }
+        if page >= response.pages {
+            break;
+        }
+        page += 1;
+    }
+
+    Ok(all_items)
+}
+
+/// Wallabag fetches the page itself, returns the id of the new entry
+pub async fn add(
+    client: &reqwest::Client,
+    base_url: &str,
+    access_token: &str,
+    url: &str,
+    tags: &[&str],
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let response = client
+        .post(format!("{base_url}/api/entries.json"))
+        .bearer_auth(access_token)
+        .json(&CreateEntryRequest {
+            url,
+            tags: tags.join(","),
+        })
+        .send()
+        .await?;
+    let response = check_status(response, "add item")?;
+    let entry = response.json::<Entry>().await?;
+    Ok(entry.id)
+}
+
+pub async fn update(
+    client: &reqwest::Client,
+    base_url: &str,
+    access_token: &str,
+    id: i64,
+    request: &UpdateEntryRequest,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let response = client
+        .patch(format!("{base_url}/api/entries/{id}.json"))
+        .bearer_auth(access_token)
+        .json(request)
+        .send()
+        .await?;
+    check_status(response, "update item")?;
+    Ok(())
+}
@@ -0,0 +1,513 @@
+use super::{
+    not_authenticated, BoxFuture, Insertable, OnlineProvider, ProviderResult, Registration,
+    ResearchItem,
+};
+use crate::db::{ItemDetails, Secrets, Tags, DB, DEFAULT_ACCOUNT};
+use crate::provider::pocket::api::ItemStatus;
+use api::{add, get, login, refresh, update, Entry, TokenResponse, UpdateEntryRequest};
+use chrono::{DateTime, Utc};
+use scraper::Html;
+
+pub mod api;
+
+pub const REGISTRATION: Registration = Registration {
+    name: "wallabag",
+    required_secrets: &["url", "client_id", "client_secret", "refresh_token"],
+    connect,
+};
+
+fn connect<'a>(
+    db: &'a DB,
+    account: &'a str,
+) -> BoxFuture<'a, ProviderResult<Box<dyn OnlineProvider>>> {
+    Box::pin(async move {
+        let provider = ProviderWallabag::connect(db, account).await?;
+        Ok(Box::new(provider) as Box<dyn OnlineProvider>)
+    })
+}
+
+/// Length of the excerpt taken from the start of the article
+const EXCERPT_LENGTH: usize = 200;
+/// Access tokens expiring within this many seconds are refreshed before they're used
+const EXPIRY_MARGIN: i64 = 60;
+
+#[derive(Debug, Default)]
+pub struct ProviderWallabag {
+    /// Root of the instance, like https://app.wallabag.it
+    pub base_url: String,
+    /// API client created at <base_url>/developer
+    pub client_id: String,
+    pub client_secret: String,
+    /// Only used by `authenticate`, never saved
+    pub username: String,
+    pub password: String,
+    pub access_token: String,
+    pub refresh_token: String,
+    pub client: reqwest::Client,
+}
+
+impl ProviderWallabag {
+    /// Provider using the tokens saved with `wallabag auth`, if any
+    pub fn from_secrets(secrets: &Secrets) -> Option<Self> {
+        Some(Self {
+            base_url: secrets.get("url")?.to_string(),
+            client_id: secrets.get("client_id")?.to_string(),
+            client_secret: secrets.get("client_secret")?.to_string(),
+            access_token: secrets.get("access_token")?.to_string(),
+            refresh_token: secrets.get("refresh_token")?.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Provider of the account. The access token is refreshed when it expired, or when its
+    /// expiry wasn't saved, and the new tokens are saved.
+    pub async fn connect(db: &DB, account: &str) -> ProviderResult<Self> {
+        let secrets = db.get_secrets("wallabag", account).await?;
+        let mut provider = Self::from_secrets(&secrets)
+            .ok_or_else(|| not_authenticated("wallabag", account))?;
+        let expires_at = secrets
+            .get("expires_at")
+            .and_then(|expires_at| expires_at.parse::<i64>().ok());
+        if expires_at
+            .is_some_and(|expires_at| expires_at > Utc::now().timestamp() + EXPIRY_MARGIN)
+        {
+            return Ok(provider);
+        }
+
+        let secrets = provider
+            .refresh()
+            .await
+            .map_err(|e| format!("Failed to refresh the wallabag token: {e}"))?;
+        db.set_secret(Secrets {
+            account: account.to_string(),
+            ..secrets
+        })
+        .await?;
+        Ok(provider)
+    }
+
+    /// Gets a fresh access token, the returned secrets have to be saved
+    /// because the previous refresh token stops working
+    pub async fn refresh(&mut self) -> Result<Secrets, Box<dyn std::error::Error>> {
+        let tokens = refresh(
+            &self.client,
+            &self.base_url,
+            &self.client_id,
+            &self.client_secret,
+            &self.refresh_token,
+        )
+        .await?;
+        self.access_token = tokens.access_token.clone();
+        self.refresh_token = tokens.refresh_token.clone();
+        Ok(self.to_secrets(tokens))
+    }
+
+    fn to_secrets(&self, tokens: TokenResponse) -> Secrets {
+        let secrets = Secrets::new("wallabag", DEFAULT_ACCOUNT)
+            .with("url", &self.base_url)
+            .with("client_id", &self.client_id)
+            .with("client_secret", &self.client_secret)
+            .with("access_token", tokens.access_token)
+            .with("refresh_token", tokens.refresh_token);
+        match tokens.expires_in {
+            Some(expires_in) => secrets.with(
+                "expires_at",
+                (Utc::now().timestamp() + expires_in).to_string(),
+            ),
+            None => secrets,
+        }
+    }
+
+    async fn update(
+        &self,
+        item_id: i64,
+        request: UpdateEntryRequest,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        update(
+            &self.client,
+            &self.base_url,
+            &self.access_token,
+            item_id,
+            &request,
+        )
+        .await
+    }
+}
+
+impl OnlineProvider for ProviderWallabag {
+    fn name(&self) -> &'static str {
+        "wallabag"
+    }
+
+    fn authenticate(&self) -> BoxFuture<'_, ProviderResult<Secrets>> {
+        Box::pin(async move {
+            let tokens = login(
+                &self.client,
+                &self.base_url,
+                &self.client_id,
+                &self.client_secret,
+                &self.username,
+                &self.password,
+            )
+            .await?;
+            Ok(self.to_secrets(tokens))
+        })
+    }
+
+    fn fetch_items(
+        &self,
+        limit: Option<usize>,
+    ) -> BoxFuture<'_, ProviderResult<Vec<Box<dyn Insertable>>>> {
+        Box::pin(async move {
+            let items = get(&self.client, &self.base_url, &self.access_token, limit).await?;
+            Ok(items
+                .into_iter()
+                .map(|item| Box::new(item) as Box<dyn Insertable>)
+                .collect())
+        })
+    }
+
+    fn add_item<'a>(
+        &'a self,
+        uri: &'a str,
+        tags: Vec<&'a str>,
+    ) -> BoxFuture<'a, ProviderResult<Option<i64>>> {
+        Box::pin(async move {
+            let id = add(&self.client, &self.base_url, &self.access_token, uri, &tags).await?;
+            Ok(Some(id))
+        })
+    }
+
+    fn mark_as_favorite(&self, item_id: i64, mark: bool) -> BoxFuture<'_, ProviderResult<()>> {
+        let request = UpdateEntryRequest {
+            starred: Some(mark.into()),
+            archive: None,
+        };
+        Box::pin(self.update(item_id, request))
+    }
+
+    fn mark_as_archived(&self, item_id: i64, mark: bool) -> BoxFuture<'_, ProviderResult<()>> {
+        let request = UpdateEntryRequest {
+            starred: None,
+            archive: Some(mark.into()),
+        };
+        Box::pin(self.update(item_id, request))
+    }
+}
+
+fn parse_date(date: &str) -> Option<i64> {
+    DateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S%z")
+        .ok()
+        .map(|date| date.timestamp())
+}
+
+impl Insertable for Entry {
+    fn to_research_item(&self) -> ResearchItem {
+        let title = self
+            .title
+            .clone()
+            .filter(|title| !title.is_empty())
+            .unwrap_or_else(|| "Untitled".to_string());
+
+        // Wallabag has no excerpt, use the start of the article text instead
+        let excerpt = self.content.as_deref().map_or_else(String::new, |content| {
+            let text = Html::parse_fragment(content)
+                .root_element()
+                .text()
+                .collect::<Vec<_>>()
+                .join(" ");
+            text.split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ")
+                .chars()
+                .take(EXCERPT_LENGTH)
+                .collect()
+        });
+
+        let archived = self.is_archived != 0;
+        ResearchItem {
+            id: None,
+            remote_id: Some(self.id),
+            account: None,
+            uri: self.url.clone(),
+            title,
+            excerpt,
+            time_added: self
+                .created_at
+                .as_deref()
+                .and_then(parse_date)
+                .unwrap_or_else(|| Utc::now().timestamp()),
+            favorite: self.is_starred != 0,
+            lang: self.language.clone(),
+            notes: None,
+            status: if archived {
+                ItemStatus::Archived
+            } else {
+                ItemStatus::Normal
+            },
+            time_read: self
+                .archived_at
+                .as_deref()
+                .and_then(parse_date)
+                .filter(|_| archived),
+            details: ItemDetails::default(),
+        }
+    }
+
+    fn to_tags(&self) -> Vec<Tags> {
+        self.tags
+            .iter()
+            .map(|tag| Tags {
+                tag_name: tag.label.clone(),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Path, Query, State};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, patch, post};
+    use axum::{Form, Json, Router};
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    use super::ProviderWallabag;
+    use crate::db::{Secrets, DB};
+    use crate::provider::pocket::api::ItemStatus;
+    use crate::provider::{spawn_mock, OnlineProvider};
+
+    /// Requests the mock received, the grant type of token requests and the body of the others
+    type Received = Arc<Mutex<Vec<(String, Value)>>>;
+
+    fn record(received: &Received, request: impl Into<String>, body: Value) {
+        received.lock().unwrap().push((request.into(), body));
+    }
+
+    /// Tokens are numbered, `refresh-1` is exchanged for `access-2` and `refresh-2`
+    async fn token(
+        State(received): State<Received>,
+        Form(form): Form<HashMap<String, String>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        assert_eq!(form["client_id"], "client");
+        assert_eq!(form["client_secret"], "secret");
+        let number = match form["grant_type"].as_str() {
+            "password" if form["username"] == "me" && form["password"] == "hunter2" => 1,
+            "refresh_token" => {
+                form["refresh_token"]
+                    .strip_prefix("refresh-")
+                    .and_then(|number| number.parse::<u32>().ok())
+                    .ok_or(StatusCode::BAD_REQUEST)?
+                    + 1
+            }
+            _ => return Err(StatusCode::BAD_REQUEST),
+        };
+        record(&received, &form["grant_type"], json!(form));
+        Ok(Json(json!({
+            "access_token": format!("access-{number}"),
+            "refresh_token": format!("refresh-{number}"),
+            "expires_in": 3600,
+            "token_type": "bearer",
+        })))
+    }
+
+    fn entry(id: i64) -> Value {
+        json!({
+            "id": id,
+            "url": format!("https://example.com/{id}"),
+            "title": format!("Entry {id}"),
+            "content": "<p>First   paragraph</p><p>and the <b>second</b></p>",
+            "is_archived": id % 2,
+            "is_starred": 1,
+            "tags": [{ "id": 1, "label": "rust" }],
+            "created_at": "2024-01-31T10:00:00+0100",
+            "archived_at": "2024-02-01T10:00:00+0100",
+        })
+    }
+
+    /// Two pages, the second with a single entry
+    async fn entries(
+        headers: HeaderMap,
+        Query(query): Query<HashMap<String, String>>,
+    ) -> Result<Json<Value>, StatusCode> {
+        if headers
+            .get("authorization")
+            .is_none_or(|value| value != "Bearer access-1")
+        {
+            return Err(StatusCode::UNAUTHORIZED);
+        }
+        let items = match query["page"].as_str() {
+            "1" => vec![entry(1), entry(2)],
+            _ => vec![entry(3)],
+        };
+        Ok(Json(
+            json!({ "page": 1, "pages": 2, "_embedded": { "items": items } }),
+        ))
+    }
+
+    async fn mock_api(received: Received) -> String {
+        let app = Router::new()
+            .route("/oauth/v2/token", post(token))
+            .route(
+                "/api/entries.json",
+                get(entries).post(
+                    |State(received): State<Received>, Json(body): Json<Value>| async move {
+                        let url = body["url"].clone();
+                        record(&received, "POST", body);
+                        Json(json!({ "id": 7, "url": url }))
+                    },
+                ),
+            )
+            .route(
+                "/api/entries/{file}",
+                patch(
+                    |State(received): State<Received>,
+                     Path(file): Path<String>,
+                     Json(body): Json<Value>| async move {
+                        record(&received, format!("PATCH {file}"), body);
+                        Json(entry(7))
+                    },
+                ),
+            )
+            .with_state(received);
+        spawn_mock(app).await
+    }
+
+    fn provider(base_url: String) -> ProviderWallabag {
+        ProviderWallabag {
+            base_url,
+            client_id: "client".to_string(),
+            client_secret: "secret".to_string(),
+            access_token: "access-1".to_string(),
+            refresh_token: "refresh-1".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn authenticate_uses_the_password_grant() {
+        let received = Received::default();
+        let wallabag = ProviderWallabag {
+            username: "me".to_string(),
+            password: "hunter2".to_string(),
+            ..provider(mock_api(received.clone()).await)
+        };
+        let secrets = wallabag.authenticate().await.unwrap();
+        assert_eq!(secrets.get("access_token"), Some("access-1"));
+        assert_eq!(secrets.get("refresh_token"), Some("refresh-1"));
+        let expires_at: i64 = secrets.get("expires_at").unwrap().parse().unwrap();
+        assert!(expires_at > Utc::now().timestamp());
+        assert_eq!(received.lock().unwrap()[0].0, "password");
+
+        let wallabag = ProviderWallabag {
+            password: "wrong".to_string(),
+            ..wallabag
+        };
+        let error = wallabag.authenticate().await.unwrap_err();
+        assert!(error.to_string().contains("400"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn connect_refreshes_expired_tokens_only() {
+        let received = Received::default();
+        let base_url = mock_api(received.clone()).await;
+        let db = DB::in_memory().await;
+        let expired = Utc::now().timestamp() - 10;
+        db.set_secret(
+            Secrets::new("wallabag", "work")
+                .with("url", &base_url)
+                .with("client_id", "client")
+                .with("client_secret", "secret")
+                .with("access_token", "access-1")
+                .with("refresh_token", "refresh-1")
+                .with("expires_at", expired.to_string()),
+        )
+        .await
+        .unwrap();
+
+        let wallabag = ProviderWallabag::connect(&db, "work").await.unwrap();
+        assert_eq!(wallabag.access_token, "access-2");
+        let secrets = db.get_secrets("wallabag", "work").await.unwrap();
+        assert_eq!(secrets.get("access_token"), Some("access-2"));
+        assert_eq!(secrets.get("refresh_token"), Some("refresh-2"));
+
+        let wallabag = ProviderWallabag::connect(&db, "work").await.unwrap();
+        assert_eq!(wallabag.access_token, "access-2");
+        let grants: Vec<String> = received
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|r| r.0.clone())
+            .collect();
+        assert_eq!(grants, ["refresh_token"]);
+
+        let error = ProviderWallabag::connect(&db, "home").await.err().unwrap();
+        assert!(
+            error.to_string().contains("wallabag --account home auth"),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn fetch_items_pages_through_entries() {
+        let wallabag = provider(mock_api(Received::default()).await);
+        let items = wallabag.fetch_items(None).await.unwrap();
+        let items: Vec<_> = items.iter().map(|item| item.to_research_item()).collect();
+        let remote_ids: Vec<_> = items.iter().map(|item| item.remote_id).collect();
+        assert_eq!(remote_ids, [Some(1), Some(2), Some(3)]);
+        assert!(items.iter().all(|item| item.id.is_none() && item.favorite));
+
+        assert_eq!(items[0].title, "Entry 1");
+        assert_eq!(items[0].excerpt, "First paragraph and the second");
+        assert_eq!(items[0].time_added, 1706691600);
+        assert_eq!(items[0].status, ItemStatus::Archived);
+        assert_eq!(items[0].time_read, Some(1706778000));
+        assert_eq!(items[1].status, ItemStatus::Normal);
+        assert_eq!(items[1].time_read, None);
+
+        let items = wallabag.fetch_items(Some(1)).await.unwrap();
+        assert_eq!(items.len(), 1);
+
+        let expired = ProviderWallabag {
+            access_token: "access-0".to_string(),
+            ..provider(wallabag.base_url.clone())
+        };
+        let error = expired.fetch_items(None).await.err().unwrap();
+        assert!(error.to_string().contains("401"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn add_and_update_entries() {
+        let received = Received::default();
+        let wallabag = provider(mock_api(received.clone()).await);
+
+        let id = wallabag
+            .add_item("https://example.com/new", vec!["rust", "db"])
+            .await
+            .unwrap();
+        assert_eq!(id, Some(7));
+        wallabag.mark_as_favorite(7, true).await.unwrap();
+        wallabag.mark_as_archived(7, false).await.unwrap();
+
+        let received = received.lock().unwrap();
+        assert_eq!(
+            received[0],
+            (
+                "POST".to_string(),
+                json!({ "url": "https://example.com/new", "tags": "rust,db" })
+            )
+        );
+        assert_eq!(
+            received[1],
+            ("PATCH 7.json".to_string(), json!({ "starred": 1 }))
+        );
+        assert_eq!(
+            received[2],
+            ("PATCH 7.json".to_string(), json!({ "archive": 0 }))
+        );
+    }
+}
@@ -0,0 +1,536 @@
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Json, Router,
+};
+use ring::hmac;
+use ring::rand::SystemRandom;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::db::{ResearchItem, DB};
+use crate::handler::{self, ItemError};
+
+/// Shorter tokens are rejected, anything on the machine can try guessing them
+const MIN_TOKEN_LENGTH: usize = 16;
+
+struct AppState {
+    db: DB,
+    /// Random key the token is signed with, so checking a token takes the same time whatever
+    /// it has in common with the real one
+    token_key: hmac::Key,
+    token_tag: hmac::Tag,
+}
+
+impl AppState {
+    fn new(db: DB, token: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        if token.trim().len() < MIN_TOKEN_LENGTH {
+            return Err(format!(
+                "The token must be at least {MIN_TOKEN_LENGTH} characters, generate one with `openssl rand -hex 16`"
+            )
+            .into());
+        }
+        let token_key = hmac::Key::generate(hmac::HMAC_SHA256, &SystemRandom::new())
+            .map_err(|_| "Failed to generate a key")?;
+        let token_tag = hmac::sign(&token_key, token.as_bytes());
+        Ok(Self {
+            db,
+            token_key,
+            token_tag,
+        })
+    }
+
+    fn is_valid_token(&self, token: &str) -> bool {
+        hmac::verify(&self.token_key, token.as_bytes(), self.token_tag.as_ref()).is_ok()
+    }
+}
+
+#[derive(Deserialize)]
+struct SaveRequest {
+    url: String,
+    provider: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct SaveResponse {
+    id: Option<i64>,
+    url: String,
+}
+
+#[derive(Deserialize)]
+struct FavoriteRequest {
+    #[serde(default = "default_mark")]
+    mark: bool,
+}
+
+fn default_mark() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct NotesRequest {
+    notes: String,
+}
+
+#[derive(Deserialize)]
+struct ListQuery {
+    /// Comma separated, items must have all of them
+    tags: Option<String>,
+}
+
+#[derive(Serialize)]
+struct ItemResponse {
+    tags: Vec<String>,
+    #[serde(flatten)]
+    item: ResearchItem,
+}
+
+struct ApiError(StatusCode, String);
+
+impl ApiError {
+    /// Only the server log gets the details, they can tell about the database or the secrets
+    fn internal(e: impl std::fmt::Display) -> Self {
+        eprintln!("Request failed: {e}");
+        ApiError(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "Internal server error".into(),
+        )
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(e: sqlx::Error) -> Self {
+        ApiError::internal(e)
+    }
+}
+
+impl From<ItemError> for ApiError {
+    fn from(e: ItemError) -> Self {
+        match e {
+            ItemError::InvalidUrl(_) => ApiError(StatusCode::BAD_REQUEST, e.to_string()),
+            ItemError::UnknownProvider(_) => ApiError(StatusCode::NOT_FOUND, e.to_string()),
+            ItemError::Fetch { ref url, .. } => {
+                eprintln!("Request failed: {e}");
+                ApiError(StatusCode::BAD_GATEWAY, format!("Failed to fetch {url}"))
+            }
+            ItemError::Provider { ref name, .. } => {
+                eprintln!("Request failed: {e}");
+                ApiError(StatusCode::BAD_GATEWAY, format!("{name} request failed"))
+            }
+            ItemError::Sqlx(e) => ApiError::internal(e),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(serde_json::json!({ "error": self.1 }));
+        (self.0, body).into_response()
+    }
+}
+
+/// Serves the JSON API on `host:port` until the process is stopped.
+/// Every request needs an `Authorization: Bearer <token>` header, and web pages can only call
+/// it from the allowed origins.
+pub async fn serve(
+    db: DB,
+    host: &str,
+    port: u16,
+    token: &str,
+    allowed_origins: &[String],
+) -> Result<(), Box<dyn std::error::Error>> {
+    let app = router(AppState::new(db, token)?, allowed_origins)?;
+    let listener = tokio::net::TcpListener::bind((host, port)).await?;
+    eprintln!("Listening on http://{}", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
+
+fn router(
+    state: AppState,
+    allowed_origins: &[String],
+) -> Result<Router, Box<dyn std::error::Error>> {
+    let origins = allowed_origins
+        .iter()
+        .map(|origin| {
+            let invalid = || {
+                format!("Invalid origin \"{origin}\", expected one like https://example.com")
+            };
+            let url = reqwest::Url::parse(origin).map_err(|_| invalid())?;
+            if !matches!(url.scheme(), "http" | "https") || url.host().is_none() {
+                return Err(invalid());
+            }
+            HeaderValue::from_str(&url.origin().ascii_serialization()).map_err(|_| invalid())
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    // Pages of other origins get no CORS headers, so browsers keep them from reading responses
+    let cors = CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST, Method::PUT])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE])
+        .allow_private_network(true);
+
+    let state = Arc::new(state);
+    Ok(Router::new()
+        .route("/items", get(list_items).post(save_item))
+        .route("/items/{id}/favorite", post(favorite_item))
+        .route("/items/{id}/notes", put(update_notes))
+        .layer(middleware::from_fn_with_state(state.clone(), authorize))
+        .layer(cors)
+        .with_state(state))
+}
+
+async fn authorize(
+    State(state): State<Arc<AppState>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    match token {
+        Some(token) if state.is_valid_token(token) => Ok(next.run(request).await),
+        _ => Err(ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid token".into(),
+        )),
+    }
+}
+
+async fn save_item(
+    State(state): State<Arc<AppState>>,
+    Json(request): Json<SaveRequest>,
+) -> Result<(StatusCode, Json<SaveResponse>), ApiError> {
+    let id = handler::save_item(
+        &state.db,
+        &request.url,
+        request.provider.as_deref(),
+        request.tags.iter().map(|tag| tag.as_str()).collect(),
+    )
+    .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(SaveResponse {
+            id,
+            url: request.url,
+        }),
+    ))
+}
+
+async fn favorite_item(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(request): Json<FavoriteRequest>,
+) -> Result<StatusCode, ApiError> {
+    ensure_item_exists(&state.db, id).await?;
+    handler::set_favorite(&state.db, id, request.mark).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn update_notes(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<i64>,
+    Json(request): Json<NotesRequest>,
+) -> Result<StatusCode, ApiError> {
+    let uri = ensure_item_exists(&state.db, id).await?;
+    state.db.update_notes(&uri, &request.notes).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_items(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<ListQuery>,
+) -> Result<Json<Vec<ItemResponse>>, ApiError> {
+    let tags = query
+        .tags
+        .map(|tags| {
+            tags.split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    let items = if tags.is_empty() {
+        state.db.get_all_items(None, None).await?
+    } else {
+        state.db.get_all_items_by_tags(&tags, None, None).await?
+    };
+
+    let mut response = Vec::with_capacity(items.len());
+    for item in items {
+        let tags = match item.id {
+            Some(id) => state.db.get_item_tags(id).await?,
+            None => Vec::new(),
+        };
+        response.push(ItemResponse {
+            tags: tags.into_iter().map(|t| t.tag_name).collect(),
+            item,
+        });
+    }
+    Ok(Json(response))
+}
+
+/// Returns the uri of the item
+async fn ensure_item_exists(db: &DB, id: i64) -> Result<String, ApiError> {
+    db.get_item_uri(id)
+        .await?
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Item {id} not found")))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        http::StatusCode,
+        response::{Html, IntoResponse},
+        routing::get,
+        Router,
+    };
+    use reqwest::header::{ACCESS_CONTROL_ALLOW_ORIGIN, AUTHORIZATION, ORIGIN};
+    use serde_json::{json, Value};
+
+    use super::{router, ApiError, AppState};
+    use crate::db::DB;
+    use crate::provider::spawn_mock;
+
+    const TOKEN: &str = "0123456789abcdef";
+    const ALLOWED_ORIGIN: &str = "https://allowed.example";
+
+    async fn spawn_api() -> String {
+        let state = AppState::new(DB::in_memory().await, TOKEN).unwrap();
+        spawn_mock(router(state, &[ALLOWED_ORIGIN.to_string()]).unwrap()).await
+    }
+
+    /// Page saved through the API, its metadata is fetched when saving
+    async fn spawn_page() -> String {
+        let page = Router::new().route(
+            "/article",
+            get(|| async { Html("<html><head><title>An article</title></head></html>") }),
+        );
+        format!("{}/article", spawn_mock(page).await)
+    }
+
+    #[test]
+    fn short_tokens_are_rejected() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        for token in ["", "   ", "short", "                 "] {
+            let db = runtime.block_on(DB::in_memory());
+            assert!(AppState::new(db, token).is_err(), "{token:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_origins_are_rejected() {
+        for origin in ["*", "example.com", "https://exa mple.com"] {
+            let state = AppState::new(DB::in_memory().await, TOKEN).unwrap();
+            assert!(router(state, &[origin.to_string()]).is_err(), "{origin}");
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_need_the_token() {
+        let api = spawn_api().await;
+        let client = reqwest::Client::new();
+        for header in [
+            None,
+            Some("Bearer ".to_string()),
+            Some(format!("Bearer {TOKEN}x")),
+            Some(format!("Bearer {}", &TOKEN[1..])),
+            Some(TOKEN.to_string()),
+        ] {
+            let mut request = client.get(format!("{api}/items"));
+            if let Some(header) = &header {
+                request = request.header(AUTHORIZATION, header);
+            }
+            let response = request.send().await.unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED, "{header:?}");
+        }
+        let response = client
+            .get(format!("{api}/items"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn only_allowed_origins_get_cors_headers() {
+        let api = spawn_api().await;
+        let client = reqwest::Client::new();
+        let preflight = |origin: &'static str| {
+            client
+                .request(reqwest::Method::OPTIONS, format!("{api}/items"))
+                .header(ORIGIN, origin)
+                .header("Access-Control-Request-Method", "POST")
+                .header("Access-Control-Request-Headers", "authorization")
+                .send()
+        };
+        let response = preflight(ALLOWED_ORIGIN).await.unwrap();
+        assert_eq!(
+            response.headers().get(ACCESS_CONTROL_ALLOW_ORIGIN).unwrap(),
+            ALLOWED_ORIGIN
+        );
+        let response = preflight("https://evil.example").await.unwrap();
+        assert!(response
+            .headers()
+            .get(ACCESS_CONTROL_ALLOW_ORIGIN)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn save_list_favorite_and_annotate_items() {
+        let api = spawn_api().await;
+        let page = spawn_page().await;
+        let client = reqwest::Client::new();
+
+        let response = client
+            .post(format!("{api}/items"))
+            .bearer_auth(TOKEN)
+            .json(&json!({ "url": page, "tags": ["rust", "it's"] }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let saved: Value = response.json().await.unwrap();
+        assert_eq!(saved["url"], page);
+        let id = saved["id"].as_i64().unwrap();
+
+        let response = client
+            .post(format!("{api}/items/{id}/favorite"))
+            .bearer_auth(TOKEN)
+            .json(&json!({}))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = client
+            .put(format!("{api}/items/{id}/notes"))
+            .bearer_auth(TOKEN)
+            .json(&json!({ "notes": "worth a reread" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+
+        let items: Value = client
+            .get(format!("{api}/items?tags=it's"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        let items = items.as_array().unwrap();
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0]["id"], id);
+        assert_eq!(items[0]["title"], "An article");
+        assert_eq!(items[0]["favorite"], true);
+        assert_eq!(items[0]["notes"], "worth a reread");
+        assert_eq!(items[0]["tags"], json!(["it's", "rust"]));
+
+        let items: Value = client
+            .get(format!("{api}/items?tags=go"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(items, json!([]));
+    }
+
+    #[tokio::test]
+    async fn missing_items_are_not_found() {
+        let api = spawn_api().await;
+        let client = reqwest::Client::new();
+        let response = client
+            .put(format!("{api}/items/42/notes"))
+            .bearer_auth(TOKEN)
+            .json(&json!({ "notes": "x" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let response = client
+            .post(format!("{api}/items/42/favorite"))
+            .bearer_auth(TOKEN)
+            .json(&json!({ "mark": false }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn save_errors_have_their_own_status() {
+        let api = spawn_api().await;
+        let page = spawn_page().await;
+        // Nothing listens on the port once the listener is dropped
+        let unreachable = {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            format!("http://{}/article", listener.local_addr().unwrap())
+        };
+        let client = reqwest::Client::new();
+        for (body, status) in [
+            (json!({ "url": "" }), StatusCode::BAD_REQUEST),
+            (
+                json!({ "url": "file:///etc/passwd" }),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                json!({ "url": page, "provider": "delicious" }),
+                StatusCode::NOT_FOUND,
+            ),
+            (json!({ "url": unreachable }), StatusCode::BAD_GATEWAY),
+            // Without saved secrets pocket can't be connected to
+            (
+                json!({ "url": page, "provider": "pocket" }),
+                StatusCode::BAD_GATEWAY,
+            ),
+        ] {
+            let response = client
+                .post(format!("{api}/items"))
+                .bearer_auth(TOKEN)
+                .json(&body)
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(response.status(), status, "{body}");
+        }
+        let items: Value = client
+            .get(format!("{api}/items"))
+            .bearer_auth(TOKEN)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(items, json!([]));
+    }
+
+    #[tokio::test]
+    async fn internal_errors_hide_their_details() {
+        let response =
+            ApiError::from(sqlx::Error::Protocol("secret detail".into())).into_response();
+        assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&body).unwrap(),
+            json!({ "error": "Internal server error" })
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use super::{render_markdown, TITLE};
+use crate::db::ResearchItem;
+use crate::util::escape_html;
+use chrono::{TimeZone, Utc};
+use std::fmt::Write;
+
+pub struct FeedOptions {
+    /// Public url of the site, feed readers can't resolve relative links
+    pub base_url: String,
+    /// Number of newest items in each feed
+    pub limit: usize,
+}
+
+impl FeedOptions {
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Stable id of an item across feeds
+    fn item_id(&self, id: Option<i64>, uri: &str) -> String {
+        match id {
+            Some(id) => self.url(&format!("items/{id}.html")),
+            None => uri.to_string(),
+        }
+    }
+}
+
+/// Items are expected newest first along with their tag names
+pub fn atom(
+    title: &str,
+    path: &str,
+    items: &[(Vec<&str>, &ResearchItem)],
+    options: &FeedOptions,
+) -> Result<String, std::fmt::Error> {
+    let items = &items[..items.len().min(options.limit)];
+    let updated = items
+        .iter()
+        .map(|(_, item)| item.time_added)
+        .max()
+        .unwrap_or_else(|| Utc::now().timestamp());
+
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(xml, r#"<feed xmlns="http://www.w3.org/2005/Atom">"#)?;
+    writeln!(xml, "  <title>{}</title>", escape_html(title))?;
+    writeln!(xml, r#"  <link href="{}"/>"#, escape_html(&options.url("")))?;
+    writeln!(
+        xml,
+        r#"  <link rel="self" href="{}"/>"#,
+        escape_html(&options.url(path))
+    )?;
+    writeln!(xml, "  <id>{}</id>", escape_html(&options.url(path)))?;
+    writeln!(xml, "  <updated>{}</updated>", rfc3339(updated))?;
+    writeln!(xml, "  <author><name>{}</name></author>", TITLE)?;
+    for (tags, item) in items {
+        writeln!(xml, "  <entry>")?;
+        writeln!(xml, "    <title>{}</title>", escape_html(&item.title))?;
+        writeln!(xml, r#"    <link href="{}"/>"#, escape_html(&item.uri))?;
+        if let Some(id) = item.id {
+            writeln!(
+                xml,
+                r#"    <link rel="related" href="{}"/>"#,
+                escape_html(&options.url(&format!("items/{id}.html")))
+            )?;
+        }
+        writeln!(
+            xml,
+            "    <id>{}</id>",
+            escape_html(&options.item_id(item.id, &item.uri))
+        )?;
+        writeln!(
+            xml,
+            "    <published>{}</published>",
+            rfc3339(item.time_added)
+        )?;
+        writeln!(xml, "    <updated>{}</updated>", rfc3339(item.time_added))?;
+        if !item.excerpt.is_empty() {
+            writeln!(xml, "    <summary>{}</summary>", escape_html(&item.excerpt))?;
+        }
+        if let Some(notes) = item.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+            writeln!(
+                xml,
+                r#"    <content type="html">{}</content>"#,
+                escape_html(&render_markdown(notes))
+            )?;
+        }
+        for tag in tags {
+            writeln!(xml, r#"    <category term="{}"/>"#, escape_html(tag))?;
+        }
+        writeln!(xml, "  </entry>")?;
+    }
+    writeln!(xml, "</feed>")?;
+    Ok(xml)
+}
+
+/// RSS 2.0 feed of the same items, notes are appended to the excerpt
+pub fn rss(
+    title: &str,
+    path: &str,
+    items: &[(Vec<&str>, &ResearchItem)],
+    options: &FeedOptions,
+) -> Result<String, std::fmt::Error> {
+    let items = &items[..items.len().min(options.limit)];
+    let last_build = items
+        .iter()
+        .map(|(_, item)| item.time_added)
+        .max()
+        .unwrap_or_else(|| Utc::now().timestamp());
+
+    let mut xml = String::new();
+    writeln!(xml, r#"<?xml version="1.0" encoding="utf-8"?>"#)?;
+    writeln!(
+        xml,
+        r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">"#
+    )?;
+    writeln!(xml, "  <channel>")?;
+    writeln!(xml, "    <title>{}</title>", escape_html(title))?;
+    writeln!(xml, "    <link>{}</link>", escape_html(&options.url("")))?;
+    writeln!(
+        xml,
+        r#"    <atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+        escape_html(&options.url(path))
+    )?;
+    writeln!(xml, "    <description>{}</description>", escape_html(title))?;
+    writeln!(
+        xml,
+        "    <lastBuildDate>{}</lastBuildDate>",
+        rfc2822(last_build)
+    )?;
+    for (tags, item) in items {
+        writeln!(xml, "    <item>")?;
+        writeln!(xml, "      <title>{}</title>", escape_html(&item.title))?;
+        writeln!(xml, "      <link>{}</link>", escape_html(&item.uri))?;
+        writeln!(
+            xml,
+            r#"      <guid isPermaLink="false">{}</guid>"#,
+            escape_html(&options.item_id(item.id, &item.uri))
+        )?;
+        writeln!(xml, "      <pubDate>{}</pubDate>", rfc2822(item.time_added))?;
+        let mut description = String::new();
+        if !item.excerpt.is_empty() {
+            description = format!("<p>{}</p>", escape_html(&item.excerpt));
+        }
+        if let Some(notes) = item.notes.as_deref().filter(|n| !n.trim().is_empty()) {
+            description.push_str(&render_markdown(notes));
+        }
+        if !description.is_empty() {
+            writeln!(
+                xml,
+                "      <description>{}</description>",
+                escape_html(&description)
+            )?;
+        }
+        for tag in tags {
+            writeln!(xml, "      <category>{}</category>", escape_html(tag))?;
+        }
+        writeln!(xml, "    </item>")?;
+    }
+    writeln!(xml, "  </channel>")?;
+    writeln!(xml, "</rss>")?;
+    Ok(xml)
+}
+
+fn rfc3339(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0).unwrap().to_rfc3339()
+}
+
+fn rfc2822(timestamp: i64) -> String {
+    Utc.timestamp_opt(timestamp, 0).unwrap().to_rfc2822()
+}
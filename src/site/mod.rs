@@ -1,12 +1,33 @@
-use crate::db::{ResearchItem, Tags};
+use crate::db::{Collection, ResearchItem, Tags};
+use crate::util::{slugify, tag_ancestors, tag_parent};
 use chrono_tz::Tz;
+use pulldown_cmark::{html, Event, Parser, Tag, TagEnd};
 use sailfish::TemplateOnce;
 use serde::Serialize;
+use std::collections::{HashMap, HashSet};
 use std::sync::RwLock;
+use url::Url;
+
+pub use feed::FeedOptions;
+
+mod feed;
 
 pub struct Site {
     pub index_html: String,
     pub search_html: String,
+    /// Item id and its page, written to `items/<id>.html`
+    pub item_pages: Vec<(i64, String)>,
+    pub tags_html: String,
+    /// Tag slug and its page, written to `tags/<slug>.html`
+    pub tag_pages: Vec<(String, String)>,
+    /// Written to `feed.xml`, feeds are only built with a base url
+    pub atom_feed: Option<String>,
+    /// Written to `rss.xml`
+    pub rss_feed: Option<String>,
+    /// Tag slug and its atom feed, written to `tags/<slug>.xml`
+    pub tag_feeds: Vec<(String, String)>,
+    /// Collection slug and its page, written to `collections/<slug>.html`
+    pub collection_pages: Vec<(String, String)>,
 }
 
 #[derive(TemplateOnce, Serialize)]
@@ -17,6 +38,11 @@ struct IndexTemplate<'a> {
     assets_dir: &'a str,
     tags: Vec<&'a str>,
     item_tags: &'a [(Vec<Tags>, ResearchItem)],
+    tag_slugs: &'a TagSlugs,
+    /// Collection name, slug and number of items
+    collections: Vec<(&'a str, &'a str, usize)>,
+    /// Whether `feed.xml` and `rss.xml` are generated
+    feeds: bool,
 }
 
 #[derive(TemplateOnce, Serialize)]
@@ -27,6 +53,134 @@ struct SearchTemplate<'a> {
     assets_dir: &'a str,
     item_tags: Vec<ItemTag<'a>>,
     tags: Vec<&'a str>,
+    tag_slugs: &'a TagSlugs,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "item.stpl")]
+#[template(rm_whitespace = true)]
+struct ItemTemplate<'a> {
+    title: &'a str,
+    assets_dir: &'a str,
+    item: &'a ResearchItem,
+    tags: Vec<&'a str>,
+    notes_html: Option<String>,
+    tag_slugs: &'a TagSlugs,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "tag.stpl")]
+#[template(rm_whitespace = true)]
+struct TagTemplate<'a> {
+    title: &'a str,
+    assets_dir: &'a str,
+    items: &'a [ResearchItem],
+    /// Last segment and slug of the parent tags, top level first
+    ancestors: Vec<(&'a str, &'a str)>,
+    children: Vec<TagNode<'a>>,
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "collection.stpl")]
+#[template(rm_whitespace = true)]
+struct CollectionTemplate<'a> {
+    title: &'a str,
+    description: Option<&'a str>,
+    assets_dir: &'a str,
+    items: &'a [ResearchItem],
+}
+
+#[derive(TemplateOnce)]
+#[template(path = "tags.stpl")]
+#[template(rm_whitespace = true)]
+struct TagsTemplate<'a> {
+    title: &'a str,
+    assets_dir: &'a str,
+    /// Every tag in tree order, children right after their parent
+    tags: Vec<TagNode<'a>>,
+}
+
+#[derive(Clone, Copy)]
+struct TagNode<'a> {
+    name: &'a str,
+    /// Last segment of the path, the rest is shown by the tree
+    label: &'a str,
+    slug: &'a str,
+    /// Items with this tag or one of its descendants
+    count: usize,
+    depth: usize,
+}
+
+/// Tags ordered depth first, siblings with the most items first
+fn tag_tree<'a>(tag_counts: &[(&'a str, &'a str, usize)]) -> Vec<TagNode<'a>> {
+    let names = tag_counts
+        .iter()
+        .map(|(name, ..)| *name)
+        .collect::<Vec<_>>();
+    let mut children = HashMap::<Option<&str>, Vec<_>>::new();
+    for tag in tag_counts {
+        // Tags whose parent has no page are shown at the top level
+        let parent = tag_parent(tag.0).filter(|parent| names.contains(parent));
+        children.entry(parent).or_default().push(*tag);
+    }
+    for siblings in children.values_mut() {
+        siblings.sort_by(|a, b| b.2.cmp(&a.2).then(a.0.cmp(b.0)));
+    }
+
+    fn visit<'a>(
+        parent: Option<&'a str>,
+        depth: usize,
+        children: &HashMap<Option<&'a str>, Vec<(&'a str, &'a str, usize)>>,
+        tree: &mut Vec<TagNode<'a>>,
+    ) {
+        for &(name, slug, count) in children.get(&parent).into_iter().flatten() {
+            tree.push(TagNode {
+                name,
+                label: tag_parent(name).map_or(name, |parent| &name[parent.len() + 1..]),
+                slug,
+                count,
+                depth,
+            });
+            visit(Some(name), depth + 1, children, tree);
+        }
+    }
+
+    let mut tree = Vec::with_capacity(tag_counts.len());
+    visit(None, 0, &children, &mut tree);
+    tree
+}
+
+/// File names of the tag and collection pages, names that slugify to the same slug get a
+/// numeric suffix
+#[derive(Serialize, Default)]
+#[serde(transparent)]
+struct TagSlugs(HashMap<String, String>);
+
+impl TagSlugs {
+    fn new<'a>(tags: impl IntoIterator<Item = &'a str>) -> Self {
+        let mut slugs = HashMap::new();
+        // Suffixed slugs included, "rust 2" can't take the "rust-2" given to a second "Rust"
+        let mut taken = HashSet::new();
+        for tag in tags {
+            if slugs.contains_key(tag) {
+                continue;
+            }
+            let base = slugify(tag);
+            let mut slug = base.clone();
+            let mut count = 1;
+            while taken.contains(&slug) {
+                count += 1;
+                slug = format!("{base}-{count}");
+            }
+            taken.insert(slug.clone());
+            slugs.insert(tag.to_string(), slug);
+        }
+        Self(slugs)
+    }
+
+    fn get(&self, tag: &str) -> &str {
+        self.0.get(tag).map_or("untitled", String::as_str)
+    }
 }
 
 #[derive(Serialize)]
@@ -44,19 +198,46 @@ impl Site {
     pub fn build(
         tags: &[Tags],
         item_tags: &[(Vec<Tags>, ResearchItem)],
+        tag_items: &[(Tags, Vec<ResearchItem>)],
+        collection_items: &[(Collection, Vec<ResearchItem>)],
         assets_dir: &str,
         timezone: Option<Tz>,
+        feed_options: Option<&FeedOptions>,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         {
             let mut timezone_lock = TIMEZONE.write().unwrap();
             *timezone_lock = timezone;
         }
         let tags = tags.iter().map(|t| t.tag_name.as_str()).collect::<Vec<_>>();
+        let tag_slugs = TagSlugs::new(
+            tags.iter().copied().chain(
+                item_tags
+                    .iter()
+                    .flat_map(|(tags, _)| tags.iter().map(|t| t.tag_name.as_str())),
+            ),
+        );
+        let collection_slugs = TagSlugs::new(
+            collection_items
+                .iter()
+                .map(|(collection, _)| collection.name.as_str()),
+        );
         let ctx = IndexTemplate {
             title: TITLE,
             item_tags,
             assets_dir,
             tags: tags.clone(),
+            tag_slugs: &tag_slugs,
+            collections: collection_items
+                .iter()
+                .map(|(collection, items)| {
+                    (
+                        collection.name.as_str(),
+                        collection_slugs.get(&collection.name),
+                        items.len(),
+                    )
+                })
+                .collect(),
+            feeds: feed_options.is_some(),
         };
 
         let index_html = ctx.render_once()?;
@@ -69,16 +250,236 @@ impl Site {
             })
             .collect::<Vec<_>>();
 
+        // Item and tag pages live one directory down
+        let nested_assets_dir = format!("../{}", assets_dir.trim_start_matches("./"));
+        let mut item_pages = Vec::new();
+        for item_tag in &item_tags {
+            let Some(id) = item_tag.item.id else {
+                continue;
+            };
+            let ctx = ItemTemplate {
+                title: &item_tag.item.title,
+                assets_dir: &nested_assets_dir,
+                item: item_tag.item,
+                tags: item_tag.tags.clone(),
+                notes_html: item_tag
+                    .item
+                    .notes
+                    .as_deref()
+                    .filter(|notes| !notes.trim().is_empty())
+                    .map(render_markdown),
+                tag_slugs: &tag_slugs,
+            };
+            item_pages.push((id, ctx.render_once()?));
+        }
+
+        let feed_items = item_tags
+            .iter()
+            .map(|item_tag| (item_tag.tags.clone(), item_tag.item))
+            .collect::<Vec<_>>();
+        let (atom_feed, rss_feed) = match feed_options {
+            Some(feed_options) => (
+                Some(feed::atom(TITLE, "feed.xml", &feed_items, feed_options)?),
+                Some(feed::rss(TITLE, "rss.xml", &feed_items, feed_options)?),
+            ),
+            None => (None, None),
+        };
+
+        let item_tag_names = feed_items
+            .iter()
+            .filter_map(|(tags, item)| item.id.map(|id| (id, tags)))
+            .collect::<HashMap<_, _>>();
+        let mut tag_feeds = Vec::new();
+        if let Some(feed_options) = feed_options {
+            for (tag, items) in tag_items {
+                let slug = tag_slugs.get(&tag.tag_name);
+                let items = items
+                    .iter()
+                    .map(|item| {
+                        let tags = item
+                            .id
+                            .and_then(|id| item_tag_names.get(&id))
+                            .map_or_else(|| vec![tag.tag_name.as_str()], |tags| tags.to_vec());
+                        (tags, item)
+                    })
+                    .collect::<Vec<_>>();
+                let feed = feed::atom(
+                    &format!("{TITLE}: {}", tag.tag_name),
+                    &format!("tags/{slug}.xml"),
+                    &items,
+                    feed_options,
+                )?;
+                tag_feeds.push((slug.to_string(), feed));
+            }
+        }
+
         let ctx = SearchTemplate {
             item_tags,
             assets_dir,
             title: "Search",
             tags: tags.clone(),
+            tag_slugs: &tag_slugs,
         };
         let search_html = ctx.render_once()?;
+
+        let tag_counts = tag_items
+            .iter()
+            .map(|(tag, items)| {
+                (
+                    tag.tag_name.as_str(),
+                    tag_slugs.get(&tag.tag_name),
+                    items.len(),
+                )
+            })
+            .collect::<Vec<_>>();
+        let tree = tag_tree(&tag_counts);
+
+        let mut tag_pages = Vec::new();
+        for (tag, items) in tag_items {
+            let ancestors = tag_ancestors(&tag.tag_name)
+                .map(|ancestor| {
+                    let label =
+                        tag_parent(ancestor).map_or(ancestor, |p| &ancestor[p.len() + 1..]);
+                    (label, tag_slugs.get(ancestor))
+                })
+                .collect();
+            let children = tree
+                .iter()
+                .filter(|node| tag_parent(node.name) == Some(tag.tag_name.as_str()))
+                .copied()
+                .collect();
+            let ctx = TagTemplate {
+                title: &tag.tag_name,
+                assets_dir: &nested_assets_dir,
+                items,
+                ancestors,
+                children,
+            };
+            tag_pages.push((tag_slugs.get(&tag.tag_name).to_string(), ctx.render_once()?));
+        }
+
+        let ctx = TagsTemplate {
+            title: "Tags",
+            assets_dir,
+            tags: tree,
+        };
+        let tags_html = ctx.render_once()?;
+
+        let mut collection_pages = Vec::new();
+        for (collection, items) in collection_items {
+            let ctx = CollectionTemplate {
+                title: &collection.name,
+                description: collection.description.as_deref(),
+                assets_dir: &nested_assets_dir,
+                items,
+            };
+            collection_pages.push((
+                collection_slugs.get(&collection.name).to_string(),
+                ctx.render_once()?,
+            ));
+        }
+
         Ok(Self {
             index_html,
             search_html,
+            item_pages,
+            tags_html,
+            tag_pages,
+            atom_feed,
+            rss_feed,
+            tag_feeds,
+            collection_pages,
         })
     }
 }
+
+/// Renders markdown to HTML, raw HTML in the input is escaped rather than passed through.
+/// Links and images to anything but http(s) and mailto urls (javascript:, data:) are dropped,
+/// keeping their text.
+fn render_markdown(markdown: &str) -> String {
+    // Whether each open link or image was kept, to drop the matching end too
+    let mut kept = Vec::new();
+    let parser = Parser::new(markdown).filter_map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+        Event::Start(Tag::Link { ref dest_url, .. } | Tag::Image { ref dest_url, .. }) => {
+            let safe = is_safe_url(dest_url);
+            kept.push(safe);
+            safe.then_some(event)
+        }
+        Event::End(TagEnd::Link | TagEnd::Image) => kept.pop().unwrap_or(true).then_some(event),
+        event => Some(event),
+    });
+    let mut html_output = String::new();
+    html::push_html(&mut html_output, parser);
+    html_output
+}
+
+fn is_safe_url(url: &str) -> bool {
+    Url::parse(url.trim()).is_ok_and(|url| matches!(url.scheme(), "http" | "https" | "mailto"))
+}
+
+/// Item uris come from imports and the url handler, anything but a web link becomes `#`
+fn safe_href(url: &str) -> &str {
+    if is_safe_url(url) {
+        url
+    } else {
+        "#"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_markdown, ItemTemplate, TagSlugs};
+    use crate::db::{ItemDetails, ResearchItem};
+    use sailfish::TemplateOnce;
+    use std::collections::HashSet;
+
+    #[test]
+    fn tag_slugs_are_unique() {
+        let slugs = TagSlugs::new(["Rust", "rust", "rust 2", "rust-2", "Go"]);
+        let unique: HashSet<&str> = slugs.0.values().map(String::as_str).collect();
+        assert_eq!(unique.len(), 5, "{:?}", slugs.0);
+        assert_eq!(slugs.get("Rust"), "rust");
+        assert_eq!(slugs.get("rust"), "rust-2");
+    }
+
+    #[test]
+    fn markdown_drops_unsafe_links() {
+        let html = render_markdown(
+            "[x](javascript:alert(1)) ![img](data:image/png;base64,AA) <javascript:alert(2)>",
+        );
+        assert_eq!(html, "<p>x img javascript:alert(2)</p>\n");
+    }
+
+    #[test]
+    fn markdown_keeps_web_and_mail_links() {
+        let html = render_markdown("[a](https://example.com) [b](mailto:me@example.com)");
+        assert!(html.contains(r#"href="https://example.com""#), "{html}");
+        assert!(html.contains(r#"href="mailto:me@example.com""#), "{html}");
+    }
+
+    #[test]
+    fn item_page_drops_unsafe_uri_and_image() {
+        let item = ResearchItem {
+            id: Some(1),
+            details: ItemDetails {
+                image_url: Some(" JavaScript:alert(2)".to_string()),
+                ..Default::default()
+            },
+            ..ResearchItem::test("javascript:alert(1)", "Title")
+        };
+        let tag_slugs = TagSlugs::new([]);
+        let page = ItemTemplate {
+            title: &item.title,
+            assets_dir: "../assets",
+            item: &item,
+            tags: Vec::new(),
+            notes_html: None,
+            tag_slugs: &tag_slugs,
+        }
+        .render_once()
+        .unwrap();
+        assert!(page.contains(r##"<a href="#""##), "{page}");
+        assert!(!page.contains("<img"), "{page}");
+    }
+}
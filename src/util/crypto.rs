@@ -0,0 +1,410 @@
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, CHACHA20_POLY1305, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::{hkdf, pbkdf2};
+
+/// Passphrase the key is derived from, takes precedence over the keyfile for new databases
+pub const PASSPHRASE_ENV: &str = "RESEARCH_PASSPHRASE";
+/// Path of the keyfile, `~/.config/research/secrets.key` when unset
+pub const KEYFILE_ENV: &str = "RESEARCH_KEYFILE";
+
+/// Marks encrypted values, followed by the base64 of the nonce and the sealed value
+const PREFIX: &str = "enc1:";
+const SALT_LEN: usize = 16;
+/// OWASP's recommendation for PBKDF2-HMAC-SHA256
+const PBKDF2_ITERATIONS: NonZeroU32 = NonZeroU32::new(600_000).unwrap();
+const KEYFILE_LEN: usize = 32;
+
+/// What the key of a database is derived from, stored next to its salt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeySource {
+    Passphrase,
+    Keyfile,
+}
+
+impl KeySource {
+    /// The passphrase when it's set, the keyfile otherwise
+    pub fn from_env() -> Self {
+        if env::var_os(PASSPHRASE_ENV).is_some() {
+            KeySource::Passphrase
+        } else {
+            KeySource::Keyfile
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeySource::Passphrase => "passphrase",
+            KeySource::Keyfile => "keyfile",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "passphrase" => Some(KeySource::Passphrase),
+            "keyfile" => Some(KeySource::Keyfile),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CryptoError {
+    /// The secrets were encrypted with a passphrase and it isn't set
+    MissingPassphrase,
+    Keyfile(PathBuf, io::Error),
+    /// Neither the keyfile nor a home directory to put it in is set
+    NoKeyfilePath,
+    /// The passphrase or keyfile isn't the one the secrets were encrypted with
+    WrongKey(KeySource),
+    /// A value isn't something this module encrypted
+    Malformed,
+}
+
+impl fmt::Display for CryptoError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CryptoError::MissingPassphrase => write!(
+                f,
+                "the secrets are encrypted with a passphrase, set it in {PASSPHRASE_ENV}"
+            ),
+            CryptoError::Keyfile(path, e) => {
+                write!(f, "couldn't read the keyfile {}: {e}", path.display())
+            }
+            CryptoError::NoKeyfilePath => {
+                write!(f, "no home directory for the keyfile, set {KEYFILE_ENV}")
+            }
+            CryptoError::WrongKey(source) => write!(
+                f,
+                "the {} doesn't decrypt the secrets of this database",
+                source.as_str()
+            ),
+            CryptoError::Malformed => write!(f, "an encrypted secret is malformed"),
+        }
+    }
+}
+
+impl Error for CryptoError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            CryptoError::Keyfile(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Key encrypting the secrets stored in the database, it's never stored there itself
+pub struct SecretsKey {
+    key: LessSafeKey,
+    rng: SystemRandom,
+}
+
+impl SecretsKey {
+    /// Derives the key from the passphrase or the keyfile. A missing keyfile is created when
+    /// `create` is set, for databases without encrypted secrets yet.
+    pub fn derive(source: KeySource, salt: &[u8], create: bool) -> Result<Self, CryptoError> {
+        match source {
+            KeySource::Passphrase => {
+                let passphrase =
+                    env::var(PASSPHRASE_ENV).map_err(|_| CryptoError::MissingPassphrase)?;
+                Ok(Self::from_passphrase(&passphrase, salt))
+            }
+            KeySource::Keyfile => Ok(Self::from_keyfile(&read_keyfile(create)?, salt)),
+        }
+    }
+
+    fn from_passphrase(passphrase: &str, salt: &[u8]) -> Self {
+        let mut key = [0; 32];
+        pbkdf2::derive(
+            pbkdf2::PBKDF2_HMAC_SHA256,
+            PBKDF2_ITERATIONS,
+            salt,
+            passphrase.as_bytes(),
+            &mut key,
+        );
+        Self::new(&key)
+    }
+
+    fn from_keyfile(keyfile: &[u8], salt: &[u8]) -> Self {
+        // The keyfile is already random, stretching it like a passphrase adds nothing
+        let mut key = [0; 32];
+        hkdf::Salt::new(hkdf::HKDF_SHA256, salt)
+            .extract(keyfile)
+            .expand(&[b"research secrets"], &CHACHA20_POLY1305)
+            .and_then(|okm| okm.fill(&mut key))
+            .expect("the output fits the key length");
+        Self::new(&key)
+    }
+
+    fn new(key: &[u8; 32]) -> Self {
+        let key =
+            UnboundKey::new(&CHACHA20_POLY1305, key).expect("the key has the right length");
+        Self {
+            key: LessSafeKey::new(key),
+            rng: SystemRandom::new(),
+        }
+    }
+
+    /// Key that isn't derived from anything, for tests
+    #[cfg(test)]
+    pub fn random() -> Self {
+        let mut key = [0; 32];
+        SystemRandom::new()
+            .fill(&mut key)
+            .expect("the system random generator works");
+        Self::new(&key)
+    }
+
+    /// Seals the value, bound to `field` so it can't be moved to another one
+    pub fn encrypt(&self, field: &str, value: &str) -> String {
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .expect("the system random generator works");
+        let mut sealed = value.as_bytes().to_vec();
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(field.as_bytes()),
+                &mut sealed,
+            )
+            .expect("values fit in a single message");
+        let mut encoded = nonce.to_vec();
+        encoded.extend(sealed);
+        format!("{PREFIX}{}", BASE64.encode(encoded))
+    }
+
+    /// Opens a value from [`SecretsKey::encrypt`], None when the key or field isn't the one it
+    /// was sealed with
+    pub fn decrypt(&self, field: &str, value: &str) -> Result<Option<String>, CryptoError> {
+        let encoded = value
+            .strip_prefix(PREFIX)
+            .and_then(|encoded| BASE64.decode(encoded).ok())
+            .filter(|encoded| encoded.len() >= NONCE_LEN)
+            .ok_or(CryptoError::Malformed)?;
+        let (nonce, sealed) = encoded.split_at(NONCE_LEN);
+        let nonce =
+            Nonce::try_assume_unique_for_key(nonce).map_err(|_| CryptoError::Malformed)?;
+        let mut sealed = sealed.to_vec();
+        let Ok(opened) =
+            self.key
+                .open_in_place(nonce, Aad::from(field.as_bytes()), &mut sealed)
+        else {
+            return Ok(None);
+        };
+        String::from_utf8(opened.to_vec())
+            .map(Some)
+            .map_err(|_| CryptoError::Malformed)
+    }
+}
+
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(PREFIX)
+}
+
+pub fn new_salt() -> Vec<u8> {
+    let mut salt = vec![0; SALT_LEN];
+    SystemRandom::new()
+        .fill(&mut salt)
+        .expect("the system random generator works");
+    salt
+}
+
+pub fn keyfile_path() -> Result<PathBuf, CryptoError> {
+    if let Some(path) = env::var_os(KEYFILE_ENV) {
+        return Ok(PathBuf::from(path));
+    }
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .ok_or(CryptoError::NoKeyfilePath)?;
+    Ok(config.join("research").join("secrets.key"))
+}
+
+/// Random bytes of the keyfile, stored as base64 so it can be pasted into a CI secret
+fn read_keyfile(create: bool) -> Result<Vec<u8>, CryptoError> {
+    let path = keyfile_path()?;
+    let error = |e| CryptoError::Keyfile(path.clone(), e);
+    match fs::read_to_string(&path) {
+        Ok(contents) => BASE64
+            .decode(contents.trim())
+            .ok()
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| {
+                error(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "expected the base64 of a random key",
+                ))
+            }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound && create => {
+            let mut key = [0; KEYFILE_LEN];
+            SystemRandom::new()
+                .fill(&mut key)
+                .expect("the system random generator works");
+            let contents = BASE64.encode(key);
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(error)?;
+            }
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create_new(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+            let mut file = options.open(&path).map_err(error)?;
+            writeln!(file, "{contents}").map_err(error)?;
+            eprintln!("Created the keyfile for the secrets: {}", path.display());
+            Ok(key.to_vec())
+        }
+        Err(e) => Err(error(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::{
+        is_encrypted, new_salt, CryptoError, KeySource, SecretsKey, BASE64, KEYFILE_ENV,
+        PASSPHRASE_ENV, PREFIX,
+    };
+    use base64::Engine;
+
+    #[test]
+    fn values_round_trip() {
+        let key = SecretsKey::random();
+        let first = key.encrypt("field", "token");
+        let second = key.encrypt("field", "token");
+        assert!(is_encrypted(&first));
+        assert!(!first.contains("token"));
+        // A new nonce every time
+        assert_ne!(first, second);
+        assert_eq!(
+            key.decrypt("field", &first).unwrap().as_deref(),
+            Some("token")
+        );
+        assert_eq!(
+            key.decrypt("field", &second).unwrap().as_deref(),
+            Some("token")
+        );
+        assert_eq!(key.decrypt("other", &first).unwrap(), None);
+        let empty = key.encrypt("field", "");
+        assert_eq!(key.decrypt("field", &empty).unwrap().as_deref(), Some(""));
+    }
+
+    #[test]
+    fn only_the_same_passphrase_decrypts() {
+        let salt = new_salt();
+        let value =
+            SecretsKey::from_passphrase("correct horse", &salt).encrypt("field", "token");
+        let key = SecretsKey::from_passphrase("correct horse", &salt);
+        assert_eq!(
+            key.decrypt("field", &value).unwrap().as_deref(),
+            Some("token")
+        );
+        let wrong = SecretsKey::from_passphrase("battery staple", &salt);
+        assert_eq!(wrong.decrypt("field", &value).unwrap(), None);
+    }
+
+    #[test]
+    fn only_the_same_keyfile_and_salt_decrypt() {
+        let salt = new_salt();
+        let value = SecretsKey::from_keyfile(b"keyfile", &salt).encrypt("field", "token");
+        let key = SecretsKey::from_keyfile(b"keyfile", &salt);
+        assert_eq!(
+            key.decrypt("field", &value).unwrap().as_deref(),
+            Some("token")
+        );
+        for key in [
+            SecretsKey::from_keyfile(b"another keyfile", &salt),
+            SecretsKey::from_keyfile(b"keyfile", &new_salt()),
+        ] {
+            assert_eq!(key.decrypt("field", &value).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn tampered_values_are_rejected() {
+        let key = SecretsKey::random();
+        let value = key.encrypt("field", "token");
+        let mut sealed = BASE64.decode(value.strip_prefix(PREFIX).unwrap()).unwrap();
+        let last = sealed.len() - 1;
+        sealed[last] ^= 1;
+        let flipped = format!("{PREFIX}{}", BASE64.encode(&sealed));
+        assert_eq!(key.decrypt("field", &flipped).unwrap(), None);
+
+        let truncated = format!("{PREFIX}{}", BASE64.encode(&sealed[..4]));
+        for malformed in [
+            "token",
+            "enc1:",
+            "enc1:not base64!",
+            truncated.as_str(),
+            &value[PREFIX.len()..],
+        ] {
+            assert!(
+                matches!(key.decrypt("field", malformed), Err(CryptoError::Malformed)),
+                "{malformed}"
+            );
+        }
+    }
+
+    #[test]
+    fn passphrase_and_keyfile_come_from_the_environment() {
+        // The only test reading the variables, the others build their keys directly
+        let dir = env::temp_dir().join(format!("research-{}-keyfile", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let keyfile = dir.join("secrets.key");
+        env::set_var(KEYFILE_ENV, &keyfile);
+        env::remove_var(PASSPHRASE_ENV);
+
+        assert_eq!(KeySource::from_env(), KeySource::Keyfile);
+        assert!(matches!(
+            SecretsKey::derive(KeySource::Passphrase, &new_salt(), true),
+            Err(CryptoError::MissingPassphrase)
+        ));
+        assert!(matches!(
+            SecretsKey::derive(KeySource::Keyfile, &new_salt(), false),
+            Err(CryptoError::Keyfile(..))
+        ));
+
+        let salt = new_salt();
+        let value = SecretsKey::derive(KeySource::Keyfile, &salt, true)
+            .unwrap()
+            .encrypt("field", "token");
+        let contents = fs::read_to_string(&keyfile).unwrap();
+        let key = BASE64.decode(contents.trim()).unwrap();
+        assert_eq!(key.len(), 32);
+        let decrypted = SecretsKey::derive(KeySource::Keyfile, &salt, false)
+            .unwrap()
+            .decrypt("field", &value)
+            .unwrap();
+        assert_eq!(decrypted.as_deref(), Some("token"));
+        assert_eq!(
+            SecretsKey::from_keyfile(&key, &salt)
+                .decrypt("field", &value)
+                .unwrap()
+                .as_deref(),
+            Some("token")
+        );
+
+        fs::write(&keyfile, "not base64!\n").unwrap();
+        assert!(matches!(
+            SecretsKey::derive(KeySource::Keyfile, &salt, true),
+            Err(CryptoError::Keyfile(..))
+        ));
+
+        env::set_var(PASSPHRASE_ENV, "correct horse");
+        assert_eq!(KeySource::from_env(), KeySource::Passphrase);
+        env::remove_var(PASSPHRASE_ENV);
+        env::remove_var(KEYFILE_ENV);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,5 +1,11 @@
+pub mod crypto;
+pub mod netscape;
+pub mod output;
+pub mod pdf;
+pub mod readability;
 pub mod serialize;
 
+use chrono::{DateTime, NaiveDate, NaiveDateTime};
 use std::path::{Path, PathBuf};
 
 pub fn absolute_path(base: impl AsRef<Path>, path: impl AsRef<Path>) -> PathBuf {
@@ -10,3 +16,53 @@ pub fn absolute_path(base: impl AsRef<Path>, path: impl AsRef<Path>) -> PathBuf
         base.as_ref().join(path)
     }
 }
+
+/// Escapes text for use in HTML or XML content and attribute values
+pub fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Lowercase ascii alphanumerics separated by single dashes, e.g. "Machine Learning!" becomes
+/// "machine-learning". Falls back to "untitled" when nothing is left.
+pub fn slugify(s: &str) -> String {
+    let slug = s
+        .to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() {
+        "untitled".to_string()
+    } else {
+        slug
+    }
+}
+
+/// Tags are `/` separated paths, the ancestors of `ml/nlp/transformers` are `ml` and `ml/nlp`
+pub fn tag_ancestors(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices('/').map(move |(i, _)| &tag[..i])
+}
+
+/// Parent of a tag path, None for top level tags
+pub fn tag_parent(tag: &str) -> Option<&str> {
+    tag.rsplit_once('/').map(|(parent, _)| parent)
+}
+
+/// Unix time of an ISO 8601 date or datetime, like 2024-01-31 or 2024-01-31T10:00:00+01:00
+pub fn parse_iso_date(date: &str) -> Option<i64> {
+    let date = date.trim();
+    DateTime::parse_from_rfc3339(date)
+        .map(|date| date.timestamp())
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(date, "%Y-%m-%dT%H:%M:%S")
+                .map(|date| date.and_utc().timestamp())
+        })
+        .or_else(|_| {
+            NaiveDate::parse_from_str(date.get(..10).unwrap_or(date), "%Y-%m-%d")
+                .map(|date| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp())
+        })
+        .ok()
+}
@@ -0,0 +1,225 @@
+use super::escape_html;
+use scraper::{ElementRef, Html, Selector};
+use std::io::{self, Write};
+
+/// A bookmark in the Netscape bookmark file format used by browsers, Pinboard, Raindrop
+/// and Pocket's HTML export
+/// @refer https://learn.microsoft.com/en-us/previous-versions/windows/internet-explorer/ie-developer/platform-apis/aa753582(v=vs.85)
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub url: String,
+    pub title: String,
+    pub description: Option<String>,
+    /// Unix time
+    pub add_date: Option<i64>,
+    /// `TAGS` attribute followed by the names of the enclosing folders
+    pub tags: Vec<String>,
+}
+
+/// Parses every link in the file, nested `<DL>` folders are mapped to tags.
+/// Browser root folders like the bookmarks toolbar are skipped.
+pub fn parse_bookmarks(html: &str) -> Vec<Bookmark> {
+    let document = Html::parse_document(html);
+    let link_selector = Selector::parse("a[href]").unwrap();
+
+    document
+        .select(&link_selector)
+        .filter_map(|link| {
+            let url = link.value().attr("href")?.trim();
+            if url.is_empty() || url.starts_with("place:") || url.starts_with("javascript:") {
+                return None;
+            }
+
+            let mut tags: Vec<String> = link
+                .value()
+                .attr("tags")
+                .map(|tags| {
+                    tags.split(',')
+                        .map(str::trim)
+                        .filter(|tag| !tag.is_empty())
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            // html5ever nests each folder's <DL> inside the <DT> holding its <H3>
+            let mut folders = link
+                .ancestors()
+                .filter_map(ElementRef::wrap)
+                .filter(|el| el.value().name() == "dt")
+                .filter_map(|dt| {
+                    dt.children()
+                        .filter_map(ElementRef::wrap)
+                        .find(|el| el.value().name() == "h3")
+                })
+                .filter(|h3| h3.value().attr("personal_toolbar_folder").is_none())
+                .map(|h3| h3.text().collect::<String>().trim().to_string())
+                .filter(|folder| !folder.is_empty())
+                .collect::<Vec<_>>();
+            folders.reverse();
+            for folder in folders {
+                if !tags.contains(&folder) {
+                    tags.push(folder);
+                }
+            }
+
+            // The description is the <DD> following the link's <DT>
+            let description = link
+                .parent()
+                .and_then(ElementRef::wrap)
+                .filter(|dt| dt.value().name() == "dt")
+                .and_then(|dt| dt.next_siblings().find_map(ElementRef::wrap))
+                .filter(|el| el.value().name() == "dd")
+                .map(|dd| dd.text().collect::<String>().trim().to_string())
+                .filter(|description| !description.is_empty());
+
+            Some(Bookmark {
+                url: url.to_string(),
+                title: link.text().collect::<String>().trim().to_string(),
+                description,
+                add_date: link
+                    .value()
+                    .attr("add_date")
+                    .and_then(|date| date.trim().parse().ok()),
+                tags,
+            })
+        })
+        .collect()
+}
+
+/// Writes the bookmarks as a single flat list, tags are kept in the `TAGS` attribute
+pub fn write_bookmarks(writer: &mut dyn Write, bookmarks: &[Bookmark]) -> io::Result<()> {
+    writeln!(writer, "<!DOCTYPE NETSCAPE-Bookmark-file-1>")?;
+    writeln!(writer, "<!-- This is an automatically generated file.")?;
+    writeln!(writer, "     It will be read and overwritten.")?;
+    writeln!(writer, "     DO NOT EDIT! -->")?;
+    writeln!(
+        writer,
+        "<META HTTP-EQUIV=\"Content-Type\" CONTENT=\"text/html; charset=UTF-8\">"
+    )?;
+    writeln!(writer, "<TITLE>Bookmarks</TITLE>")?;
+    writeln!(writer, "<H1>Bookmarks</H1>")?;
+    writeln!(writer, "<DL><p>")?;
+    for bookmark in bookmarks {
+        write!(writer, "    <DT><A HREF=\"{}\"", escape_html(&bookmark.url))?;
+        if let Some(add_date) = bookmark.add_date {
+            write!(writer, " ADD_DATE=\"{add_date}\"")?;
+        }
+        if !bookmark.tags.is_empty() {
+            write!(
+                writer,
+                " TAGS=\"{}\"",
+                escape_html(&bookmark.tags.join(","))
+            )?;
+        }
+        writeln!(writer, ">{}</A>", escape_html(&bookmark.title))?;
+        if let Some(description) = &bookmark.description {
+            writeln!(writer, "    <DD>{}", escape_html(description))?;
+        }
+    }
+    writeln!(writer, "</DL><p>")?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_bookmarks, write_bookmarks, Bookmark};
+
+    /// Shaped like a Firefox export, with Pinboard's TAGS attribute on one link
+    const EXPORT: &str = r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks Menu</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1700000000" PERSONAL_TOOLBAR_FOLDER="true">Bookmarks Toolbar</H3>
+    <DL><p>
+        <DT><A HREF="https://toolbar.example/" ADD_DATE="1700000001">Toolbar link</A>
+        <DT><A HREF="place:sort=8&maxResults=10">Most Visited</A>
+    </DL><p>
+    <DT><H3>Research</H3>
+    <DL><p>
+        <DT><H3>ml</H3>
+        <DL><p>
+            <DT><A HREF="https://nested.example/paper" ADD_DATE="1700000002" TAGS="papers, ml">Attention &amp; more</A>
+            <DD>Read the appendix
+        </DL><p>
+        <DT><A HREF="https://research.example/">Research link</A>
+    </DL><p>
+    <DT><A HREF="javascript:alert(1)">Bookmarklet</A>
+    <DT><A HREF="https://top.example/" ADD_DATE="not a date">  Top link  </A>
+</DL><p>
+"#;
+
+    /// Url, title, description, date and tags
+    type Summary<'a> = (&'a str, &'a str, Option<&'a str>, Option<i64>, Vec<&'a str>);
+
+    fn summary(bookmarks: &[Bookmark]) -> Vec<Summary<'_>> {
+        bookmarks
+            .iter()
+            .map(|bookmark| {
+                (
+                    bookmark.url.as_str(),
+                    bookmark.title.as_str(),
+                    bookmark.description.as_deref(),
+                    bookmark.add_date,
+                    bookmark.tags.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn folders_tags_and_descriptions_are_parsed() {
+        let bookmarks = parse_bookmarks(EXPORT);
+        assert_eq!(
+            summary(&bookmarks),
+            [
+                (
+                    "https://toolbar.example/",
+                    "Toolbar link",
+                    None,
+                    Some(1700000001),
+                    vec![]
+                ),
+                (
+                    "https://nested.example/paper",
+                    "Attention & more",
+                    Some("Read the appendix"),
+                    Some(1700000002),
+                    vec!["papers", "ml", "Research"]
+                ),
+                (
+                    "https://research.example/",
+                    "Research link",
+                    None,
+                    None,
+                    vec!["Research"]
+                ),
+                ("https://top.example/", "Top link", None, None, vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn written_bookmarks_parse_back() {
+        let bookmarks = parse_bookmarks(EXPORT);
+        let mut written = Vec::new();
+        write_bookmarks(&mut written, &bookmarks).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written.contains(r#"TAGS="papers,ml,Research">Attention &amp; more</A>"#));
+        assert_eq!(summary(&parse_bookmarks(&written)), summary(&bookmarks));
+
+        let escaped = [Bookmark {
+            url: "https://example.com/?a=1&b=\"2\"".to_string(),
+            title: "<script>alert(1)</script>".to_string(),
+            description: Some("Tom & Jerry's <notes>".to_string()),
+            add_date: Some(0),
+            tags: vec!["c++".to_string(), "\"quoted\"".to_string()],
+        }];
+        let mut written = Vec::new();
+        write_bookmarks(&mut written, &escaped).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(!written.contains("<script>"));
+        assert_eq!(summary(&parse_bookmarks(&written)), summary(&escaped));
+    }
+}
@@ -0,0 +1,355 @@
+use crate::db::ResearchItem;
+use chrono::{Local, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::io::{self, Write};
+
+/// Fields of `research list`, in the order of the JSON output
+pub const FIELDS: &[&str] = &[
+    "id",
+    "uri",
+    "title",
+    "excerpt",
+    "time_added",
+    "favorite",
+    "lang",
+    "notes",
+    "status",
+    "time_read",
+    "author",
+    "published",
+    "site_name",
+    "canonical_url",
+    "image_url",
+    "domain",
+    "tags",
+];
+
+/// Shown by the table and markdown formats when no fields are given
+pub const SUMMARY_FIELDS: &[&str] = &["id", "title", "domain", "tags", "time_added"];
+
+/// Unix times, shown as dates by the table and markdown formats
+const TIME_FIELDS: &[&str] = &["time_added", "time_read", "published"];
+
+/// Longer table cells are cut with an ellipsis
+const MAX_CELL_WIDTH: usize = 60;
+
+#[derive(Serialize)]
+pub struct ListedItem {
+    #[serde(flatten)]
+    pub item: ResearchItem,
+    pub domain: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl ListedItem {
+    pub fn new(item: ResearchItem, tags: Vec<String>) -> Self {
+        let domain = url::Url::parse(&item.uri).ok().and_then(|url| {
+            url.host_str()
+                .map(|host| host.trim_start_matches("www.").to_string())
+        });
+        Self { item, domain, tags }
+    }
+}
+
+/// Only the given fields of every item, all of [`FIELDS`] when `fields` is empty
+fn records(
+    items: &[ListedItem],
+    fields: &[&str],
+) -> Result<Vec<Map<String, Value>>, serde_json::Error> {
+    let fields = if fields.is_empty() { FIELDS } else { fields };
+    items
+        .iter()
+        .map(|item| {
+            let Value::Object(mut record) = serde_json::to_value(item)? else {
+                unreachable!("items serialize to objects");
+            };
+            Ok(fields
+                .iter()
+                .map(|field| {
+                    (
+                        field.to_string(),
+                        record.remove(*field).unwrap_or(Value::Null),
+                    )
+                })
+                .collect())
+        })
+        .collect()
+}
+
+/// A JSON array, or one object per line with `lines` set
+pub fn write_json(
+    out: &mut impl Write,
+    items: &[ListedItem],
+    fields: &[&str],
+    lines: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let records = records(items, fields)?;
+    if lines {
+        for record in records {
+            serde_json::to_writer(&mut *out, &record)?;
+            writeln!(out)?;
+        }
+    } else {
+        serde_json::to_writer_pretty(&mut *out, &records)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Comma or tab separated values with a header row, times are kept as unix times
+pub fn write_delimited(
+    out: &mut impl Write,
+    items: &[ListedItem],
+    fields: &[&str],
+    delimiter: u8,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fields = if fields.is_empty() { FIELDS } else { fields };
+    let mut wtr = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        // Fields are never quoted in TSV, tabs and newlines in the values are replaced instead
+        .quote_style(if delimiter == b'\t' {
+            csv::QuoteStyle::Never
+        } else {
+            csv::QuoteStyle::Necessary
+        })
+        .from_writer(out);
+    wtr.write_record(fields)?;
+    for record in records(items, fields)? {
+        wtr.write_record(fields.iter().map(|field| {
+            let cell = cell(&record[*field]);
+            if delimiter == b'\t' {
+                cell.replace(['\t', '\n', '\r'], " ")
+            } else {
+                cell
+            }
+        }))?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Columns padded to the same width
+pub fn write_table(
+    out: &mut impl Write,
+    items: &[ListedItem],
+    fields: &[&str],
+    timezone: Option<Tz>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fields = if fields.is_empty() {
+        SUMMARY_FIELDS
+    } else {
+        fields
+    };
+    let rows = human_rows(items, fields, timezone)?;
+    let widths = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([field.len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let write_row = |out: &mut dyn Write, row: &[&str]| -> io::Result<()> {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ");
+        writeln!(out, "{}", line.trim_end())
+    };
+    write_row(out, fields)?;
+    let rule = widths
+        .iter()
+        .map(|width| "-".repeat(*width))
+        .collect::<Vec<_>>();
+    write_row(out, &rule.iter().map(String::as_str).collect::<Vec<_>>())?;
+    for row in &rows {
+        write_row(out, &row.iter().map(String::as_str).collect::<Vec<_>>())?;
+    }
+    Ok(())
+}
+
+/// A GitHub flavored markdown table
+pub fn write_markdown(
+    out: &mut impl Write,
+    items: &[ListedItem],
+    fields: &[&str],
+    timezone: Option<Tz>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let fields = if fields.is_empty() {
+        SUMMARY_FIELDS
+    } else {
+        fields
+    };
+    writeln!(out, "| {} |", fields.join(" | "))?;
+    writeln!(out, "|{}", " --- |".repeat(fields.len()))?;
+    for row in human_rows(items, fields, timezone)? {
+        let row = row
+            .iter()
+            .map(|cell| cell.replace('|', "\\|"))
+            .collect::<Vec<_>>();
+        writeln!(out, "| {} |", row.join(" | "))?;
+    }
+    Ok(())
+}
+
+/// Cells on a single line, with dates instead of unix times and long values cut
+fn human_rows(
+    items: &[ListedItem],
+    fields: &[&str],
+    timezone: Option<Tz>,
+) -> Result<Vec<Vec<String>>, serde_json::Error> {
+    Ok(records(items, fields)?
+        .iter()
+        .map(|record| {
+            fields
+                .iter()
+                .map(|field| {
+                    let value = &record[*field];
+                    let cell = match value.as_i64() {
+                        Some(time) if TIME_FIELDS.contains(field) => {
+                            format_date(time, timezone)
+                        }
+                        _ => cell(value),
+                    };
+                    truncate(&cell.split_whitespace().collect::<Vec<_>>().join(" "))
+                })
+                .collect()
+        })
+        .collect())
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(values) => values.iter().map(cell).collect::<Vec<_>>().join(", "),
+        value => value.to_string(),
+    }
+}
+
+fn format_date(time: i64, timezone: Option<Tz>) -> String {
+    let Some(datetime) = Utc.timestamp_opt(time, 0).single() else {
+        return time.to_string();
+    };
+    match timezone {
+        Some(tz) => datetime.with_timezone(&tz).format("%Y-%m-%d").to_string(),
+        None => datetime
+            .with_timezone(&Local)
+            .format("%Y-%m-%d")
+            .to_string(),
+    }
+}
+
+fn truncate(cell: &str) -> String {
+    if cell.chars().count() <= MAX_CELL_WIDTH {
+        return cell.to_string();
+    }
+    let cut = cell.chars().take(MAX_CELL_WIDTH - 1).collect::<String>();
+    format!("{}…", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{write_delimited, write_json, write_markdown, write_table, ListedItem, FIELDS};
+    use crate::db::ResearchItem;
+    use chrono_tz::Tz;
+    use serde_json::{Map, Value};
+
+    fn items() -> Vec<ListedItem> {
+        vec![
+            ListedItem::new(
+                ResearchItem {
+                    id: Some(1),
+                    excerpt: "line one\nline two, \"quoted\"".to_string(),
+                    time_added: 1706659200,
+                    favorite: true,
+                    ..ResearchItem::test("https://www.example.com/a", "Tabs\tand | pipes")
+                },
+                vec!["rust".to_string(), "async".to_string()],
+            ),
+            ListedItem::new(
+                ResearchItem {
+                    id: Some(2),
+                    ..ResearchItem::test("not a url", &"long ".repeat(20))
+                },
+                Vec::new(),
+            ),
+        ]
+    }
+
+    fn output(
+        write: impl FnOnce(&mut Vec<u8>, &[ListedItem]) -> Result<(), Box<dyn std::error::Error>>,
+    ) -> String {
+        let mut out = Vec::new();
+        write(&mut out, &items()).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn json_keeps_the_selected_fields() {
+        let lines =
+            output(|out, items| write_json(out, items, &["id", "domain", "tags"], true));
+        assert_eq!(
+            lines,
+            "{\"id\":1,\"domain\":\"example.com\",\"tags\":[\"rust\",\"async\"]}\n\
+            {\"id\":2,\"domain\":null,\"tags\":[]}\n"
+        );
+
+        let pretty = output(|out, items| write_json(out, items, &[], false));
+        let records: Vec<Map<String, Value>> = serde_json::from_str(&pretty).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].keys().collect::<Vec<_>>(), FIELDS);
+        assert_eq!(records[0]["excerpt"], "line one\nline two, \"quoted\"");
+        assert_eq!(records[1]["favorite"], false);
+    }
+
+    #[test]
+    fn csv_quotes_and_tsv_flattens_values() {
+        let fields = ["id", "title", "excerpt", "tags"];
+        let csv = output(|out, items| write_delimited(out, items, &fields, b','));
+        assert_eq!(
+            csv,
+            "id,title,excerpt,tags\n\
+            1,Tabs\tand | pipes,\"line one\nline two, \"\"quoted\"\"\",\"rust, async\"\n\
+            2,long long long long long long long long long long long long long long long long long long long long ,,\n"
+        );
+        let tsv = output(|out, items| write_delimited(out, items, &fields, b'\t'));
+        assert_eq!(
+            tsv,
+            "id\ttitle\texcerpt\ttags\n\
+            1\tTabs and | pipes\tline one line two, \"quoted\"\trust, async\n\
+            2\tlong long long long long long long long long long long long long long long long long long long long \t\t\n"
+        );
+        let all = output(|out, items| write_delimited(out, items, &[], b','));
+        assert_eq!(all.lines().next().unwrap(), FIELDS.join(","));
+    }
+
+    #[test]
+    fn table_and_markdown_show_dates_and_cut_long_cells() {
+        let table = output(|out, items| write_table(out, items, &[], Some(Tz::UTC)));
+        assert_eq!(
+            table,
+            "id  title                                                         domain       tags         time_added\n\
+            --  ------------------------------------------------------------  -----------  -----------  ----------\n\
+            1   Tabs and | pipes                                              example.com  rust, async  2024-01-31\n\
+            2   long long long long long long long long long long long long…                            1970-01-01\n"
+        );
+        let fields = ["id", "title", "favorite", "time_read"];
+        let markdown = output(|out, items| write_markdown(out, items, &fields, Some(Tz::UTC)));
+        assert_eq!(
+            markdown,
+            "| id | title | favorite | time_read |\n\
+            | --- | --- | --- | --- |\n\
+            | 1 | Tabs and \\| pipes | true |  |\n\
+            | 2 | long long long long long long long long long long long long… | false |  |\n"
+        );
+    }
+}
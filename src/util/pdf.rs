@@ -0,0 +1,295 @@
+use super::parse_iso_date;
+use chrono::{TimeZone, Utc};
+use lopdf::{decode_text_string, Dictionary, Document};
+use std::panic;
+
+/// Length of the excerpt taken from the first page
+const EXCERPT_LENGTH: usize = 500;
+
+#[derive(Debug, Default)]
+pub struct PdfMetadata {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    /// Unix time the document was created
+    pub published: Option<i64>,
+    pub lang: Option<String>,
+    /// Start of the first page's text
+    pub excerpt: Option<String>,
+    /// Text of every page, pages separated by blank lines
+    pub text: Option<String>,
+}
+
+/// Reads the XMP metadata, falling back to the info dictionary, and the text of the document.
+/// Returns None when the bytes aren't a readable PDF.
+pub fn extract_pdf(bytes: &[u8]) -> Option<PdfMetadata> {
+    let document = Document::load_mem(bytes).ok()?;
+    let info = info_dictionary(&document);
+    let xmp = xmp_metadata(&document);
+    let info_string = |key: &[u8]| {
+        info.and_then(|info| info.get(key).ok())
+            .and_then(|value| document.dereference(value).ok())
+            .and_then(|(_, value)| decode_text_string(value).ok())
+            .and_then(non_empty)
+    };
+
+    let title = xmp
+        .as_deref()
+        .and_then(|xmp| xmp_value(xmp, "dc:title"))
+        .or_else(|| info_string(b"Title"));
+    let author = xmp
+        .as_deref()
+        .and_then(|xmp| xmp_value(xmp, "dc:creator"))
+        .or_else(|| info_string(b"Author"));
+    let published = xmp
+        .as_deref()
+        .and_then(|xmp| xmp_value(xmp, "xmp:CreateDate"))
+        .and_then(|date| parse_iso_date(&date))
+        .or_else(|| info_string(b"CreationDate").and_then(|date| parse_pdf_date(&date)));
+    let lang = document
+        .catalog()
+        .ok()
+        .and_then(|catalog| catalog.get(b"Lang").ok())
+        .and_then(|lang| decode_text_string(lang).ok())
+        .and_then(non_empty);
+
+    // pdf-extract panics on some malformed fonts, the metadata is still worth keeping
+    let pages = panic::catch_unwind(|| pdf_extract::extract_text_from_mem_by_pages(bytes))
+        .ok()
+        .and_then(Result::ok)
+        .unwrap_or_default();
+    let excerpt = pages.first().map(|page| {
+        page.split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .chars()
+            .take(EXCERPT_LENGTH)
+            .collect::<String>()
+    });
+    let text = pages
+        .iter()
+        .map(|page| page.trim())
+        .filter(|page| !page.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    Some(PdfMetadata {
+        title,
+        author,
+        published,
+        lang,
+        excerpt: excerpt.and_then(non_empty),
+        text: non_empty(text),
+    })
+}
+
+fn info_dictionary(document: &Document) -> Option<&Dictionary> {
+    let info = document.trailer.get(b"Info").ok()?;
+    let (_, info) = document.dereference(info).ok()?;
+    info.as_dict().ok()
+}
+
+fn xmp_metadata(document: &Document) -> Option<String> {
+    let metadata = document.catalog().ok()?.get(b"Metadata").ok()?;
+    let (_, metadata) = document.dereference(metadata).ok()?;
+    let stream = metadata.as_stream().ok()?;
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    String::from_utf8(content).ok()
+}
+
+/// Text of the first `<rdf:li>` of the element, or of the element itself
+fn xmp_value(xmp: &str, name: &str) -> Option<String> {
+    let start = xmp.find(&format!("<{name}"))?;
+    let end = start + xmp[start..].find(&format!("</{name}>"))?;
+    let element = &xmp[start..end];
+    let inner = match element.find("<rdf:li") {
+        Some(li) => {
+            let li = &element[li..];
+            let li_end = li.find("</rdf:li>").unwrap_or(li.len());
+            &li[..li_end]
+        }
+        None => element,
+    };
+    let text = &inner[inner.find('>')? + 1..];
+    non_empty(unescape_xml(text))
+}
+
+fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+fn non_empty(s: String) -> Option<String> {
+    let s = s.trim();
+    (!s.is_empty()).then(|| s.to_string())
+}
+
+/// PDF dates look like D:20240131100000+01'00', everything after the year is optional
+fn parse_pdf_date(date: &str) -> Option<i64> {
+    let digits = date
+        .trim_start_matches("D:")
+        .chars()
+        .take_while(char::is_ascii_digit)
+        .collect::<String>();
+    let part = |range: std::ops::Range<usize>, default: u32| {
+        digits
+            .get(range)
+            .and_then(|part| part.parse().ok())
+            .unwrap_or(default)
+    };
+    let year = digits.get(0..4)?.parse().ok()?;
+    // The timezone is ignored, the day is what matters
+    Utc.with_ymd_and_hms(
+        year,
+        part(4..6, 1),
+        part(6..8, 1),
+        part(8..10, 0),
+        part(10..12, 0),
+        part(12..14, 0),
+    )
+    .single()
+    .map(|date| date.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_pdf, parse_pdf_date, xmp_value};
+    use lopdf::content::{Content, Operation};
+    use lopdf::{dictionary, Document, Object, Stream, StringFormat};
+
+    const XMP: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about="" xmlns:dc="http://purl.org/dc/elements/1.1/"
+        xmlns:xmp="http://ns.adobe.com/xap/1.0/">
+      <dc:title><rdf:Alt><rdf:li xml:lang="x-default">Types &amp; Programming Languages</rdf:li></rdf:Alt></dc:title>
+      <dc:creator><rdf:Seq><rdf:li>Benjamin Pierce</rdf:li><rdf:li>Someone Else</rdf:li></rdf:Seq></dc:creator>
+      <xmp:CreateDate>2024-01-31T10:00:00+01:00</xmp:CreateDate>
+      <dc:description><rdf:Alt><rdf:li xml:lang="x-default">  </rdf:li></rdf:Alt></dc:description>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    /// A one page document with the info dictionary, and the XMP packet when given
+    fn pdf(xmp: Option<&str>) -> Vec<u8> {
+        let mut document = Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let font_id = document.add_object(dictionary! {
+            "Type" => "Font",
+            "Subtype" => "Type1",
+            "BaseFont" => "Helvetica",
+        });
+        let content = Content {
+            operations: vec![
+                Operation::new("BT", vec![]),
+                Operation::new("Tf", vec!["F1".into(), 12.into()]),
+                Operation::new("Td", vec![72.into(), 720.into()]),
+                Operation::new(
+                    "Tj",
+                    vec![Object::string_literal("Hello from the first page")],
+                ),
+                Operation::new("ET", vec![]),
+            ],
+        };
+        let content_id =
+            document.add_object(Stream::new(dictionary! {}, content.encode().unwrap()));
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "Contents" => content_id,
+            "Resources" => dictionary! { "Font" => dictionary! { "F1" => font_id } },
+            "MediaBox" => vec![0.into(), 0.into(), 612.into(), 792.into()],
+        });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let mut catalog = dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Lang" => Object::String(b"de-DE".to_vec(), StringFormat::Literal),
+        };
+        if let Some(xmp) = xmp {
+            let metadata = Stream::new(
+                dictionary! { "Type" => "Metadata", "Subtype" => "XML" },
+                xmp.as_bytes().to_vec(),
+            );
+            catalog.set("Metadata", document.add_object(metadata));
+        }
+        let catalog_id = document.add_object(catalog);
+        let info_id = document.add_object(dictionary! {
+            "Title" => Object::string_literal("Info title"),
+            "Author" => Object::string_literal("Info author"),
+            "CreationDate" => Object::string_literal("D:20230502090000+02'00'"),
+        });
+        document.trailer.set("Root", catalog_id);
+        document.trailer.set("Info", info_id);
+        let mut bytes = Vec::new();
+        document.save_to(&mut bytes).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn xmp_values_are_read() {
+        assert_eq!(
+            xmp_value(XMP, "dc:title").as_deref(),
+            Some("Types & Programming Languages")
+        );
+        assert_eq!(
+            xmp_value(XMP, "dc:creator").as_deref(),
+            Some("Benjamin Pierce")
+        );
+        assert_eq!(
+            xmp_value(XMP, "xmp:CreateDate").as_deref(),
+            Some("2024-01-31T10:00:00+01:00")
+        );
+        assert_eq!(xmp_value(XMP, "dc:description"), None);
+        assert_eq!(xmp_value(XMP, "dc:subject"), None);
+    }
+
+    #[test]
+    fn pdf_dates_are_parsed() {
+        assert_eq!(parse_pdf_date("D:20240131100000+01'00'"), Some(1706695200));
+        assert_eq!(parse_pdf_date("D:20240131"), Some(1706659200));
+        assert_eq!(parse_pdf_date("2024"), Some(1704067200));
+        assert_eq!(parse_pdf_date("D:20241341"), None);
+        assert_eq!(parse_pdf_date("yesterday"), None);
+    }
+
+    #[test]
+    fn xmp_metadata_is_preferred_over_the_info_dictionary() {
+        let metadata = extract_pdf(&pdf(Some(XMP))).unwrap();
+        assert_eq!(
+            metadata.title.as_deref(),
+            Some("Types & Programming Languages")
+        );
+        assert_eq!(metadata.author.as_deref(), Some("Benjamin Pierce"));
+        // 2024-01-31T09:00:00Z
+        assert_eq!(metadata.published, Some(1706691600));
+        assert_eq!(metadata.lang.as_deref(), Some("de-DE"));
+        assert_eq!(
+            metadata.excerpt.as_deref(),
+            Some("Hello from the first page")
+        );
+        assert_eq!(metadata.text.as_deref(), Some("Hello from the first page"));
+    }
+
+    #[test]
+    fn info_dictionary_is_the_fallback() {
+        let metadata = extract_pdf(&pdf(None)).unwrap();
+        assert_eq!(metadata.title.as_deref(), Some("Info title"));
+        assert_eq!(metadata.author.as_deref(), Some("Info author"));
+        // The timezone is ignored
+        assert_eq!(metadata.published, Some(1683018000));
+        assert!(extract_pdf(b"<html>not a pdf</html>").is_none());
+    }
+}
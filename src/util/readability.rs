@@ -0,0 +1,400 @@
+use super::escape_html;
+use scraper::{ElementRef, Html, Node, Selector};
+use std::collections::HashMap;
+use url::Url;
+
+/// Main article body of a page, without the navigation, ads and comments around it
+#[derive(Debug, Clone)]
+pub struct ArticleContent {
+    /// Paragraphs separated by blank lines
+    pub text: String,
+    /// Only whitelisted tags and attributes are kept, so it is safe to embed
+    pub html: String,
+}
+
+impl ArticleContent {
+    /// Content of documents that only have text, like PDFs
+    pub fn from_text(text: String) -> Self {
+        let html = text
+            .split("\n\n")
+            .map(|paragraph| paragraph.trim())
+            .filter(|paragraph| !paragraph.is_empty())
+            .map(|paragraph| format!("<p>{}</p>", escape_html(paragraph)))
+            .collect();
+        Self { text, html }
+    }
+}
+
+/// Never part of the article, dropped with everything inside them
+const SKIPPED_TAGS: &[&str] = &[
+    "script", "style", "noscript", "template", "iframe", "object", "embed", "svg", "canvas",
+    "nav", "header", "footer", "aside", "form", "button", "input", "select", "textarea",
+];
+
+/// Kept in the sanitized html, any other element is replaced by its children
+const ALLOWED_TAGS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "ul",
+    "ol",
+    "li",
+    "blockquote",
+    "pre",
+    "code",
+    "em",
+    "strong",
+    "b",
+    "i",
+    "a",
+    "img",
+    "br",
+    "hr",
+    "figure",
+    "figcaption",
+    "table",
+    "thead",
+    "tbody",
+    "tr",
+    "th",
+    "td",
+    "dl",
+    "dt",
+    "dd",
+    "sub",
+    "sup",
+];
+
+/// Start a new paragraph in the plain text
+const BLOCK_TAGS: &[&str] = &[
+    "p",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "li",
+    "blockquote",
+    "pre",
+    "figure",
+    "figcaption",
+    "tr",
+    "dt",
+    "dd",
+    "div",
+    "section",
+    "article",
+    "br",
+    "hr",
+    "table",
+    "ul",
+    "ol",
+];
+
+/// Class or id fragments of boilerplate blocks
+const UNLIKELY_NAMES: &[&str] = &[
+    "comment",
+    "sidebar",
+    "footer",
+    "menu",
+    "share",
+    "social",
+    "related",
+    "promo",
+    "advert",
+    "banner",
+    "cookie",
+    "popup",
+    "newsletter",
+    "subscribe",
+];
+
+/// Paragraphs shorter than this are usually captions or link lists
+const MIN_PARAGRAPH_LENGTH: usize = 25;
+
+/// Finds the element holding most of the page's paragraphs (or its `<article>`) and returns its
+/// content, links and images are made absolute using `base_url`. Returns None when the page
+/// has no readable text.
+pub fn extract_article(document: &Html, base_url: Option<&Url>) -> Option<ArticleContent> {
+    let root = find_root(document)?;
+
+    let mut writer = ContentWriter {
+        base_url,
+        html: String::new(),
+        paragraphs: Vec::new(),
+        current: String::new(),
+    };
+    writer.write_children(root);
+    writer.end_paragraph();
+
+    let text = writer.paragraphs.join("\n\n");
+    if text.is_empty() {
+        return None;
+    }
+    Some(ArticleContent {
+        text,
+        html: writer.html,
+    })
+}
+
+fn find_root(document: &Html) -> Option<ElementRef<'_>> {
+    let article_selector = Selector::parse("article, [role='main'], main").unwrap();
+    let articles = document
+        .select(&article_selector)
+        .filter(|article| text_length(*article) >= MIN_PARAGRAPH_LENGTH)
+        .collect::<Vec<_>>();
+    if let Some(article) = articles.into_iter().max_by_key(|a| paragraphs_length(*a)) {
+        return Some(article);
+    }
+
+    // Like readability: every long paragraph scores its parent, and half as much its grandparent
+    let paragraph_selector = Selector::parse("p, pre, blockquote").unwrap();
+    let mut scores = HashMap::new();
+    for paragraph in document.select(&paragraph_selector) {
+        if is_boilerplate(paragraph) {
+            continue;
+        }
+        let length = text_length(paragraph);
+        if length < MIN_PARAGRAPH_LENGTH {
+            continue;
+        }
+        let score = 1.0 + (length as f64 / 100.0).min(3.0);
+        let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+        if let Some(parent) = ancestors.next() {
+            *scores.entry(parent.id()).or_insert(0.0) += score;
+        }
+        if let Some(grandparent) = ancestors.next() {
+            *scores.entry(grandparent.id()).or_insert(0.0) += score / 2.0;
+        }
+    }
+
+    scores
+        .into_iter()
+        .max_by(|(_, a), (_, b)| f64::total_cmp(a, b))
+        .and_then(|(id, _)| document.tree.get(id))
+        .and_then(ElementRef::wrap)
+        .or_else(|| {
+            let body_selector = Selector::parse("body").unwrap();
+            document.select(&body_selector).next()
+        })
+}
+
+fn text_length(element: ElementRef) -> usize {
+    element
+        .text()
+        .map(|text| text.split_whitespace().map(str::len).sum::<usize>())
+        .sum()
+}
+
+fn paragraphs_length(element: ElementRef) -> usize {
+    let paragraph_selector = Selector::parse("p").unwrap();
+    element.select(&paragraph_selector).map(text_length).sum()
+}
+
+fn is_unlikely(element: ElementRef) -> bool {
+    let value = element.value();
+    if value.attr("hidden").is_some() || value.attr("aria-hidden") == Some("true") {
+        return true;
+    }
+    let names = format!(
+        "{} {}",
+        value.attr("class").unwrap_or(""),
+        value.attr("id").unwrap_or("")
+    )
+    .to_lowercase();
+    UNLIKELY_NAMES.iter().any(|name| names.contains(name))
+}
+
+fn is_boilerplate(element: ElementRef) -> bool {
+    element.ancestors().filter_map(ElementRef::wrap).any(|el| {
+        SKIPPED_TAGS.contains(&el.value().name())
+            || (el.value().name() != "body" && is_unlikely(el))
+    })
+}
+
+struct ContentWriter<'a> {
+    base_url: Option<&'a Url>,
+    html: String,
+    paragraphs: Vec<String>,
+    /// Text of the paragraph being written, whitespace collapsed
+    current: String,
+}
+
+impl ContentWriter<'_> {
+    fn write_children(&mut self, element: ElementRef) {
+        for child in element.children() {
+            match child.value() {
+                Node::Text(text) => {
+                    self.html.push_str(&escape_html(text));
+                    self.push_text(text);
+                }
+                Node::Element(_) => {
+                    if let Some(child) = ElementRef::wrap(child) {
+                        self.write_element(child);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn write_element(&mut self, element: ElementRef) {
+        let name = element.value().name();
+        if SKIPPED_TAGS.contains(&name) || is_unlikely(element) {
+            return;
+        }
+        let is_block = BLOCK_TAGS.contains(&name);
+        if is_block {
+            self.end_paragraph();
+        }
+
+        if !ALLOWED_TAGS.contains(&name) {
+            self.write_children(element);
+        } else if name == "img" {
+            if let Some(src) = self.resolve(element.value().attr("src")) {
+                let alt = element.value().attr("alt").unwrap_or("");
+                self.html.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\">",
+                    escape_html(&src),
+                    escape_html(alt)
+                ));
+            }
+        } else if name == "br" || name == "hr" {
+            self.html.push_str(&format!("<{name}>"));
+        } else {
+            match (name, self.resolve(element.value().attr("href"))) {
+                ("a", Some(href)) => self
+                    .html
+                    .push_str(&format!("<a href=\"{}\">", escape_html(&href))),
+                _ => self.html.push_str(&format!("<{name}>")),
+            }
+            self.write_children(element);
+            self.html.push_str(&format!("</{name}>"));
+        }
+
+        if is_block {
+            self.end_paragraph();
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if text.starts_with(char::is_whitespace) && !self.current.ends_with(' ') {
+            self.current.push(' ');
+        }
+        let words = text.split_whitespace().collect::<Vec<_>>();
+        self.current.push_str(&words.join(" "));
+        if text.ends_with(char::is_whitespace) && !words.is_empty() {
+            self.current.push(' ');
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        let paragraph = self.current.trim();
+        if !paragraph.is_empty() {
+            self.paragraphs.push(paragraph.to_string());
+        }
+        self.current.clear();
+    }
+
+    /// Absolute http(s) url, anything else (javascript:, data:) is dropped
+    fn resolve(&self, url: Option<&str>) -> Option<String> {
+        let url = url?.trim();
+        let url = match self.base_url {
+            Some(base) => base.join(url).ok()?,
+            None => Url::parse(url).ok()?,
+        };
+        matches!(url.scheme(), "http" | "https").then(|| url.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_article, ArticleContent};
+    use scraper::Html;
+    use url::Url;
+
+    /// A blog post with the usual navigation, sidebar and comments around it
+    const POST: &str = r#"<!DOCTYPE html>
+<html>
+<head><title>Post</title><script>var tracking = true;</script></head>
+<body>
+  <header><nav><a href="/">Home</a> <a href="/about">About</a></nav></header>
+  <div class="sidebar"><p>Subscribe to the newsletter for more posts like this one every week.</p></div>
+  <article>
+    <h1>Understanding   borrows</h1>
+    <p onclick="steal()">The borrow checker makes sure references never outlive their data.</p>
+    <p>See <a href="/docs/borrows.html">the docs</a> or <a href="javascript:alert(1)">this</a>.</p>
+    <img src="images/diagram.png" alt="A &quot;diagram&quot;">
+    <img src="data:image/png;base64,AA" alt="inline">
+    <div class="share-buttons"><p>Share this post with everyone you know on every network.</p></div>
+    <script>alert("inside")</script>
+  </article>
+  <section id="comments"><p>First! This comment is long enough to look like a paragraph.</p></section>
+  <footer><p>Copyright 2024, all rights reserved by the author of this blog.</p></footer>
+</body>
+</html>"#;
+
+    /// Without an <article>, the paragraphs have to point to the content
+    const UNMARKED: &str = r#"<html><body>
+  <div id="menu"><p>Menu entries that are long enough to count as a paragraph.</p></div>
+  <div class="content">
+    <p>The first paragraph of the story is long enough to be scored.</p>
+    <p>The second paragraph continues the story with even more words.</p>
+    <p>Short</p>
+  </div>
+  <div class="links"><p>A single paragraph of unrelated links below the story.</p></div>
+</body></html>"#;
+
+    #[test]
+    fn article_is_extracted_without_boilerplate() {
+        let base = Url::parse("https://blog.example/posts/borrows").unwrap();
+        let article = extract_article(&Html::parse_document(POST), Some(&base)).unwrap();
+        assert_eq!(
+            article.text,
+            "Understanding borrows\n\n\
+            The borrow checker makes sure references never outlive their data.\n\n\
+            See the docs or this."
+        );
+        assert_eq!(
+            article.html.split_whitespace().collect::<Vec<_>>().join(" "),
+            "<h1>Understanding borrows</h1> \
+            <p>The borrow checker makes sure references never outlive their data.</p> \
+            <p>See <a href=\"https://blog.example/docs/borrows.html\">the docs</a> or <a>this</a>.</p> \
+            <img src=\"https://blog.example/posts/images/diagram.png\" alt=\"A &quot;diagram&quot;\">"
+        );
+    }
+
+    #[test]
+    fn paragraphs_find_the_content_without_an_article() {
+        let article = extract_article(&Html::parse_document(UNMARKED), None).unwrap();
+        assert_eq!(
+            article.text,
+            "The first paragraph of the story is long enough to be scored.\n\n\
+            The second paragraph continues the story with even more words.\n\n\
+            Short"
+        );
+    }
+
+    #[test]
+    fn pages_without_text_have_no_article() {
+        let document = Html::parse_document(
+            "<html><body><nav>Home</nav><script>x()</script></body></html>",
+        );
+        assert!(extract_article(&document, None).is_none());
+    }
+
+    #[test]
+    fn text_content_is_escaped() {
+        let content =
+            ArticleContent::from_text("Fish & <chips>\n\n\n\nSecond page".to_string());
+        assert_eq!(
+            content.html,
+            "<p>Fish &amp; &lt;chips&gt;</p><p>Second page</p>"
+        );
+    }
+}
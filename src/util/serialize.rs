@@ -124,6 +124,23 @@ where
     }
 }
 
+/// Object of the items, Pocket sends `[]` instead of `{}` when there are none
+pub fn map_or_empty_array<'de, D>(
+    deserializer: D,
+) -> Result<serde_json::Map<String, Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Option::<Value>::deserialize(deserializer)? {
+        Some(Value::Object(map)) => Ok(map),
+        Some(Value::Array(array)) if array.is_empty() => Ok(serde_json::Map::new()),
+        None | Some(Value::Null) => Ok(serde_json::Map::new()),
+        Some(other) => Err(de::Error::custom(format!(
+            "expected an object of items, got {other}"
+        ))),
+    }
+}
+
 pub fn to_comma_delimited_string<S>(
     x: &Option<&[&str]>,
     serializer: S,